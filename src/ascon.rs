@@ -0,0 +1,292 @@
+//! Ascon-Hash256 and Ascon-XOF128, from NIST SP 800-232.
+//!
+//! Ascon was picked by NIST for lightweight cryptography: small
+//! microcontrollers and sensors that can't spare the memory or the cycles for
+//! SHA-256 or Keccak. It's a sponge like SHA-3, but a much smaller one -- the
+//! state is five 64-bit words (320 bits), and only 8 bytes of it are the rate.
+//!
+//! Note that SP 800-232 changed the byte order from the Ascon v1.2 submission
+//! (bytes now go into the words little endian) as well as the initial values,
+//! so the digests here won't match the older "Ascon-Hash".
+
+use crate::digest::Digest;
+use crate::utils::bytes_to_hex;
+
+// the constant for round `i` of 12; fewer rounds use the end of the list, like
+// Keccak-p does
+const ROUND_CONSTANTS: [u64; 12] = [
+    0xf0, 0xe1, 0xd2, 0xc3, 0xb4, 0xa5, 0x96, 0x87, 0x78, 0x69, 0x5a, 0x4b,
+];
+
+pub const STATE_WORDS: usize = 5;
+
+const RATE: usize = 8;
+
+/// Ascon-p[rounds], the 320-bit permutation.
+///
+/// Each round adds a constant to the middle word, runs a 5-bit S-box across
+/// all 64 columns of the state at once (a column being one bit from each
+/// word), and then mixes each word with two rotated copies of itself.
+pub fn ascon_p(state: &mut [u64; STATE_WORDS], rounds: usize) {
+    assert!(rounds <= ROUND_CONSTANTS.len());
+
+    for round_constant in ROUND_CONSTANTS[ROUND_CONSTANTS.len() - rounds..].iter() {
+        let [mut x0, mut x1, mut x2, mut x3, mut x4] = *state;
+
+        // constant addition
+        x2 ^= round_constant;
+
+        // substitution layer: the S-box written out as operations on whole words
+        x0 ^= x4;
+        x4 ^= x3;
+        x2 ^= x1;
+        let t0 = !x0 & x1;
+        let t1 = !x1 & x2;
+        let t2 = !x2 & x3;
+        let t3 = !x3 & x4;
+        let t4 = !x4 & x0;
+        x0 ^= t1;
+        x1 ^= t2;
+        x2 ^= t3;
+        x3 ^= t4;
+        x4 ^= t0;
+        x1 ^= x0;
+        x0 ^= x4;
+        x3 ^= x2;
+        x2 = !x2;
+
+        // linear diffusion layer
+        x0 ^= x0.rotate_right(19) ^ x0.rotate_right(28);
+        x1 ^= x1.rotate_right(61) ^ x1.rotate_right(39);
+        x2 ^= x2.rotate_right(1) ^ x2.rotate_right(6);
+        x3 ^= x3.rotate_right(10) ^ x3.rotate_right(17);
+        x4 ^= x4.rotate_right(7) ^ x4.rotate_right(41);
+
+        *state = [x0, x1, x2, x3, x4];
+    }
+}
+
+/// The sponge both functions share: the message goes into the first word 8
+/// bytes at a time, with the full 12-round permutation after every block.
+#[derive(Clone)]
+struct AsconSponge {
+    state: [u64; STATE_WORDS],
+    position: usize,
+    squeezing: bool,
+}
+
+impl AsconSponge {
+    /// The initial value holds the parameters (the algorithm number, rounds,
+    /// rate and output size), and gets run through the permutation once before
+    /// any message goes in.
+    fn new(initial_value: u64) -> Self {
+        let mut state = [initial_value, 0, 0, 0, 0];
+        ascon_p(&mut state, 12);
+
+        AsconSponge {
+            state,
+            position: 0,
+            squeezing: false,
+        }
+    }
+
+    fn xor_byte(&mut self, byte: u8) {
+        self.state[0] ^= u64::from(byte) << (8 * self.position);
+    }
+
+    fn absorb(&mut self, bytes: &[u8]) {
+        assert!(!self.squeezing, "can't absorb once squeezing has started");
+
+        for byte in bytes {
+            self.xor_byte(*byte);
+            self.position += 1;
+
+            if self.position == RATE {
+                ascon_p(&mut self.state, 12);
+                self.position = 0;
+            }
+        }
+    }
+
+    // a single 1 bit after the message; the last block always has room for
+    // it, since a full block gets permuted right away
+    fn pad(&mut self) {
+        self.xor_byte(0x01);
+        ascon_p(&mut self.state, 12);
+        self.position = 0;
+        self.squeezing = true;
+    }
+
+    fn squeeze(&mut self, output: &mut [u8]) {
+        if !self.squeezing {
+            self.pad();
+        }
+
+        for byte in output.iter_mut() {
+            if self.position == RATE {
+                ascon_p(&mut self.state, 12);
+                self.position = 0;
+            }
+
+            *byte = (self.state[0] >> (8 * self.position)) as u8;
+            self.position += 1;
+        }
+    }
+}
+
+const HASH_256_INITIAL_VALUE: u64 = 0x0000_0801_00cc_0002;
+const XOF_128_INITIAL_VALUE: u64 = 0x0000_0800_00cc_0003;
+
+/// Ascon-Hash256: a 256-bit digest with 128-bit security.
+#[derive(Clone)]
+pub struct AsconHash256 {
+    sponge: AsconSponge,
+}
+
+impl Default for AsconHash256 {
+    fn default() -> Self {
+        AsconHash256 {
+            sponge: AsconSponge::new(HASH_256_INITIAL_VALUE),
+        }
+    }
+}
+
+impl Digest for AsconHash256 {
+    const BLOCK_LENGTH: usize = RATE;
+    const OUTPUT_LENGTH: usize = 32;
+
+    type Output = [u8; 32];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.sponge.absorb(bytes);
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let mut output = [0u8; 32];
+        self.sponge.squeeze(&mut output);
+        output
+    }
+}
+
+/// Ascon-XOF128: as much output as you ask for, with 128-bit security.
+///
+/// Squeezing can happen any number of times, and the pieces join up into one
+/// long output -- but nothing can be absorbed once it's started.
+#[derive(Clone)]
+pub struct AsconXof128 {
+    sponge: AsconSponge,
+}
+
+impl Default for AsconXof128 {
+    fn default() -> Self {
+        AsconXof128 {
+            sponge: AsconSponge::new(XOF_128_INITIAL_VALUE),
+        }
+    }
+}
+
+impl AsconXof128 {
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.sponge.absorb(bytes);
+    }
+
+    pub fn squeeze(&mut self, output: &mut [u8]) {
+        self.sponge.squeeze(output);
+    }
+}
+
+pub fn ascon_hash256(raw_message: &str) -> String {
+    bytes_to_hex(&AsconHash256::digest(raw_message.as_bytes()))
+}
+
+/// `output_length` is in bytes.
+pub fn ascon_xof128(raw_message: &str, output_length: usize) -> String {
+    let mut xof = AsconXof128::default();
+    xof.update(raw_message.as_bytes());
+
+    let mut output = vec![0u8; output_length];
+    xof.squeeze(&mut output);
+    bytes_to_hex(&output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_state() {
+        // SP 800-232 lists the state after the first permutation, so
+        // implementations can start from there
+        let sponge = AsconSponge::new(HASH_256_INITIAL_VALUE);
+        assert_eq!(
+            [
+                0x9b1e5494e934d681,
+                0x4bc3a01e333751d2,
+                0xae65396c6b34b81a,
+                0x3c7fd4a4d56a4db3,
+                0x1a5c464906c5976d
+            ],
+            sponge.state
+        );
+
+        let sponge = AsconSponge::new(XOF_128_INITIAL_VALUE);
+        assert_eq!(
+            [
+                0xda82ce768d9447eb,
+                0xcc7ce6c75f1ef969,
+                0xe7508fd780085631,
+                0x0ee0ea53416b58cc,
+                0xe0547524db6f0bde
+            ],
+            sponge.state
+        );
+    }
+
+    #[test]
+    fn test_ascon_hash256() {
+        assert_eq!(
+            "0b3be5850f2f6b98caf29f8fdea89b64a1fa70aa249b8f839bd53baa304d92b2",
+            ascon_hash256("")
+        );
+    }
+
+    #[test]
+    fn test_ascon_streaming() {
+        let message = "The quick brown fox jumps over the lazy dog".repeat(3);
+
+        let mut hasher = AsconHash256::default();
+        for piece in message.as_bytes().chunks(5) {
+            hasher.update(piece);
+        }
+        assert_eq!(ascon_hash256(&message), bytes_to_hex(&hasher.finalize()));
+    }
+
+    #[test]
+    fn test_ascon_xof128() {
+        // LWC_XOF_KAT_128_512.txt from the Ascon reference code, Count = 1
+        assert_eq!(
+            concat!(
+                "473d5e6164f58b39dfd84aacdb8ae42ec2d91fed33388ee0d960d9b3993295c6",
+                "ad77855a5d3b13fe6ad9e6098988373af7d0956d05a8f1665d2c67d1a3ad10ff"
+            ),
+            ascon_xof128("", 64)
+        );
+
+        let long = ascon_xof128("abc", 100);
+        assert_eq!(200, long.len());
+
+        // squeezing in pieces gives the same stream as squeezing all at once
+        let mut xof = AsconXof128::default();
+        xof.update(b"abc");
+        let mut first = [0u8; 3];
+        let mut second = [0u8; 97];
+        xof.squeeze(&mut first);
+        xof.squeeze(&mut second);
+        assert_eq!(long, bytes_to_hex(&first) + &bytes_to_hex(&second));
+
+        // and a shorter output is just a prefix of a longer one
+        assert_eq!(long[..64], ascon_xof128("abc", 32));
+        assert_ne!(ascon_hash256("abc"), ascon_xof128("abc", 32));
+    }
+
+}
@@ -0,0 +1,76 @@
+//! Forges `H(secret || message || glue || extension)` from `H(secret || message)`.
+//!
+//! ```text
+//! length-extension [--hex] <md5|sha1|sha256> <digest in hex> <secret length> <message> <extension>
+//! ```
+//!
+//! With `--hex`, the message and extension are given in hex, for when they
+//! aren't text (say, a message that was itself forged earlier). Prints the
+//! suffix to append to the message (glue padding and extension), the whole
+//! forged message, and the forged digest. The suffix and message are printed
+//! in hex and percent-encoded, since the glue padding is binary.
+
+use sha1::length_extension::{extend, Algorithm};
+use sha1::{bytes_to_hex, hex_to_bytes};
+use std::env;
+use std::process;
+
+const USAGE: &str = "usage: length-extension [--hex] <md5|sha1|sha256> <digest in hex> \
+                     <secret length> <message> <extension>";
+
+fn percent_encode(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'&' | b'=' => {
+                (*byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let mut arguments = env::args().skip(1).collect::<Vec<String>>();
+    let hex_input = arguments.first().map(String::as_str) == Some("--hex");
+    if hex_input {
+        arguments.remove(0);
+    }
+    if arguments.len() != 5 {
+        fail(USAGE);
+    }
+
+    let algorithm = arguments[0]
+        .parse::<Algorithm>()
+        .unwrap_or_else(|error| fail(&error.to_string()));
+    let digest = hex_to_bytes(&arguments[1]).unwrap_or_else(|| fail("the digest isn't valid hex"));
+    let secret_length = arguments[2]
+        .parse::<usize>()
+        .unwrap_or_else(|_| fail("the secret length isn't a number"));
+
+    let (message, appended) = if hex_input {
+        (
+            hex_to_bytes(&arguments[3]).unwrap_or_else(|| fail("the message isn't valid hex")),
+            hex_to_bytes(&arguments[4]).unwrap_or_else(|| fail("the extension isn't valid hex")),
+        )
+    } else {
+        (
+            arguments[3].as_bytes().to_vec(),
+            arguments[4].as_bytes().to_vec(),
+        )
+    };
+
+    let extension = extend(algorithm, &digest, secret_length, &message, &appended)
+        .unwrap_or_else(|error| fail(&error.to_string()));
+
+    println!("suffix (hex):       {}", bytes_to_hex(&extension.suffix));
+    println!("suffix (encoded):   {}", percent_encode(&extension.suffix));
+    println!("message (hex):      {}", bytes_to_hex(&extension.message));
+    println!("message (encoded):  {}", percent_encode(&extension.message));
+    println!("digest:             {}", bytes_to_hex(&extension.digest));
+}
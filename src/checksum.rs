@@ -0,0 +1,313 @@
+//! Checksums: CRC-32, CRC-32C, CRC-64 and Adler-32.
+//!
+//! These catch accidental damage -- a flipped bit on disk, a truncated
+//! download -- and they're very good at it, but they are *not* cryptographic.
+//! Anyone can make a file with whatever CRC they like, so never use these to
+//! protect against someone tampering with data on purpose.
+//!
+//! They share the `Digest` interface with the hashes, so a verifier can treat
+//! an SFV/zip CRC and a SHA-1 the same way. The `checksum` method hands back
+//! the value as a plain integer; `finalize` gives the same value as big-endian
+//! bytes, which is the order the hex is usually written in.
+
+use crate::digest::Digest;
+
+/// Builds the lookup table for a reflected (least significant bit first)
+/// CRC-32: entry `n` is what the CRC register turns into after shifting the
+/// byte `n` through it one bit at a time. With the table, each message byte
+/// costs one lookup instead of eight shifts.
+const fn reflected_table_32(polynomial: u32) -> [u32; 256] {
+    let mut table = [0u32; 256];
+
+    let mut index = 0;
+    while index < 256 {
+        let mut value = index as u32;
+
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 == 1 {
+                (value >> 1) ^ polynomial
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+
+        table[index] = value;
+        index += 1;
+    }
+
+    table
+}
+
+/// The same idea as `reflected_table_32` for a CRC-64 that is *not* reflected,
+/// so bits come in most significant first and the register shifts left.
+const fn table_64(polynomial: u64) -> [u64; 256] {
+    let mut table = [0u64; 256];
+
+    let mut index = 0;
+    while index < 256 {
+        let mut value = (index as u64) << 56;
+
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & (1 << 63) != 0 {
+                (value << 1) ^ polynomial
+            } else {
+                value << 1
+            };
+            bit += 1;
+        }
+
+        table[index] = value;
+        index += 1;
+    }
+
+    table
+}
+
+// these are the polynomials written backwards, since both CRC-32s are reflected
+const CRC_32_TABLE: [u32; 256] = reflected_table_32(0xEDB8_8320);
+const CRC_32C_TABLE: [u32; 256] = reflected_table_32(0x82F6_3B78);
+const CRC_64_TABLE: [u64; 256] = table_64(0x42F0_E1EB_A9EA_3693);
+
+fn update_reflected_32(table: &[u32; 256], mut crc: u32, bytes: &[u8]) -> u32 {
+    for byte in bytes {
+        crc = table[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+
+    crc
+}
+
+/// CRC-32 as used by zip, gzip, PNG, Ethernet and SFV files (the IEEE 802.3
+/// polynomial).
+#[derive(Clone)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Crc32 { crc: !0 }
+    }
+}
+
+impl Crc32 {
+    pub fn checksum(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl Digest for Crc32 {
+    const BLOCK_LENGTH: usize = 1;
+    const OUTPUT_LENGTH: usize = 4;
+
+    type Output = [u8; 4];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.crc = update_reflected_32(&CRC_32_TABLE, self.crc, bytes);
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.checksum().to_be_bytes()
+    }
+}
+
+/// CRC-32C, with the Castagnoli polynomial -- the one iSCSI, ext4, Btrfs and
+/// SCTP use. It spots more errors than plain CRC-32 for the same 32 bits.
+#[derive(Clone)]
+pub struct Crc32c {
+    crc: u32,
+}
+
+impl Default for Crc32c {
+    fn default() -> Self {
+        Crc32c { crc: !0 }
+    }
+}
+
+impl Crc32c {
+    pub fn checksum(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl Digest for Crc32c {
+    const BLOCK_LENGTH: usize = 1;
+    const OUTPUT_LENGTH: usize = 4;
+
+    type Output = [u8; 4];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.crc = update_reflected_32(&CRC_32C_TABLE, self.crc, bytes);
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.checksum().to_be_bytes()
+    }
+}
+
+/// CRC-64/ECMA-182: the polynomial from the ECMA-182 tape standard, not
+/// reflected, starting from zero and with no final xor.
+///
+/// (XZ uses the same polynomial but reflected, and starting from all ones --
+/// so its CRC-64 values are different.)
+#[derive(Clone, Default)]
+pub struct Crc64 {
+    crc: u64,
+}
+
+impl Crc64 {
+    pub fn checksum(&self) -> u64 {
+        self.crc
+    }
+}
+
+impl Digest for Crc64 {
+    const BLOCK_LENGTH: usize = 1;
+    const OUTPUT_LENGTH: usize = 8;
+
+    type Output = [u8; 8];
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            let index = ((self.crc >> 56) ^ u64::from(*byte)) & 0xFF;
+            self.crc = CRC_64_TABLE[index as usize] ^ (self.crc << 8);
+        }
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.checksum().to_be_bytes()
+    }
+}
+
+const ADLER_MODULUS: u32 = 65521; // the largest prime below 2 ** 16
+
+// the most bytes we can add up before `b` could overflow a u32 and we have
+// to take the modulus (the same limit zlib uses)
+const ADLER_MAX_RUN: usize = 5552;
+
+/// Adler-32, the checksum in zlib streams. Two running sums: `a` is one plus
+/// the sum of all the bytes, and `b` is the sum of every value `a` has had,
+/// both modulo 65521.
+#[derive(Clone)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Adler32 { a: 1, b: 0 }
+    }
+}
+
+impl Adler32 {
+    pub fn checksum(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+impl Digest for Adler32 {
+    const BLOCK_LENGTH: usize = 1;
+    const OUTPUT_LENGTH: usize = 4;
+
+    type Output = [u8; 4];
+
+    fn update(&mut self, bytes: &[u8]) {
+        for run in bytes.chunks(ADLER_MAX_RUN) {
+            for byte in run {
+                self.a += u32::from(*byte);
+                self.b += self.a;
+            }
+
+            self.a %= ADLER_MODULUS;
+            self.b %= ADLER_MODULUS;
+        }
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.checksum().to_be_bytes()
+    }
+}
+
+pub fn crc32(raw_message: &str) -> u32 {
+    let mut crc = Crc32::default();
+    crc.update(raw_message.as_bytes());
+    crc.checksum()
+}
+
+pub fn crc32c(raw_message: &str) -> u32 {
+    let mut crc = Crc32c::default();
+    crc.update(raw_message.as_bytes());
+    crc.checksum()
+}
+
+pub fn crc64(raw_message: &str) -> u64 {
+    let mut crc = Crc64::default();
+    crc.update(raw_message.as_bytes());
+    crc.checksum()
+}
+
+pub fn adler32(raw_message: &str) -> u32 {
+    let mut adler = Adler32::default();
+    adler.update(raw_message.as_bytes());
+    adler.checksum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the "check" values from the usual CRC catalogues are all for this input
+    const CHECK_INPUT: &str = "123456789";
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(0, crc32(""));
+        assert_eq!(0xCBF4_3926, crc32(CHECK_INPUT));
+        assert_eq!(
+            0x414F_A339,
+            crc32("The quick brown fox jumps over the lazy dog")
+        );
+        assert_eq!([0xCB, 0xF4, 0x39, 0x26], Crc32::digest(b"123456789"));
+    }
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(0xE306_9283, crc32c(CHECK_INPUT));
+        // RFC 3720, B.4: 32 bytes of zeroes
+        let mut crc = Crc32c::default();
+        crc.update(&[0u8; 32]);
+        assert_eq!(0x8A91_36AA, crc.checksum());
+    }
+
+    #[test]
+    fn test_crc64() {
+        assert_eq!(0x6C40_DF5F_0B49_7347, crc64(CHECK_INPUT));
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(1, adler32(""));
+        assert_eq!(0x11E6_0398, adler32("Wikipedia"));
+        assert_eq!(0x091E_01DE, adler32(CHECK_INPUT));
+    }
+
+    #[test]
+    fn test_streaming() {
+        // long enough for Adler-32 to take the modulus more than once
+        let message = "The quick brown fox jumps over the lazy dog".repeat(300);
+
+        let mut crc = Crc32::default();
+        let mut adler = Adler32::default();
+        for piece in message.as_bytes().chunks(1000) {
+            crc.update(piece);
+            adler.update(piece);
+        }
+
+        assert_eq!(0x3681_6361, crc.checksum());
+        assert_eq!(0xB675_935B, adler.checksum());
+    }
+
+}
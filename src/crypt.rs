@@ -0,0 +1,602 @@
+//! The Unix `crypt(3)` password hashes found in `/etc/shadow`: `$1$`
+//! (md5crypt), `$5$` (SHA-256-crypt) and `$6$` (SHA-512-crypt).
+//!
+//! A stored hash carries everything needed to check a password against it:
+//!
+//! ```text
+//! $6$rounds=10000$saltsaltsaltsalt$<86 characters of hash>
+//! ```
+//!
+//! md5crypt is Poul-Henning Kamp's design for FreeBSD: a thousand rounds of
+//! MD5 over the password, salt and previous digest in an order that changes
+//! from round to round. Ulrich Drepper's SHA-crypt, the default on Linux for
+//! years, follows the same idea with SHA-2, but with a configurable number of
+//! rounds (5000 by default), and it avoids md5crypt's habit of doing work
+//! proportional to the password length in every round.
+//!
+//! None of these are memory-hard; they're here because existing systems use
+//! them, not as a recommendation for new ones.
+
+use crate::digest::Digest;
+use crate::md5::Md5;
+use crate::sha2::{Sha256, Sha512};
+use crate::utils::constant_time_eq;
+use std::fmt;
+use std::str::FromStr;
+
+pub(crate) const ALPHABET: &[u8; 64] =
+    b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+const DEFAULT_ROUNDS: u32 = 5000;
+const MINIMUM_ROUNDS: u32 = 1000;
+const MAXIMUM_ROUNDS: u32 = 999_999_999;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CryptError {
+    /// The `$id$` at the start isn't one of `$1$`, `$5$` or `$6$`.
+    UnknownScheme(String),
+    /// The string isn't laid out like a crypt hash.
+    Malformed,
+    /// The salt has a character that can't appear in the hash string:
+    /// `$`, `:` or a newline.
+    InvalidSalt,
+    /// md5crypt always does 1000 rounds, so it can't be given a count.
+    RoundsNotSupported,
+}
+
+impl fmt::Display for CryptError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CryptError::UnknownScheme(id) => {
+                write!(formatter, "unsupported crypt scheme \"${}$\"", id)
+            }
+            CryptError::Malformed => write!(formatter, "not a valid crypt hash"),
+            CryptError::InvalidSalt => write!(
+                formatter,
+                "a crypt salt can't contain '$', ':' or a newline"
+            ),
+            CryptError::RoundsNotSupported => {
+                write!(formatter, "md5crypt doesn't take a number of rounds")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CryptError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CryptScheme {
+    /// `$1$`
+    Md5,
+    /// `$5$`
+    Sha256,
+    /// `$6$`
+    Sha512,
+}
+
+impl CryptScheme {
+    /// The identifier between the first two `$`s.
+    pub fn id(self) -> &'static str {
+        match self {
+            CryptScheme::Md5 => "1",
+            CryptScheme::Sha256 => "5",
+            CryptScheme::Sha512 => "6",
+        }
+    }
+
+    /// The longest salt the scheme uses; anything past it is ignored.
+    pub fn maximum_salt_length(self) -> usize {
+        match self {
+            CryptScheme::Md5 => 8,
+            CryptScheme::Sha256 | CryptScheme::Sha512 => 16,
+        }
+    }
+}
+
+impl FromStr for CryptScheme {
+    type Err = CryptError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        match id {
+            "1" => Ok(CryptScheme::Md5),
+            "5" => Ok(CryptScheme::Sha256),
+            "6" => Ok(CryptScheme::Sha512),
+            _ => Err(CryptError::UnknownScheme(id.to_string())),
+        }
+    }
+}
+
+/// A crypt hash string, taken apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CryptHash {
+    pub scheme: CryptScheme,
+    /// Only set when the string spelled out `rounds=`; SHA-crypt falls back
+    /// to 5000 without it.
+    pub rounds: Option<u32>,
+    pub salt: String,
+    pub hash: String,
+}
+
+impl FromStr for CryptHash {
+    type Err = CryptError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let rest = text.strip_prefix('$').ok_or(CryptError::Malformed)?;
+        let (id, rest) = split_field(rest).ok_or(CryptError::Malformed)?;
+        let scheme = id.parse::<CryptScheme>()?;
+
+        let (rounds, rest) = match rest.strip_prefix("rounds=") {
+            Some(rounds) if scheme != CryptScheme::Md5 => {
+                let (rounds, rest) = split_field(rounds).ok_or(CryptError::Malformed)?;
+                let rounds = rounds.parse::<u32>().map_err(|_| CryptError::Malformed)?;
+                (Some(rounds), rest)
+            }
+            _ => (None, rest),
+        };
+
+        let (salt, hash) = split_field(rest).ok_or(CryptError::Malformed)?;
+        if hash.is_empty() || !hash.bytes().all(|byte| ALPHABET.contains(&byte)) {
+            return Err(CryptError::Malformed);
+        }
+
+        Ok(CryptHash {
+            scheme,
+            rounds,
+            salt: salt.to_string(),
+            hash: hash.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for CryptHash {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "${}$", self.scheme.id())?;
+        if let Some(rounds) = self.rounds {
+            write!(formatter, "rounds={}$", rounds)?;
+        }
+        write!(formatter, "{}${}", self.salt, self.hash)
+    }
+}
+
+fn split_field(text: &str) -> Option<(&str, &str)> {
+    let end = text.find('$')?;
+    Some((&text[..end], &text[end + 1..]))
+}
+
+/// crypt's base 64: its own alphabet, and `bytes` (most significant first)
+/// written out six bits at a time starting from the *least* significant end.
+fn encode_group(bytes: &[u8], output: &mut String) {
+    let mut value = bytes
+        .iter()
+        .fold(0u32, |value, byte| (value << 8) | u32::from(*byte));
+    for _ in 0..=bytes.len() {
+        output.push(ALPHABET[(value & 0x3f) as usize] as char);
+        value >>= 6;
+    }
+}
+
+// each scheme shuffles its digest before encoding it, three bytes at a time
+// and then whatever's left over
+const MD5_ORDER: [[usize; 3]; 5] = [[0, 6, 12], [1, 7, 13], [2, 8, 14], [3, 9, 15], [4, 10, 5]];
+const MD5_REST: &[usize] = &[11];
+
+const SHA256_ORDER: [[usize; 3]; 10] = [
+    [0, 10, 20],
+    [21, 1, 11],
+    [12, 22, 2],
+    [3, 13, 23],
+    [24, 4, 14],
+    [15, 25, 5],
+    [6, 16, 26],
+    [27, 7, 17],
+    [18, 28, 8],
+    [9, 19, 29],
+];
+const SHA256_REST: &[usize] = &[31, 30];
+
+const SHA512_ORDER: [[usize; 3]; 21] = [
+    [0, 21, 42],
+    [22, 43, 1],
+    [44, 2, 23],
+    [3, 24, 45],
+    [25, 46, 4],
+    [47, 5, 26],
+    [6, 27, 48],
+    [28, 49, 7],
+    [50, 8, 29],
+    [9, 30, 51],
+    [31, 52, 10],
+    [53, 11, 32],
+    [12, 33, 54],
+    [34, 55, 13],
+    [56, 14, 35],
+    [15, 36, 57],
+    [37, 58, 16],
+    [59, 17, 38],
+    [18, 39, 60],
+    [40, 61, 19],
+    [62, 20, 41],
+];
+const SHA512_REST: &[usize] = &[63];
+
+fn encode_digest(digest: &[u8], order: &[[usize; 3]], rest: &[usize]) -> String {
+    let mut output = String::new();
+    for indices in order.iter().map(|indices| &indices[..]).chain(Some(rest)) {
+        let bytes = indices
+            .iter()
+            .map(|index| digest[*index])
+            .collect::<Vec<u8>>();
+        encode_group(&bytes, &mut output);
+    }
+    output
+}
+
+/// md5crypt's core, with the magic string that starts the hash: `$1$`, or
+/// `$apr1$` for Apache's copy of it.
+pub(crate) fn md5crypt(password: &[u8], magic: &str, salt: &[u8]) -> [u8; 16] {
+    let alternate = Md5::digest(&[password, salt, password].concat());
+
+    let mut hasher = Md5::default();
+    hasher.update(password);
+    hasher.update(magic.as_bytes());
+    hasher.update(salt);
+    hasher.update(&repeat_to(&alternate, password.len()));
+
+    // one byte for every bit of the length: a zero byte for a 1 bit, and the
+    // first byte of the password for a 0 bit (this was meant to be the first
+    // byte of the digest, but shipped this way)
+    let mut length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            hasher.update(&[0]);
+        } else {
+            hasher.update(&password[..1]);
+        }
+        length >>= 1;
+    }
+    let mut digest = hasher.finalize();
+
+    for round in 0..1000 {
+        let mut hasher = Md5::default();
+        if round % 2 == 1 {
+            hasher.update(password);
+        } else {
+            hasher.update(&digest);
+        }
+        if round % 3 != 0 {
+            hasher.update(salt);
+        }
+        if round % 7 != 0 {
+            hasher.update(password);
+        }
+        if round % 2 == 1 {
+            hasher.update(&digest);
+        } else {
+            hasher.update(password);
+        }
+        digest = hasher.finalize();
+    }
+
+    digest
+}
+
+pub(crate) fn encode_md5crypt(digest: &[u8; 16]) -> String {
+    encode_digest(digest, &MD5_ORDER, MD5_REST)
+}
+
+/// The digest an md5crypt hash string encodes, if it's one `encode_md5crypt`
+/// could have written.
+pub(crate) fn decode_md5crypt(encoded: &str) -> Option<[u8; 16]> {
+    if encoded.len() != 22 {
+        return None;
+    }
+
+    let mut digest = [0u8; 16];
+    let groups = MD5_ORDER
+        .iter()
+        .map(|indices| &indices[..])
+        .chain(Some(MD5_REST));
+    let mut characters = encoded.bytes();
+    for indices in groups {
+        let mut value = 0u32;
+        for shift in 0..=indices.len() {
+            let character = characters.next()?;
+            let position = ALPHABET.iter().position(|letter| *letter == character)?;
+            value |= (position as u32) << (6 * shift);
+        }
+        for (place, index) in indices.iter().rev().enumerate() {
+            digest[*index] = (value >> (8 * place)) as u8;
+        }
+    }
+
+    // the last character has bits to spare, which have to be zero
+    if encode_md5crypt(&digest) == encoded {
+        Some(digest)
+    } else {
+        None
+    }
+}
+
+// `source` repeated (or cut short) to exactly `length` bytes
+fn repeat_to(source: &[u8], length: usize) -> Vec<u8> {
+    source.iter().cycle().take(length).copied().collect()
+}
+
+fn sha_crypt<D: Digest>(password: &[u8], salt: &[u8], rounds: u32) -> Vec<u8> {
+    let alternate = D::digest(&[password, salt, password].concat());
+
+    let mut hasher = D::default();
+    hasher.update(password);
+    hasher.update(salt);
+    hasher.update(&repeat_to(alternate.as_ref(), password.len()));
+    // unlike md5crypt, every bit of the length picks the digest or the password
+    let mut length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            hasher.update(alternate.as_ref());
+        } else {
+            hasher.update(password);
+        }
+        length >>= 1;
+    }
+    let mut digest = hasher.finalize();
+
+    // the password and salt are each hashed once up front into byte strings of
+    // the same length, so that the rounds don't depend on what's in them
+    let mut hasher = D::default();
+    for _ in 0..password.len() {
+        hasher.update(password);
+    }
+    let password_bytes = repeat_to(hasher.finalize().as_ref(), password.len());
+
+    let mut hasher = D::default();
+    for _ in 0..16 + usize::from(digest.as_ref()[0]) {
+        hasher.update(salt);
+    }
+    let salt_bytes = repeat_to(hasher.finalize().as_ref(), salt.len());
+
+    for round in 0..rounds {
+        let mut hasher = D::default();
+        if round % 2 == 1 {
+            hasher.update(&password_bytes);
+        } else {
+            hasher.update(digest.as_ref());
+        }
+        if round % 3 != 0 {
+            hasher.update(&salt_bytes);
+        }
+        if round % 7 != 0 {
+            hasher.update(&password_bytes);
+        }
+        if round % 2 == 1 {
+            hasher.update(digest.as_ref());
+        } else {
+            hasher.update(&password_bytes);
+        }
+        digest = hasher.finalize();
+    }
+
+    digest.as_ref().to_vec()
+}
+
+/// Hashes `password` into a full crypt string, such as
+/// `$5$rounds=10000$saltstring$...`.
+///
+/// Salts longer than the scheme's `maximum_salt_length` are cut short, and
+/// round counts outside 1000 to 999,999,999 are moved to the nearest end,
+/// both the way glibc does it.
+pub fn hash_password(
+    scheme: CryptScheme,
+    password: &[u8],
+    salt: &str,
+    rounds: Option<u32>,
+) -> Result<String, CryptError> {
+    if salt.contains(['$', ':', '\n']) {
+        return Err(CryptError::InvalidSalt);
+    }
+    let salt = match salt.char_indices().nth(scheme.maximum_salt_length()) {
+        Some((end, _)) => &salt[..end],
+        None => salt,
+    };
+
+    let rounds = rounds.map(|rounds| rounds.clamp(MINIMUM_ROUNDS, MAXIMUM_ROUNDS));
+    let hash = match scheme {
+        CryptScheme::Md5 => {
+            if rounds.is_some() {
+                return Err(CryptError::RoundsNotSupported);
+            }
+            encode_md5crypt(&md5crypt(password, "$1$", salt.as_bytes()))
+        }
+        CryptScheme::Sha256 => {
+            let digest =
+                sha_crypt::<Sha256>(password, salt.as_bytes(), rounds.unwrap_or(DEFAULT_ROUNDS));
+            encode_digest(&digest, &SHA256_ORDER, SHA256_REST)
+        }
+        CryptScheme::Sha512 => {
+            let digest =
+                sha_crypt::<Sha512>(password, salt.as_bytes(), rounds.unwrap_or(DEFAULT_ROUNDS));
+            encode_digest(&digest, &SHA512_ORDER, SHA512_REST)
+        }
+    };
+
+    Ok(CryptHash {
+        scheme,
+        rounds,
+        salt: salt.to_string(),
+        hash,
+    }
+    .to_string())
+}
+
+/// A salt for `hash_password`, made from random bytes the caller supplies
+/// (from the OS, or a `drbg`): six bits per character, cut to the scheme's
+/// maximum length. Twelve bytes fill a SHA-crypt salt, six an md5crypt one.
+pub fn generate_salt(scheme: CryptScheme, random: &[u8]) -> String {
+    let mut salt = String::new();
+    for group in random.chunks(3) {
+        encode_group(group, &mut salt);
+    }
+    salt.truncate(scheme.maximum_salt_length());
+    salt
+}
+
+/// Checks `password` against the hash field of a shadow entry.
+///
+/// Locked and disabled accounts (a hash starting with `!` or `*`) never
+/// match; anything else that isn't a `$1$`, `$5$` or `$6$` hash is an error.
+pub fn verify(password: &[u8], hashed: &str) -> Result<bool, CryptError> {
+    if hashed.starts_with('!') || hashed.starts_with('*') {
+        return Ok(false);
+    }
+
+    let parsed = hashed.parse::<CryptHash>()?;
+    let expected = hash_password(parsed.scheme, password, &parsed.salt, parsed.rounds)?;
+    Ok(constant_time_eq(expected.as_bytes(), hashed.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_md5crypt() {
+        assert_eq!(
+            Ok(String::from("$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/")),
+            hash_password(CryptScheme::Md5, b"password", "saltsalt", None)
+        );
+        assert_eq!(
+            Ok(String::from("$1$$qRPK7m23GJusamGpoGLby/")),
+            hash_password(CryptScheme::Md5, b"", "", None)
+        );
+        assert_eq!(
+            Ok(String::from("$1$abc$jvzLkIwGJPs3xI75H4I00/")),
+            hash_password(
+                CryptScheme::Md5,
+                b"a much longer password, to go over sixteen bytes",
+                "abc",
+                None
+            )
+        );
+        assert_eq!(
+            Err(CryptError::RoundsNotSupported),
+            hash_password(CryptScheme::Md5, b"password", "saltsalt", Some(5000))
+        );
+    }
+
+    // the examples from Drepper's SHA-crypt specification
+    #[test]
+    fn test_sha256_crypt() {
+        assert_eq!(
+            Ok(String::from(
+                "$5$saltstring$5B8vYYiY.CVt1RlTTf8KbXBH3hsxY/GNooZaBBGWEc5"
+            )),
+            hash_password(CryptScheme::Sha256, b"Hello world!", "saltstring", None)
+        );
+        // the salt is cut to 16 characters
+        assert_eq!(
+            Ok(String::from(
+                "$5$rounds=10000$saltstringsaltst$3xv.VbSHBb41AL9AvLeujZkZRBAwqFMz2.opqey6IcA"
+            )),
+            hash_password(
+                CryptScheme::Sha256,
+                b"Hello world!",
+                "saltstringsaltstring",
+                Some(10000)
+            )
+        );
+        // and too few rounds becomes the minimum
+        assert_eq!(
+            Ok(String::from(
+                "$5$rounds=1000$roundstoolow$yfvwcWrQ8l/K0DAWyuPMDNHpIVlTQebY9l/gL972bIC"
+            )),
+            hash_password(
+                CryptScheme::Sha256,
+                b"the minimum number is still observed",
+                "roundstoolow",
+                Some(10)
+            )
+        );
+    }
+
+    #[test]
+    fn test_sha512_crypt() {
+        assert_eq!(
+            Ok(String::from("$6$saltstring$svn8UoSVapNtMuq1ukKS4tPQd8iKwSMHWjl/O817G3uBnIFNjnQJuesI68u4OTLiBFdcbYEdFCoEOfaS35inz1")),
+            hash_password(CryptScheme::Sha512, b"Hello world!", "saltstring", None)
+        );
+        assert_eq!(
+            Ok(String::from("$6$rounds=1000$toolongsaltstrin$sesQxVr.eO8J/1tYcwFWM0XaMVaHFLetz9ssK1oNSPUQgLRm8v3S4i6CgxB9aqGOAFFEMnB2V1gvxEm/Gv2gw/")),
+            hash_password(
+                CryptScheme::Sha512,
+                b"Hello world!",
+                "toolongsaltstringXYZ",
+                Some(1000)
+            )
+        );
+        // a password longer than a SHA-512 block
+        assert_eq!(
+            Ok(String::from("$6$rounds=1000$x$hmUAWvBVFrty2IVLgPyXYLSaMxqcl3.u672lsvrB7qUNrsPExe5SrA3kXImeiZ6iDDxOcemFpeJ31AAjEu84j/")),
+            hash_password(
+                CryptScheme::Sha512,
+                b"a much longer password, longer than a sixty-four byte sha-512 block, so more",
+                "x",
+                Some(1000)
+            )
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let shadow = "$5$rounds=1000$roundstoolow$yfvwcWrQ8l/K0DAWyuPMDNHpIVlTQebY9l/gL972bIC";
+        assert_eq!(
+            Ok(true),
+            verify(b"the minimum number is still observed", shadow)
+        );
+        assert_eq!(Ok(false), verify(b"the minimum number", shadow));
+        assert_eq!(
+            Ok(true),
+            verify(b"password", "$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/")
+        );
+
+        // locked accounts
+        assert_eq!(
+            Ok(false),
+            verify(b"password", "!$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/")
+        );
+        assert_eq!(Ok(false), verify(b"", "*"));
+
+        assert_eq!(
+            Err(CryptError::UnknownScheme(String::from("2b"))),
+            verify(b"password", "$2b$10$abcdefghijklmnopqrstuu")
+        );
+        assert_eq!(Err(CryptError::Malformed), verify(b"password", "$5$salt"));
+        assert_eq!(
+            Err(CryptError::Malformed),
+            verify(b"password", "$5$rounds=many$salt$hash")
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let random = (0u8..12)
+            .map(|byte| byte.wrapping_mul(37))
+            .collect::<Vec<u8>>();
+        let salt = generate_salt(CryptScheme::Sha512, &random);
+        assert_eq!(16, salt.len());
+        assert_eq!(8, generate_salt(CryptScheme::Md5, &random).len());
+
+        let hashed = hash_password(CryptScheme::Sha512, b"hunter2", &salt, Some(1000)).unwrap();
+        let parsed = hashed.parse::<CryptHash>().unwrap();
+        assert_eq!(Some(1000), parsed.rounds);
+        assert_eq!(salt, parsed.salt);
+        assert_eq!(86, parsed.hash.len());
+        assert_eq!(hashed, parsed.to_string());
+        assert_eq!(Ok(true), verify(b"hunter2", &hashed));
+
+        assert_eq!(
+            Err(CryptError::InvalidSalt),
+            hash_password(CryptScheme::Sha256, b"hunter2", "a:b", None)
+        );
+    }
+
+}
@@ -0,0 +1,33 @@
+use std::fmt::Debug;
+
+/// The streaming side of the hash functions in this crate.
+///
+/// Functions like `sha1` take the whole message as a `&str` and hand back hex,
+/// which is great for poking at the algorithms but not so great when the
+/// message is a file, a socket, or just bytes that aren't valid UTF-8. A
+/// `Digest` gets fed its message a piece at a time through `update`, and
+/// `finalize` hands back the raw digest bytes.
+///
+/// Feeding "ab" and then "c" gives exactly the same answer as feeding "abc" in
+/// one go -- where the message gets split up never matters.
+pub trait Digest: Clone + Default {
+    /// How many bytes the engine chews through per step: the 64-byte block
+    /// for SHA-1, the rate of the sponge for Keccak, and so on.
+    const BLOCK_LENGTH: usize;
+
+    /// How many bytes `finalize` hands back.
+    const OUTPUT_LENGTH: usize;
+
+    type Output: AsRef<[u8]> + Clone + Debug + PartialEq;
+
+    fn update(&mut self, bytes: &[u8]);
+
+    fn finalize(self) -> Self::Output;
+
+    /// Hash a whole message in one go.
+    fn digest(bytes: &[u8]) -> Self::Output {
+        let mut hasher = Self::default();
+        hasher.update(bytes);
+        hasher.finalize()
+    }
+}
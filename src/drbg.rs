@@ -0,0 +1,592 @@
+//! Deterministic random bit generators from NIST SP 800-90A: Hash_DRBG and
+//! HMAC_DRBG.
+//!
+//! A DRBG stretches a seed into a long stream of random-looking bytes. Given
+//! the same entropy, nonce and personalization string it always produces the
+//! same stream, which is what makes it useful in tests -- and what makes it
+//! important that the entropy really is secret and unpredictable when it's
+//! used for keys.
+//!
+//! Nothing here gathers entropy itself: every call that needs fresh entropy
+//! (instantiating, reseeding, and generating with prediction resistance)
+//! takes it as an argument.
+
+use crate::digest::Digest;
+use crate::hmac::Hmac;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// After this many requests the DRBG refuses to generate until it's
+/// reseeded. SP 800-90A allows up to 2 ** 48.
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+
+/// The most one `generate` call can ask for: 2 ** 19 bits.
+pub const MAXIMUM_REQUEST_LENGTH: usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DrbgError {
+    /// There isn't enough entropy for the security strength of the hash.
+    EntropyTooShort { length: usize, minimum: usize },
+    /// More than `MAXIMUM_REQUEST_LENGTH` bytes were asked for at once.
+    RequestTooLong { requested: usize, maximum: usize },
+    /// `RESEED_INTERVAL` requests have been made since the last reseed.
+    ReseedRequired,
+}
+
+impl fmt::Display for DrbgError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrbgError::EntropyTooShort { length, minimum } => write!(
+                formatter,
+                "the entropy input needs at least {} bytes, but has {}",
+                minimum, length
+            ),
+            DrbgError::RequestTooLong { requested, maximum } => write!(
+                formatter,
+                "at most {} bytes can be generated at once, but {} were requested",
+                maximum, requested
+            ),
+            DrbgError::ReseedRequired => write!(formatter, "the DRBG must be reseeded"),
+        }
+    }
+}
+
+impl std::error::Error for DrbgError {}
+
+/// The security strength (in bytes) SP 800-57 gives each hash for random
+/// number generation: 128 bits for SHA-1, 192 for SHA-224, 256 for SHA-256 and
+/// up. The entropy input has to be at least this long.
+fn security_strength<D: Digest>() -> usize {
+    match D::OUTPUT_LENGTH {
+        0..=20 => 16,
+        21..=28 => 24,
+        _ => 32,
+    }
+}
+
+fn check_entropy<D: Digest>(entropy: &[u8]) -> Result<(), DrbgError> {
+    let minimum = security_strength::<D>();
+    if entropy.len() < minimum {
+        return Err(DrbgError::EntropyTooShort {
+            length: entropy.len(),
+            minimum,
+        });
+    }
+
+    Ok(())
+}
+
+/// What both DRBGs can do once they're instantiated.
+pub trait Drbg {
+    /// Mixes fresh entropy (and optionally some additional input) into the
+    /// state, and resets the reseed counter.
+    fn reseed(&mut self, entropy: &[u8], additional_input: &[u8]) -> Result<(), DrbgError>;
+
+    /// Fills `output`. The additional input can be empty; if it isn't, it's
+    /// mixed into the state first.
+    fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) -> Result<(), DrbgError>;
+
+    /// Reseeds with `entropy` right before generating, so that even someone
+    /// who somehow learned the state can't predict this output. The additional
+    /// input goes into the reseed instead of the generate, as the standard
+    /// says.
+    fn generate_with_prediction_resistance(
+        &mut self,
+        output: &mut [u8],
+        entropy: &[u8],
+        additional_input: &[u8],
+    ) -> Result<(), DrbgError> {
+        self.reseed(entropy, additional_input)?;
+        self.generate(output, &[])
+    }
+}
+
+fn check_request(output: &[u8], reseed_counter: u64) -> Result<(), DrbgError> {
+    if output.len() > MAXIMUM_REQUEST_LENGTH {
+        return Err(DrbgError::RequestTooLong {
+            requested: output.len(),
+            maximum: MAXIMUM_REQUEST_LENGTH,
+        });
+    }
+
+    if reseed_counter > RESEED_INTERVAL {
+        return Err(DrbgError::ReseedRequired);
+    }
+
+    Ok(())
+}
+
+/// HMAC_DRBG: the state is an HMAC key `K` and a value `V`, and output is
+/// `V = HMAC(K, V)` over and over.
+#[derive(Clone)]
+pub struct HmacDrbg<D: Digest> {
+    key: Vec<u8>,
+    value: Vec<u8>,
+    reseed_counter: u64,
+    hash: PhantomData<D>,
+}
+
+impl<D: Digest> HmacDrbg<D> {
+    pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Result<Self, DrbgError> {
+        check_entropy::<D>(entropy)?;
+
+        let mut drbg = HmacDrbg {
+            key: vec![0x00; D::OUTPUT_LENGTH],
+            value: vec![0x01; D::OUTPUT_LENGTH],
+            reseed_counter: 1,
+            hash: PhantomData,
+        };
+        drbg.update(&[entropy, nonce, personalization]);
+
+        Ok(drbg)
+    }
+
+    fn mac(&self, pieces: &[&[u8]]) -> Vec<u8> {
+        let mut mac = Hmac::<D>::new(&self.key);
+        for piece in pieces {
+            mac.update(piece);
+        }
+        mac.finalize().as_ref().to_vec()
+    }
+
+    // HMAC_DRBG_Update; the provided data is passed in pieces so callers
+    // don't have to join them up
+    fn update(&mut self, provided_data: &[&[u8]]) {
+        let value = self.value.clone();
+        let mut pieces: Vec<&[u8]> = vec![&value, &[0x00]];
+        pieces.extend_from_slice(provided_data);
+        self.key = self.mac(&pieces);
+        self.value = self.mac(&[&self.value]);
+
+        if provided_data.iter().all(|piece| piece.is_empty()) {
+            return;
+        }
+
+        let value = self.value.clone();
+        let mut pieces: Vec<&[u8]> = vec![&value, &[0x01]];
+        pieces.extend_from_slice(provided_data);
+        self.key = self.mac(&pieces);
+        self.value = self.mac(&[&self.value]);
+    }
+}
+
+impl<D: Digest> Drbg for HmacDrbg<D> {
+    fn reseed(&mut self, entropy: &[u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_entropy::<D>(entropy)?;
+
+        self.update(&[entropy, additional_input]);
+        self.reseed_counter = 1;
+
+        Ok(())
+    }
+
+    fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_request(output, self.reseed_counter)?;
+
+        if !additional_input.is_empty() {
+            self.update(&[additional_input]);
+        }
+
+        for piece in output.chunks_mut(D::OUTPUT_LENGTH) {
+            self.value = self.mac(&[&self.value]);
+            piece.copy_from_slice(&self.value[..piece.len()]);
+        }
+
+        self.update(&[additional_input]);
+        self.reseed_counter += 1;
+
+        Ok(())
+    }
+}
+
+/// How long `V` and `C` are for Hash_DRBG: 440 bits for hashes with outputs
+/// up to 256 bits, 888 for the bigger ones.
+fn seed_length<D: Digest>() -> usize {
+    if D::OUTPUT_LENGTH <= 32 {
+        55
+    } else {
+        111
+    }
+}
+
+/// `left = (left + right) mod 2 ** (8 * left.len())`, both big endian.
+fn add_into(left: &mut [u8], right: &[u8]) {
+    let mut carry = 0u16;
+    let mut right_bytes = right.iter().rev();
+
+    for byte in left.iter_mut().rev() {
+        let sum = u16::from(*byte) + u16::from(*right_bytes.next().unwrap_or(&0)) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+/// Hash_DRBG: the state is a value `V` and a constant `C`, both a bit longer
+/// than the hash output. Output is the hash of `V`, `V + 1`, `V + 2`, ..., and
+/// after every request `V` moves on by a hash of itself, `C` and the reseed
+/// counter.
+#[derive(Clone)]
+pub struct HashDrbg<D: Digest> {
+    value: Vec<u8>,
+    constant: Vec<u8>,
+    reseed_counter: u64,
+    hash: PhantomData<D>,
+}
+
+impl<D: Digest> HashDrbg<D> {
+    pub fn new(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Result<Self, DrbgError> {
+        check_entropy::<D>(entropy)?;
+
+        let value = HashDrbg::<D>::hash_df(&[entropy, nonce, personalization]);
+        let constant = HashDrbg::<D>::hash_df(&[&[0x00], &value]);
+
+        Ok(HashDrbg {
+            value,
+            constant,
+            reseed_counter: 1,
+            hash: PhantomData,
+        })
+    }
+
+    fn hash(pieces: &[&[u8]]) -> D::Output {
+        let mut hasher = D::default();
+        for piece in pieces {
+            hasher.update(piece);
+        }
+        hasher.finalize()
+    }
+
+    /// Hash_df, the derivation function: hashes the input with a counter and
+    /// the output length in front, as many times as it takes to get
+    /// `seed_length` bytes.
+    fn hash_df(input: &[&[u8]]) -> Vec<u8> {
+        let length = seed_length::<D>();
+        let bits = (8 * length as u32).to_be_bytes();
+
+        let mut output = Vec::with_capacity(length + D::OUTPUT_LENGTH);
+        let mut counter = 1u8;
+        while output.len() < length {
+            let counter_byte = [counter];
+            let mut pieces: Vec<&[u8]> = vec![&counter_byte, &bits];
+            pieces.extend_from_slice(input);
+            output.extend_from_slice(HashDrbg::<D>::hash(&pieces).as_ref());
+            counter += 1;
+        }
+
+        output.truncate(length);
+        output
+    }
+}
+
+impl<D: Digest> Drbg for HashDrbg<D> {
+    fn reseed(&mut self, entropy: &[u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_entropy::<D>(entropy)?;
+
+        self.value = HashDrbg::<D>::hash_df(&[&[0x01], &self.value, entropy, additional_input]);
+        self.constant = HashDrbg::<D>::hash_df(&[&[0x00], &self.value]);
+        self.reseed_counter = 1;
+
+        Ok(())
+    }
+
+    fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_request(output, self.reseed_counter)?;
+
+        if !additional_input.is_empty() {
+            let mixed = HashDrbg::<D>::hash(&[&[0x02], &self.value, additional_input]);
+            add_into(&mut self.value, mixed.as_ref());
+        }
+
+        // Hashgen
+        let mut data = self.value.clone();
+        for piece in output.chunks_mut(D::OUTPUT_LENGTH) {
+            let block = D::digest(&data);
+            piece.copy_from_slice(&block.as_ref()[..piece.len()]);
+            add_into(&mut data, &[1]);
+        }
+
+        let mixed = HashDrbg::<D>::hash(&[&[0x03], &self.value]);
+        add_into(&mut self.value, mixed.as_ref());
+        let constant = self.constant.clone();
+        add_into(&mut self.value, &constant);
+        add_into(&mut self.value, &self.reseed_counter.to_be_bytes());
+        self.reseed_counter += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::Sha256;
+    use crate::utils::hex_to_bytes;
+    use crate::Sha1;
+
+    // CAVP runs every case the same way: instantiate, generate once and throw
+    // the output away, then generate again and compare
+    fn second_output(drbg: &mut impl Drbg, length: usize, additional: [&[u8]; 2]) -> Vec<u8> {
+        let mut output = vec![0u8; length];
+        drbg.generate(&mut output, additional[0]).unwrap();
+        drbg.generate(&mut output, additional[1]).unwrap();
+        output
+    }
+
+    // ... and with prediction resistance, each generate reseeds with its own
+    // entropy and additional input first
+    fn second_output_with_prediction_resistance(
+        drbg: &mut impl Drbg,
+        length: usize,
+        entropy: [&[u8]; 2],
+        additional: [&[u8]; 2],
+    ) -> Vec<u8> {
+        let mut output = vec![0u8; length];
+        drbg.generate_with_prediction_resistance(&mut output, entropy[0], additional[0])
+            .unwrap();
+        drbg.generate_with_prediction_resistance(&mut output, entropy[1], additional[1])
+            .unwrap();
+        output
+    }
+
+    #[test]
+    fn test_hash_drbg_sha256() {
+        let mut drbg = HashDrbg::<Sha256>::new(
+            &hex_to_bytes("a65ad0f345db4e0effe875c3a2e71f42c7129d620ff5c119a9ef55f05185e0fb")
+                .unwrap(),
+            &hex_to_bytes("8581f9317517276e06e9607ddbcbcc2e").unwrap(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            hex_to_bytes("d3e160c35b99f340b2628264d1751060e0045da383ff57a57d73a673d2b8d80daaf6a6c35a91bb4579d73fd0c8fed111b0391306828adfed528f018121b3febdc343e797b87dbb63db1333ded9d1ece177cfa6b71fe8ab1da46624ed6415e51ccde2c7ca86e283990eeaeb91120415528b2295910281b02dd431f4c9f70427df").unwrap(),
+            second_output(&mut drbg, 128, [&[], &[]])
+        );
+    }
+
+    #[test]
+    fn test_hmac_drbg_sha256() {
+        let mut drbg = HmacDrbg::<Sha256>::new(
+            &hex_to_bytes("ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488")
+                .unwrap(),
+            &hex_to_bytes("659ba96c601dc69fc902940805ec0ca8").unwrap(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            hex_to_bytes("e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697d04d5b89d54fbb978a15b5c443c9ec21036d2460b6f73ebad0dc2aba6e624abf07745bc107694bb7547bb0995f70de25d6b29e2d3011bb19d27676c07162c8b5ccde0668961df86803482cb37ed6d5c0bb8d50cf1f50d476aa0458bdaba806f48be9dcb8").unwrap(),
+            second_output(&mut drbg, 128, [&[], &[]])
+        );
+
+        // with additional input
+        let mut drbg = HmacDrbg::<Sha256>::new(
+            &hex_to_bytes("d3cc4d1acf3dde0c4bd2290d262337042dc632948223d3a2eaab87da44295fbd")
+                .unwrap(),
+            &hex_to_bytes("0109b0e729f457328aa18569a9224921").unwrap(),
+            &[],
+        )
+        .unwrap();
+        let additional = [
+            hex_to_bytes("3c311848183c9a212a26f27f8c6647e40375e466a0857cc39c4e47575d53f1f6")
+                .unwrap(),
+            hex_to_bytes("fcb9abd19ccfbccef88c9c39bfb3dd7b1c12266c9808992e305bc3cff566e4e4")
+                .unwrap(),
+        ];
+        assert_eq!(
+            hex_to_bytes("9c7b758b212cd0fcecd5daa489821712e3cdea4467b560ef5ddc24ab47749a1f1ffdbbb118f4e62fcfca3371b8fbfc5b0646b83e06bfbbab5fac30ea09ea2bc76f1ea568c9be0444b2cc90517b20ca825f2d0eccd88e7175538b85d90ab390183ca6395535d34473af6b5a5b88f5a59ee7561573337ea819da0dcc3573a22974").unwrap(),
+            second_output(&mut drbg, 128, [&additional[0], &additional[1]])
+        );
+    }
+
+    #[test]
+    fn test_prediction_resistance() {
+        // CAVP Hash_DRBG.rsp, [SHA-256], PredictionResistance = True, COUNT = 14
+        let mut drbg = HashDrbg::<Sha256>::new(
+            &hex_to_bytes("066dc8ce75b28966a685163fe2a4d427fbdb616650616ba282fc332b4e6f1220")
+                .unwrap(),
+            &hex_to_bytes("559f7c64897083ec2d7370d9f0e5071f").unwrap(),
+            &hex_to_bytes("886f549aad1ac63d18cbcc6685daa2c2f79eb0894cb4aef1ac544fce57f15e11")
+                .unwrap(),
+        )
+        .unwrap();
+        let entropy = [
+            hex_to_bytes("ff80b7d26a05bc8a7abe53286b0eeb733b715a205bfa4ff63703deadb6ea0ef4")
+                .unwrap(),
+            hex_to_bytes("c73832534681ede37e03846d3c841767297d246c689241d2e775be7ec996293d")
+                .unwrap(),
+        ];
+        let additional = [
+            hex_to_bytes("b7215f14ac7bafd0a91772ba22f719afbd20b311636c2b1e83e4a823353fc6ea")
+                .unwrap(),
+            hex_to_bytes("ced31f7e0dae5bb5c043e246b29473e2fd39512ead4569eee3e3803314aba7a3")
+                .unwrap(),
+        ];
+        assert_eq!(
+            hex_to_bytes("60c234cfafb468033bf195e578ce266e1465326a96a9e03f8b893670ef62754d5e80d553a1f84950208b9343079f2ef856e9c570618597b5dc82a2daeaa3fd9b2fd2a0d71bc62935ccb83da0679805a0e31efee4f0e513b08317faca935e382948d272db763e6df32510ff1b99fff8c60eb0dd292ebcbbc80a016ed3b00e4eab").unwrap(),
+            second_output_with_prediction_resistance(
+                &mut drbg,
+                128,
+                [&entropy[0], &entropy[1]],
+                [&additional[0], &additional[1]]
+            )
+        );
+
+        // CAVP HMAC_DRBG.rsp, [SHA-256], PredictionResistance = True, COUNT = 0
+        let mut drbg = HmacDrbg::<Sha256>::new(
+            &hex_to_bytes("ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488")
+                .unwrap(),
+            &hex_to_bytes("659ba96c601dc69fc902940805ec0ca8").unwrap(),
+            &hex_to_bytes("e72dd8590d4ed5295515c35ed6199e9d211b8f069b3058caa6670b96ef1208d0")
+                .unwrap(),
+        )
+        .unwrap();
+        let entropy = [
+            hex_to_bytes("5cacc68165a2e2ee20812f35ec73a79dbf30fd475476ac0c44fc6174cdac2b55")
+                .unwrap(),
+            hex_to_bytes("8df013b4d103523073917ddf6a869793059e9943fc8654549e7ab22f7c29f122")
+                .unwrap(),
+        ];
+        let additional = [
+            hex_to_bytes("793a7ef8f6f0482beac542bb785c10f8b7b406a4de92667ab168ecc2cf7573c6")
+                .unwrap(),
+            hex_to_bytes("2238cdb4e23d629fe0c2a83dd8d5144ce1a6229ef41dabe2a99ff722e510b530")
+                .unwrap(),
+        ];
+        assert_eq!(
+            hex_to_bytes("b1d17c002a7febd28412d8e58a7f32318e4ee3605a99b05b05d59356d5f0c6b4960a4b8f963b7efa55bb6872fbeac7b99b78dea8f3531973637c946a9cab3349744b24a0851dd47f2b3b460c2c61846e91181d62d42c60a4efda5ed57902bfd702b349c54952c7f644769d8ef4015ecc5f5bbd4af06134688e30050e0497fb0a").unwrap(),
+            second_output_with_prediction_resistance(
+                &mut drbg,
+                128,
+                [&entropy[0], &entropy[1]],
+                [&additional[0], &additional[1]]
+            )
+        );
+
+        // CAVP HMAC_DRBG.rsp, [SHA-1], PredictionResistance = True, with a
+        // personalization string and additional input, COUNT = 0
+        let mut drbg = HmacDrbg::<Sha1>::new(
+            &hex_to_bytes("680face90d7bca21d4a0edb7799ee5d8").unwrap(),
+            &hex_to_bytes("b7be9eeddd0e3b4b").unwrap(),
+            &hex_to_bytes("f58c40ae70f7a55648a931a0a9313dd7").unwrap(),
+        )
+        .unwrap();
+        let entropy = [
+            hex_to_bytes("7cafe231630aa95a742c4e5f5f22c6a4").unwrap(),
+            hex_to_bytes("1c0d7792898827948a589f822d1af7a6").unwrap(),
+        ];
+        let additional = [
+            hex_to_bytes("dc3663f062789cd15cbb20c3c18cd9d7").unwrap(),
+            hex_to_bytes("fe85b0ab14c696e69c24e7b5a137120c").unwrap(),
+        ];
+        assert_eq!(
+            hex_to_bytes("68004b3a28f7f01cf9e9b5712079ef80871b08b9a91bcd2b9f094da48480b34cafd5596b0c0a48e148dabc6f77b8ffaf187028e104137a4feb1c72b0c44fe8b1afaba5bcfd8667f2f55b4606632e3cbc").unwrap(),
+            second_output_with_prediction_resistance(
+                &mut drbg,
+                80,
+                [&entropy[0], &entropy[1]],
+                [&additional[0], &additional[1]]
+            )
+        );
+    }
+
+    #[test]
+    fn test_reseed() {
+        // CAVP HMAC_DRBG.rsp, [SHA-1], PredictionResistance = False, with a
+        // personalization string and additional input, COUNT = 0: reseed
+        // before the two generates
+        let entropy = hex_to_bytes("03e7b41c95818eb0b667bfa8a175a824").unwrap();
+        let nonce = hex_to_bytes("66a1e417a9b6b92f").unwrap();
+        let personalization = hex_to_bytes("126dded5eb0bc81be37c10bcd9d5f793").unwrap();
+        let reseed_entropy = hex_to_bytes("d17e98c2e50ee0db00d25c3364451e95").unwrap();
+        let additional = [
+            hex_to_bytes("dc596d188e2343802240bc7f5cc60516").unwrap(),
+            hex_to_bytes("14c8ec10f5bdde6b9e75898d7f9f03d0").unwrap(),
+            hex_to_bytes("31aa842afcc1daa94098241a87d6ddfc").unwrap(),
+        ];
+
+        let mut drbg = HmacDrbg::<Sha1>::new(&entropy, &nonce, &personalization).unwrap();
+        drbg.reseed(&reseed_entropy, &additional[0]).unwrap();
+        assert_eq!(
+            hex_to_bytes("4739b1bcf87404a2290829bd7a61f0b391a794c71c055c7cc513b28dcb5fdc88645bc9cb490f41fab134c6b33ce9336571762754343961de671b02a47960b4b4e23c5bfb87dcc19b260b3bcb921ae325").unwrap(),
+            second_output(&mut drbg, 80, [&additional[1], &additional[2]])
+        );
+
+        // the same run through Hash_DRBG, checked against OpenSSL's HASH-DRBG;
+        // this one also covers additional input to Hash_DRBG's generate
+        let mut drbg = HashDrbg::<Sha1>::new(&entropy, &nonce, &personalization).unwrap();
+        drbg.reseed(&reseed_entropy, &additional[0]).unwrap();
+        assert_eq!(
+            hex_to_bytes("293881af14d58de0f9f6be23cce9bc87232911297cc2ccc9ca08c6903ce1dce7bbe8313d602c4dccca4b161273614535d36bdf996e5c2fd410165c90b7d61f779629069ddd2ba3036af3c94ca3e5db1e").unwrap(),
+            second_output(&mut drbg, 80, [&additional[1], &additional[2]])
+        );
+    }
+
+    #[test]
+    fn test_reseed_and_prediction_resistance() {
+        let entropy = [0x11; 16];
+        let fresh_entropy = [0x22; 16];
+
+        let original = HmacDrbg::<Sha1>::new(&entropy, b"nonce", b"test harness").unwrap();
+
+        // the same seed always gives the same stream
+        let mut first = original.clone();
+        let mut second = original.clone();
+        assert_eq!(
+            second_output(&mut first, 40, [&[], &[]]),
+            second_output(&mut second, 40, [&[], &[]])
+        );
+
+        let mut plain = original.clone();
+        let mut predicted = [0u8; 40];
+        plain.generate(&mut predicted, &[]).unwrap();
+
+        let mut resistant = original.clone();
+        let mut output = [0u8; 40];
+        resistant
+            .generate_with_prediction_resistance(&mut output, &fresh_entropy, &[])
+            .unwrap();
+        assert_ne!(predicted, output);
+
+        let mut reseeded = original;
+        reseeded.reseed(&fresh_entropy, &[]).unwrap();
+        let mut after_reseed = [0u8; 40];
+        reseeded.generate(&mut after_reseed, &[]).unwrap();
+        assert_eq!(output, after_reseed);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Some(DrbgError::EntropyTooShort {
+                length: 16,
+                minimum: 32
+            }),
+            HashDrbg::<Sha256>::new(&[0; 16], &[0; 16], &[]).err()
+        );
+
+        let mut drbg = HashDrbg::<Sha1>::new(&[0; 16], &[0; 8], &[]).unwrap();
+        let mut too_long = vec![0u8; MAXIMUM_REQUEST_LENGTH + 1];
+        assert_eq!(
+            Err(DrbgError::RequestTooLong {
+                requested: MAXIMUM_REQUEST_LENGTH + 1,
+                maximum: MAXIMUM_REQUEST_LENGTH
+            }),
+            drbg.generate(&mut too_long, &[])
+        );
+        assert_eq!(
+            Err(DrbgError::EntropyTooShort {
+                length: 8,
+                minimum: 16
+            }),
+            drbg.reseed(&[0; 8], &[])
+        );
+
+        drbg.reseed_counter = RESEED_INTERVAL + 1;
+        assert_eq!(
+            Err(DrbgError::ReseedRequired),
+            drbg.generate(&mut [0u8; 16], &[])
+        );
+        drbg.reseed(&[1; 16], &[]).unwrap();
+        assert!(drbg.generate(&mut [0u8; 16], &[]).is_ok());
+    }
+
+}
@@ -0,0 +1,255 @@
+//! HKDF (RFC 5869): turning one secret into as many keys as you need.
+//!
+//! It runs in two steps, both built on HMAC:
+//!
+//! * **extract** concentrates whatever entropy the input keying material has
+//!   (a Diffie-Hellman shared secret, a master password hash) into a
+//!   pseudorandom key, `PRK = HMAC(salt, IKM)`
+//! * **expand** stretches the PRK out into output keying material, labelled by
+//!   an `info` string so that different uses of the same PRK get unrelated
+//!   keys: `T(i) = HMAC(PRK, T(i - 1) || info || i)`, and the output is
+//!   `T(1) || T(2) || ...` cut to length
+//!
+//! Since the counter `i` is a single byte, expand can produce at most 255
+//! blocks of hash output (8160 bytes for SHA-256).
+
+use crate::digest::Digest;
+use crate::hmac::{hmac, Hmac};
+use std::fmt;
+use std::marker::PhantomData;
+
+const MAXIMUM_BLOCKS: usize = 255;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HkdfError {
+    /// More output was asked for than 255 blocks of the hash.
+    OutputTooLong { requested: usize, maximum: usize },
+    /// A PRK given to `Hkdf::from_prk` was shorter than the hash output.
+    PrkTooShort { length: usize, minimum: usize },
+}
+
+impl fmt::Display for HkdfError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HkdfError::OutputTooLong { requested, maximum } => write!(
+                formatter,
+                "HKDF can produce at most {} bytes with this hash, but {} were requested",
+                maximum, requested
+            ),
+            HkdfError::PrkTooShort { length, minimum } => write!(
+                formatter,
+                "an HKDF pseudorandom key must be at least {} bytes, but this one is {}",
+                minimum, length
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HkdfError {}
+
+/// HKDF-Extract. An empty salt is the same as a salt of `OUTPUT_LENGTH` zero
+/// bytes, which is what the RFC says to use when there isn't one.
+pub fn extract<D: Digest>(salt: &[u8], input_key_material: &[u8]) -> D::Output {
+    hmac::<D>(salt, input_key_material)
+}
+
+/// HKDF-Expand, filling all of `output`.
+pub fn expand<D: Digest>(
+    pseudorandom_key: &[u8],
+    info: &[u8],
+    output: &mut [u8],
+) -> Result<(), HkdfError> {
+    let maximum = MAXIMUM_BLOCKS * D::OUTPUT_LENGTH;
+    if output.len() > maximum {
+        return Err(HkdfError::OutputTooLong {
+            requested: output.len(),
+            maximum,
+        });
+    }
+
+    // keyed once, and cloned for every block
+    let keyed = Hmac::<D>::new(pseudorandom_key);
+    let mut previous: Option<D::Output> = None;
+
+    for (index, piece) in output.chunks_mut(D::OUTPUT_LENGTH).enumerate() {
+        let mut mac = keyed.clone();
+        if let Some(previous) = &previous {
+            mac.update(previous.as_ref());
+        }
+        mac.update(info);
+        mac.update(&[index as u8 + 1]);
+
+        let block = mac.finalize();
+        piece.copy_from_slice(&block.as_ref()[..piece.len()]);
+        previous = Some(block);
+    }
+
+    Ok(())
+}
+
+/// A pseudorandom key, ready to expand into any number of keys.
+#[derive(Clone)]
+pub struct Hkdf<D: Digest> {
+    pseudorandom_key: Vec<u8>,
+    hash: PhantomData<D>,
+}
+
+impl<D: Digest> Hkdf<D> {
+    /// Runs extract on the input keying material.
+    pub fn new(salt: &[u8], input_key_material: &[u8]) -> Self {
+        Hkdf {
+            pseudorandom_key: extract::<D>(salt, input_key_material).as_ref().to_vec(),
+            hash: PhantomData,
+        }
+    }
+
+    /// Skips extract, for a key that's already uniformly random (such as one
+    /// that came out of an earlier expand).
+    pub fn from_prk(pseudorandom_key: &[u8]) -> Result<Self, HkdfError> {
+        if pseudorandom_key.len() < D::OUTPUT_LENGTH {
+            return Err(HkdfError::PrkTooShort {
+                length: pseudorandom_key.len(),
+                minimum: D::OUTPUT_LENGTH,
+            });
+        }
+
+        Ok(Hkdf {
+            pseudorandom_key: pseudorandom_key.to_vec(),
+            hash: PhantomData,
+        })
+    }
+
+    pub fn pseudorandom_key(&self) -> &[u8] {
+        &self.pseudorandom_key
+    }
+
+    pub fn expand(&self, info: &[u8], output: &mut [u8]) -> Result<(), HkdfError> {
+        expand::<D>(&self.pseudorandom_key, info, output)
+    }
+
+    /// `expand`, into a new `Vec` of `length` bytes.
+    pub fn expand_to_vec(&self, info: &[u8], length: usize) -> Result<Vec<u8>, HkdfError> {
+        let mut output = vec![0u8; length];
+        self.expand(info, &mut output)?;
+        Ok(output)
+    }
+}
+
+/// Extract and expand in one go.
+pub fn hkdf<D: Digest>(
+    salt: &[u8],
+    input_key_material: &[u8],
+    info: &[u8],
+    length: usize,
+) -> Result<Vec<u8>, HkdfError> {
+    Hkdf::<D>::new(salt, input_key_material).expand_to_vec(info, length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::Sha256;
+    use crate::utils::bytes_to_hex;
+    use crate::Sha1;
+
+    fn counting(from: u8, to: u8) -> Vec<u8> {
+        (from..=to).collect()
+    }
+
+    // RFC 5869, appendix A
+    #[test]
+    fn test_case_1() {
+        let hkdf = Hkdf::<Sha256>::new(&counting(0x00, 0x0c), &[0x0b; 22]);
+        assert_eq!(
+            "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+            bytes_to_hex(hkdf.pseudorandom_key())
+        );
+        assert_eq!(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+            bytes_to_hex(&hkdf.expand_to_vec(&counting(0xf0, 0xf9), 42).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_case_2() {
+        let okm = hkdf::<Sha256>(
+            &counting(0x60, 0xaf),
+            &counting(0x00, 0x4f),
+            &counting(0xb0, 0xff),
+            82,
+        )
+        .unwrap();
+        assert_eq!(
+            "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87",
+            bytes_to_hex(&okm)
+        );
+    }
+
+    #[test]
+    fn test_case_3() {
+        // no salt and no info
+        let hkdf = Hkdf::<Sha256>::new(&[], &[0x0b; 22]);
+        assert_eq!(
+            "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+            bytes_to_hex(hkdf.pseudorandom_key())
+        );
+        assert_eq!(
+            "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8",
+            bytes_to_hex(&hkdf.expand_to_vec(&[], 42).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_case_4() {
+        let hkdf = Hkdf::<Sha1>::new(&counting(0x00, 0x0c), &[0x0b; 11]);
+        assert_eq!(
+            "9b6c18c432a7bf8f0e71c8eb88f4b30baa2ba243",
+            bytes_to_hex(hkdf.pseudorandom_key())
+        );
+        assert_eq!(
+            "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896",
+            bytes_to_hex(&hkdf.expand_to_vec(&counting(0xf0, 0xf9), 42).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_case_7() {
+        let hkdf = Hkdf::<Sha1>::new(&[], &[0x0c; 22]);
+        assert_eq!(
+            "2adccada18779e7c2077ad2eb19d3f3e731385dd",
+            bytes_to_hex(hkdf.pseudorandom_key())
+        );
+        assert_eq!(
+            "2c91117204d745f3500d636a62f64f0ab3bae548aa53d423b0d1f27ebba6f5e5673a081d70cce7acfc48",
+            bytes_to_hex(&hkdf.expand_to_vec(&[], 42).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let hkdf = Hkdf::<Sha256>::new(b"salt", b"secret");
+        assert_eq!(8160, hkdf.expand_to_vec(b"", 255 * 32).unwrap().len());
+        assert_eq!(
+            Err(HkdfError::OutputTooLong {
+                requested: 8161,
+                maximum: 8160
+            }),
+            hkdf.expand_to_vec(b"", 8161)
+        );
+
+        assert_eq!(
+            HkdfError::PrkTooShort {
+                length: 16,
+                minimum: 32
+            },
+            Hkdf::<Sha256>::from_prk(&[0; 16]).err().unwrap()
+        );
+
+        let from_prk = Hkdf::<Sha256>::from_prk(hkdf.pseudorandom_key()).unwrap();
+        assert_eq!(
+            hkdf.expand_to_vec(b"tenant 42", 32),
+            from_prk.expand_to_vec(b"tenant 42", 32)
+        );
+    }
+
+}
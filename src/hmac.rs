@@ -0,0 +1,194 @@
+//! HMAC (RFC 2104): a message authentication code built from any hash.
+//!
+//! Just hashing `key || message` isn't safe with MD5, SHA-1 or SHA-256 --
+//! anyone who sees one tag can extend the message and compute a valid tag for
+//! the longer one. HMAC hashes twice instead, with the key mixed in on both
+//! sides:
+//!
+//! `H((key ^ opad) || H((key ^ ipad) || message))`
+//!
+//! where the key is padded with zeroes to one block (or hashed first, if it's
+//! longer than a block, and then padded to one block or one hash output,
+//! whichever is longer), `ipad` is the byte `0x36` repeated and `opad` is
+//! `0x5c` repeated.
+
+use crate::digest::Digest;
+use crate::md5::Md5;
+use crate::sha2::Sha256;
+use crate::utils::{bytes_to_hex, constant_time_eq};
+use crate::Sha1;
+
+const INNER_PAD: u8 = 0x36;
+const OUTER_PAD: u8 = 0x5c;
+
+/// HMAC over any of the crate's hashes. Feed the message through `update`,
+/// then either `finalize` for the tag or `verify` to check one.
+#[derive(Clone)]
+pub struct Hmac<D: Digest> {
+    inner: D,
+    outer: D,
+}
+
+pub type HmacMd5 = Hmac<Md5>;
+pub type HmacSha1 = Hmac<Sha1>;
+pub type HmacSha256 = Hmac<Sha256>;
+
+impl<D: Digest> Hmac<D> {
+    /// Any length of key works, but keys shorter than the hash output are
+    /// weaker than they need to be.
+    pub fn new(key: &[u8]) -> Self {
+        // a few hashes here (the checksums, Ascon) have outputs longer than
+        // their blocks, and a hashed key has to fit too
+        let mut block_key = vec![0u8; D::BLOCK_LENGTH.max(D::OUTPUT_LENGTH)];
+        if key.len() > D::BLOCK_LENGTH {
+            let hashed_key = D::digest(key);
+            block_key[..D::OUTPUT_LENGTH].copy_from_slice(hashed_key.as_ref());
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+
+        // both hashes start with a keyed block, so they can be started right
+        // away -- and cloning a keyed `Hmac` skips redoing that work
+        let mut inner = D::default();
+        inner.update(
+            &block_key
+                .iter()
+                .map(|byte| byte ^ INNER_PAD)
+                .collect::<Vec<u8>>(),
+        );
+
+        let mut outer = D::default();
+        outer.update(
+            &block_key
+                .iter()
+                .map(|byte| byte ^ OUTER_PAD)
+                .collect::<Vec<u8>>(),
+        );
+
+        Hmac { inner, outer }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.inner.update(bytes);
+    }
+
+    pub fn finalize(self) -> D::Output {
+        let Hmac { inner, mut outer } = self;
+        outer.update(inner.finalize().as_ref());
+        outer.finalize()
+    }
+
+    /// Checks a tag someone sent us, without giving away how close a wrong
+    /// one came. Always use this rather than comparing with `==`.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        constant_time_eq(self.finalize().as_ref(), tag)
+    }
+}
+
+/// Hashes a message with HMAC in one go -- the `Digest::digest` of HMAC.
+pub fn hmac<D: Digest>(key: &[u8], message: &[u8]) -> D::Output {
+    let mut hmac = Hmac::<D>::new(key);
+    hmac.update(message);
+    hmac.finalize()
+}
+
+pub fn hmac_md5(key: &str, raw_message: &str) -> String {
+    bytes_to_hex(&hmac::<Md5>(key.as_bytes(), raw_message.as_bytes()))
+}
+
+pub fn hmac_sha1(key: &str, raw_message: &str) -> String {
+    bytes_to_hex(&hmac::<Sha1>(key.as_bytes(), raw_message.as_bytes()))
+}
+
+pub fn hmac_sha256(key: &str, raw_message: &str) -> String {
+    bytes_to_hex(&hmac::<Sha256>(key.as_bytes(), raw_message.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOX: &str = "The quick brown fox jumps over the lazy dog";
+
+    #[test]
+    fn test_hmac() {
+        assert_eq!("80070713463e7749b90c2dc24911e275", hmac_md5("key", FOX));
+        assert_eq!(
+            "de7c9b85b8b78aa6bc8a7a36f70a90701c9db4d9",
+            hmac_sha1("key", FOX)
+        );
+        assert_eq!(
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8",
+            hmac_sha256("key", FOX)
+        );
+    }
+
+    #[test]
+    fn test_rfc_4231() {
+        // test case 2
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            hmac_sha256("Jefe", "what do ya want for nothing?")
+        );
+
+        // test case 6: a key longer than the block gets hashed first
+        assert_eq!(
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            bytes_to_hex(&hmac::<Sha256>(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_output_longer_than_block() {
+        // Ascon-Hash256 has 8-byte blocks and 32-byte outputs, so a 16-byte
+        // key is hashed to something longer than a block
+        use crate::ascon::AsconHash256;
+
+        let key = [0x0b; 16];
+        let hashed_key = AsconHash256::digest(&key);
+        let padded = |pad: u8| {
+            hashed_key
+                .iter()
+                .map(|byte| byte ^ pad)
+                .collect::<Vec<u8>>()
+        };
+
+        let inner = AsconHash256::digest(&[&padded(INNER_PAD)[..], FOX.as_bytes()].concat());
+        let expected = AsconHash256::digest(&[&padded(OUTER_PAD)[..], &inner[..]].concat());
+        assert_eq!(expected, hmac::<AsconHash256>(&key, FOX.as_bytes()));
+
+        let mut crc = Hmac::<crate::checksum::Crc32>::new(b"longer than one byte");
+        crc.update(FOX.as_bytes());
+        assert_eq!(4, crc.finalize().len());
+    }
+
+    #[test]
+    fn test_streaming() {
+        let mut mac = HmacSha256::new(b"key");
+        for piece in FOX.as_bytes().chunks(5) {
+            mac.update(piece);
+        }
+
+        let tag = mac.clone().finalize();
+        assert_eq!(hmac::<Sha256>(b"key", FOX.as_bytes()), tag);
+        assert!(mac.verify(&tag));
+    }
+
+    #[test]
+    fn test_verify() {
+        let tag = hmac::<Sha1>(b"key", FOX.as_bytes());
+
+        let mut mac = HmacSha1::new(b"key");
+        mac.update(FOX.as_bytes());
+        assert!(mac.clone().verify(&tag));
+
+        let mut forged = tag;
+        forged[19] ^= 1;
+        assert!(!mac.clone().verify(&forged));
+        assert!(!mac.verify(&tag[..19]));
+    }
+
+}
@@ -0,0 +1,371 @@
+//! The password hashes in Apache `htpasswd` files and LDAP `userPassword`
+//! attributes.
+//!
+//! The LDAP ones (RFC 2307 and its unofficial salted follow-ups) are all the
+//! same shape: a scheme in braces, then base64 of the digest with the salt,
+//! if there is one, tacked onto the end.
+//!
+//! ```text
+//! {SSHA}base64(SHA-1(password || salt) || salt)
+//! ```
+//!
+//! so the salt is whatever comes after the digest. Apache's `$apr1$` is
+//! md5crypt (see `crypt`) with `$apr1$` mixed in instead of `$1$`. htpasswd
+//! files also hold `{SHA}`, plus bcrypt and DES crypt hashes, which aren't
+//! handled here.
+//!
+//! A single unsalted hash of the password is fast to brute-force, and even
+//! the salted ones are only one hash deep. These exist to check credentials
+//! that were stored this way long ago, so that they can be rehashed with
+//! something stronger.
+
+use crate::crypt::{decode_md5crypt, encode_md5crypt, md5crypt, ALPHABET};
+use crate::digest::Digest;
+use crate::md5::Md5;
+use crate::sha2::Sha256;
+use crate::utils::{base64_decode, base64_encode, constant_time_eq};
+use crate::Sha1;
+use std::fmt;
+use std::str::FromStr;
+
+const APR1_MAGIC: &str = "$apr1$";
+const APR1_MAXIMUM_SALT_LENGTH: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HtpasswdError {
+    /// A `{SCHEME}` or `$id$` that isn't one of the supported ones.
+    UnknownScheme(String),
+    /// The part after the scheme isn't valid base64, or isn't a valid
+    /// `$apr1$` salt and hash.
+    InvalidEncoding,
+    /// The decoded hash is too short for the scheme's digest, or (for the
+    /// unsalted schemes) longer than it.
+    WrongLength { expected: usize, actual: usize },
+    /// A salt given to an unsalted scheme, or an `$apr1$` salt with
+    /// characters outside `[./0-9A-Za-z]`.
+    InvalidSalt,
+}
+
+impl fmt::Display for HtpasswdError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HtpasswdError::UnknownScheme(scheme) => {
+                write!(formatter, "unsupported password scheme \"{}\"", scheme)
+            }
+            HtpasswdError::InvalidEncoding => write!(formatter, "the stored hash is malformed"),
+            HtpasswdError::WrongLength { expected, actual } => write!(
+                formatter,
+                "expected a {}-byte digest, but the stored hash has {} bytes",
+                expected, actual
+            ),
+            HtpasswdError::InvalidSalt => write!(formatter, "this scheme can't use that salt"),
+        }
+    }
+}
+
+impl std::error::Error for HtpasswdError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// `{SHA}`: SHA-1 of the password.
+    Sha,
+    /// `{SSHA}`: SHA-1 of the password and salt.
+    SaltedSha,
+    /// `{MD5}`
+    Md5,
+    /// `{SMD5}`
+    SaltedMd5,
+    /// `{SSHA256}`
+    SaltedSha256,
+    /// `$apr1$`, Apache's md5crypt.
+    Apr1,
+}
+
+impl Scheme {
+    /// How the scheme starts a stored hash.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Scheme::Sha => "{SHA}",
+            Scheme::SaltedSha => "{SSHA}",
+            Scheme::Md5 => "{MD5}",
+            Scheme::SaltedMd5 => "{SMD5}",
+            Scheme::SaltedSha256 => "{SSHA256}",
+            Scheme::Apr1 => APR1_MAGIC,
+        }
+    }
+
+    pub fn is_salted(self) -> bool {
+        !matches!(self, Scheme::Sha | Scheme::Md5)
+    }
+
+    pub fn digest_length(self) -> usize {
+        match self {
+            Scheme::Sha | Scheme::SaltedSha => Sha1::OUTPUT_LENGTH,
+            Scheme::Md5 | Scheme::SaltedMd5 | Scheme::Apr1 => Md5::OUTPUT_LENGTH,
+            Scheme::SaltedSha256 => Sha256::OUTPUT_LENGTH,
+        }
+    }
+
+    fn digest(self, password: &[u8], salt: &[u8]) -> Vec<u8> {
+        let input = [password, salt].concat();
+        match self {
+            Scheme::Sha | Scheme::SaltedSha => Sha1::digest(&input).to_vec(),
+            Scheme::Md5 | Scheme::SaltedMd5 => Md5::digest(&input).to_vec(),
+            Scheme::SaltedSha256 => Sha256::digest(&input).to_vec(),
+            Scheme::Apr1 => md5crypt(password, APR1_MAGIC, salt).to_vec(),
+        }
+    }
+}
+
+/// A stored password hash, taken apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PasswordHash {
+    pub scheme: Scheme,
+    /// Raw bytes for the LDAP schemes; the salt's characters for `$apr1$`.
+    pub salt: Vec<u8>,
+    pub digest: Vec<u8>,
+}
+
+impl PasswordHash {
+    /// Hashes `password` with `salt`, which should be empty for `{SHA}` and
+    /// `{MD5}`. LDAP servers mostly use 4 or 8 random bytes; `$apr1$` takes
+    /// up to 8 characters of `[./0-9A-Za-z]`, such as
+    /// `crypt::generate_salt(CryptScheme::Md5, random)` makes.
+    pub fn new(scheme: Scheme, password: &[u8], salt: &[u8]) -> Result<Self, HtpasswdError> {
+        let valid_salt = match scheme {
+            Scheme::Sha | Scheme::Md5 => salt.is_empty(),
+            Scheme::Apr1 => {
+                salt.len() <= APR1_MAXIMUM_SALT_LENGTH
+                    && salt.iter().all(|byte| ALPHABET.contains(byte))
+            }
+            _ => true,
+        };
+        if !valid_salt {
+            return Err(HtpasswdError::InvalidSalt);
+        }
+
+        Ok(PasswordHash {
+            scheme,
+            salt: salt.to_vec(),
+            digest: scheme.digest(password, salt),
+        })
+    }
+
+    pub fn verify(&self, password: &[u8]) -> bool {
+        constant_time_eq(&self.scheme.digest(password, &self.salt), &self.digest)
+    }
+}
+
+impl FromStr for PasswordHash {
+    type Err = HtpasswdError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = text.strip_prefix(APR1_MAGIC) {
+            let separator = rest.find('$').ok_or(HtpasswdError::InvalidEncoding)?;
+            let (salt, hash) = (&rest[..separator], &rest[separator + 1..]);
+            if salt.len() > APR1_MAXIMUM_SALT_LENGTH
+                || !salt.bytes().all(|byte| ALPHABET.contains(&byte))
+            {
+                return Err(HtpasswdError::InvalidSalt);
+            }
+
+            return Ok(PasswordHash {
+                scheme: Scheme::Apr1,
+                salt: salt.as_bytes().to_vec(),
+                digest: decode_md5crypt(hash)
+                    .ok_or(HtpasswdError::InvalidEncoding)?
+                    .to_vec(),
+            });
+        }
+
+        // LDAP scheme names are case-insensitive
+        let end = match text.find('}') {
+            Some(end) if text.starts_with('{') => end,
+            _ => {
+                // just the `$id$`, so as not to put the hash itself in an error
+                let scheme = text
+                    .strip_prefix('$')
+                    .and_then(|rest| rest.split('$').next())
+                    .map(|id| format!("${}$", id))
+                    .unwrap_or_default();
+                return Err(HtpasswdError::UnknownScheme(scheme));
+            }
+        };
+        let scheme = match text[..=end].to_ascii_uppercase().as_str() {
+            "{SHA}" => Scheme::Sha,
+            "{SSHA}" => Scheme::SaltedSha,
+            "{MD5}" => Scheme::Md5,
+            "{SMD5}" => Scheme::SaltedMd5,
+            "{SSHA256}" => Scheme::SaltedSha256,
+            _ => return Err(HtpasswdError::UnknownScheme(text[..=end].to_string())),
+        };
+
+        let mut decoded = base64_decode(&text[end + 1..]).ok_or(HtpasswdError::InvalidEncoding)?;
+        let expected = scheme.digest_length();
+        if decoded.len() < expected || (!scheme.is_salted() && decoded.len() != expected) {
+            return Err(HtpasswdError::WrongLength {
+                expected,
+                actual: decoded.len(),
+            });
+        }
+
+        let salt = decoded.split_off(expected);
+        Ok(PasswordHash {
+            scheme,
+            salt,
+            digest: decoded,
+        })
+    }
+}
+
+impl fmt::Display for PasswordHash {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if let Scheme::Apr1 = self.scheme {
+            let mut digest = [0u8; 16];
+            digest.copy_from_slice(&self.digest);
+            write!(
+                formatter,
+                "{}{}${}",
+                APR1_MAGIC,
+                String::from_utf8_lossy(&self.salt),
+                encode_md5crypt(&digest)
+            )
+        } else {
+            let encoded = base64_encode(&[&self.digest[..], &self.salt[..]].concat());
+            write!(formatter, "{}{}", self.scheme.prefix(), encoded)
+        }
+    }
+}
+
+/// Checks `password` against a stored hash in any of the supported schemes.
+pub fn verify(password: &[u8], stored: &str) -> Result<bool, HtpasswdError> {
+    Ok(stored.parse::<PasswordHash>()?.verify(password))
+}
+
+/// The `user:hash` entries of an htpasswd file, skipping blank lines and
+/// `#` comments.
+pub fn parse_htpasswd(contents: &str) -> Vec<(&str, &str)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let separator = line.find(':')?;
+            Some((&line[..separator], &line[separator + 1..]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsalted() {
+        let hash = PasswordHash::new(Scheme::Sha, b"password", b"").unwrap();
+        assert_eq!("{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=", hash.to_string());
+        let hash = PasswordHash::new(Scheme::Md5, b"password", b"").unwrap();
+        assert_eq!("{MD5}X03MO1qnZdYdgyfeuILPmQ==", hash.to_string());
+
+        assert_eq!(
+            Err(HtpasswdError::InvalidSalt),
+            PasswordHash::new(Scheme::Sha, b"password", b"salt")
+        );
+        assert_eq!(
+            Err(HtpasswdError::WrongLength {
+                expected: 20,
+                actual: 24
+            }),
+            "{SHA}ed2BWy3mDxA7a0HM3oCWtw1YwdgSNFZ4".parse::<PasswordHash>()
+        );
+    }
+
+    #[test]
+    fn test_salted() {
+        let salt = [0x12, 0x34, 0x56, 0x78];
+        let examples = [
+            (Scheme::SaltedSha, "{SSHA}ed2BWy3mDxA7a0HM3oCWtw1YwdgSNFZ4"),
+            (Scheme::SaltedMd5, "{SMD5}jBH0trVXpZVij5O3IWEpqhI0Vng="),
+        ];
+        for (scheme, stored) in examples.iter() {
+            let hash = PasswordHash::new(*scheme, b"secret", &salt).unwrap();
+            assert_eq!(*stored, hash.to_string());
+            assert_eq!(Ok(hash), stored.parse::<PasswordHash>());
+            assert_eq!(Ok(true), verify(b"secret", stored));
+            assert_eq!(Ok(false), verify(b"Secret", stored));
+        }
+
+        let stored = "{SSHA256}lAejl/Of0h/+J/b6px7T8LLPd/QxmtSbDDvk8djjRJEAAQIDBAUGBw==";
+        let hash = stored.parse::<PasswordHash>().unwrap();
+        assert_eq!((0..8).collect::<Vec<u8>>(), hash.salt);
+        assert!(hash.verify(b"secret"));
+
+        // LDAP servers don't agree on the case of the scheme
+        assert_eq!(
+            Ok(true),
+            verify(b"secret", "{ssha}ed2BWy3mDxA7a0HM3oCWtw1YwdgSNFZ4")
+        );
+    }
+
+    #[test]
+    fn test_apr1() {
+        let hash = PasswordHash::new(Scheme::Apr1, b"password", b"saltsalt").unwrap();
+        assert_eq!("$apr1$saltsalt$yAAkm4libquA.ZWLHbSBq/", hash.to_string());
+        assert_eq!(
+            Ok(hash),
+            "$apr1$saltsalt$yAAkm4libquA.ZWLHbSBq/".parse::<PasswordHash>()
+        );
+        assert_eq!(
+            Ok(true),
+            verify(b"Hello world!", "$apr1$x$JTvpALI9/43YgVodh/vfV0")
+        );
+        assert_eq!(
+            Ok(false),
+            verify(b"Hello world", "$apr1$x$JTvpALI9/43YgVodh/vfV0")
+        );
+
+        assert_eq!(
+            Err(HtpasswdError::InvalidSalt),
+            PasswordHash::new(Scheme::Apr1, b"password", b"salt:salt")
+        );
+        // the last character only carries two bits
+        assert_eq!(
+            Err(HtpasswdError::InvalidEncoding),
+            "$apr1$x$JTvpALI9/43YgVodh/vfVz".parse::<PasswordHash>()
+        );
+    }
+
+    #[test]
+    fn test_htpasswd_file() {
+        let contents = "# users\nalice:{SHA}W6ph5Mm5Pz8GgiULbPgzG37mj9g=\n\nbob:$apr1$saltsalt$yAAkm4libquA.ZWLHbSBq/\ncarol:$2y$05$c4WoMPo3SXsafkva.HHa6uXQZWr7oboPiC2bT/r7q1BB8I2s0BRqC\n";
+        let entries = parse_htpasswd(contents);
+        assert_eq!(3, entries.len());
+
+        assert_eq!(
+            ("alice", Ok(true)),
+            (entries[0].0, verify(b"password", entries[0].1))
+        );
+        assert_eq!(
+            ("bob", Ok(true)),
+            (entries[1].0, verify(b"password", entries[1].1))
+        );
+        assert_eq!(
+            Err(HtpasswdError::UnknownScheme(String::from("$2y$"))),
+            verify(b"password", entries[2].1)
+        );
+        assert_eq!(
+            Err(HtpasswdError::UnknownScheme(String::from("{CRYPT}"))),
+            verify(b"password", "{CRYPT}$1$saltsalt$qjXMvbEw8oaL.CzflDtaK/")
+        );
+        // DES crypt has no marker at all
+        assert_eq!(
+            Err(HtpasswdError::UnknownScheme(String::new())),
+            verify(b"password", "saHW9GdxihkGQ")
+        );
+        assert_eq!(
+            Err(HtpasswdError::InvalidEncoding),
+            verify(b"password", "{SSHA}not base64!")
+        );
+    }
+
+}
@@ -0,0 +1,1029 @@
+//! HTTP Digest access authentication (RFC 7616, which replaces RFC 2617).
+//!
+//! The server challenges with a nonce, and the client proves it knows the
+//! password by hashing it together with that nonce and the request:
+//!
+//! ```text
+//! HA1      = H(username ":" realm ":" password)
+//! HA2      = H(method ":" uri)                    (or ":" H(body) too, for auth-int)
+//! response = H(HA1 ":" nonce ":" nc ":" cnonce ":" qop ":" HA2)
+//! ```
+//!
+//! with `H` giving lowercase hex. The `-sess` algorithms replace HA1 with
+//! `H(HA1 ":" nonce ":" cnonce)`, and a server that sends no `qop` at all
+//! gets the RFC 2069 response, `H(HA1 ":" nonce ":" HA2)`.
+//!
+//! The nonce count `nc` goes up by one with every request made under the
+//! same nonce, so that a server which remembers the last one it saw can turn
+//! away replayed requests. `DigestClient` and `NonceTracker` keep track of
+//! it on each side.
+//!
+//! Servers only need HA1, not the password, which is why it's what they
+//! usually store. It's still a password equivalent for that realm, though.
+
+use crate::digest::Digest;
+use crate::md5::Md5;
+use crate::sha2::{Sha256, Sha512_256};
+use crate::utils::{bytes_to_hex, constant_time_eq};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DigestAuthError {
+    /// The header isn't for the `Digest` scheme.
+    NotDigest,
+    /// The parameters aren't a valid comma-separated list of `name=value`.
+    Malformed,
+    MissingParameter(&'static str),
+    UnknownAlgorithm(String),
+    /// A `qop` other than `auth` and `auth-int`, or a challenge that only
+    /// offers ones like that.
+    UnsupportedQop(String),
+    /// An `nc` that isn't 8 hex digits.
+    InvalidNonceCount(String),
+    /// A value with a control character in it, which can't go in a header.
+    InvalidCharacter(&'static str),
+}
+
+impl fmt::Display for DigestAuthError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DigestAuthError::NotDigest => write!(formatter, "not a Digest authentication header"),
+            DigestAuthError::Malformed => write!(formatter, "malformed Digest parameters"),
+            DigestAuthError::MissingParameter(name) => {
+                write!(formatter, "the Digest header has no \"{}\"", name)
+            }
+            DigestAuthError::UnknownAlgorithm(name) => {
+                write!(formatter, "unsupported Digest algorithm \"{}\"", name)
+            }
+            DigestAuthError::UnsupportedQop(qop) => {
+                write!(formatter, "unsupported quality of protection \"{}\"", qop)
+            }
+            DigestAuthError::InvalidNonceCount(count) => {
+                write!(formatter, "\"{}\" isn't a valid nonce count", count)
+            }
+            DigestAuthError::InvalidCharacter(name) => {
+                write!(
+                    formatter,
+                    "the Digest \"{}\" has a control character in it",
+                    name
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DigestAuthError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+    Sha512_256,
+    Sha512_256Sess,
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Md5 => "MD5",
+            Algorithm::Md5Sess => "MD5-sess",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha256Sess => "SHA-256-sess",
+            Algorithm::Sha512_256 => "SHA-512-256",
+            Algorithm::Sha512_256Sess => "SHA-512-256-sess",
+        }
+    }
+
+    pub fn is_session(self) -> bool {
+        matches!(
+            self,
+            Algorithm::Md5Sess | Algorithm::Sha256Sess | Algorithm::Sha512_256Sess
+        )
+    }
+
+    /// `H(data)`, as lowercase hex.
+    pub fn hash(self, data: &[u8]) -> String {
+        match self {
+            Algorithm::Md5 | Algorithm::Md5Sess => bytes_to_hex(&Md5::digest(data)),
+            Algorithm::Sha256 | Algorithm::Sha256Sess => bytes_to_hex(&Sha256::digest(data)),
+            Algorithm::Sha512_256 | Algorithm::Sha512_256Sess => {
+                bytes_to_hex(&Sha512_256::digest(data))
+            }
+        }
+    }
+
+    fn hash_fields(self, fields: &[&str]) -> String {
+        self.hash(fields.join(":").as_bytes())
+    }
+}
+
+impl Default for Algorithm {
+    /// What a header without an `algorithm` means.
+    fn default() -> Self {
+        Algorithm::Md5
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = DigestAuthError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [
+            Algorithm::Md5,
+            Algorithm::Md5Sess,
+            Algorithm::Sha256,
+            Algorithm::Sha256Sess,
+            Algorithm::Sha512_256,
+            Algorithm::Sha512_256Sess,
+        ]
+        .iter()
+        .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+        .copied()
+        .ok_or_else(|| DigestAuthError::UnknownAlgorithm(name.to_string()))
+    }
+}
+
+/// The quality of protection: whether the request body goes into the
+/// response too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Qop {
+    Auth,
+    AuthInt,
+}
+
+impl Qop {
+    pub fn name(self) -> &'static str {
+        match self {
+            Qop::Auth => "auth",
+            Qop::AuthInt => "auth-int",
+        }
+    }
+}
+
+impl FromStr for Qop {
+    type Err = DigestAuthError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "auth" => Ok(Qop::Auth),
+            "auth-int" => Ok(Qop::AuthInt),
+            _ => Err(DigestAuthError::UnsupportedQop(name.to_string())),
+        }
+    }
+}
+
+/// `HA1 = H(username ":" realm ":" password)`, before any `-sess` step.
+pub fn ha1(algorithm: Algorithm, username: &str, realm: &str, password: &str) -> String {
+    algorithm.hash_fields(&[username, realm, password])
+}
+
+/// `HA2`; `body` only counts for `auth-int`.
+pub fn ha2(algorithm: Algorithm, qop: Option<Qop>, method: &str, uri: &str, body: &[u8]) -> String {
+    match qop {
+        Some(Qop::AuthInt) => algorithm.hash_fields(&[method, uri, &algorithm.hash(body)]),
+        _ => algorithm.hash_fields(&[method, uri]),
+    }
+}
+
+// the parameter list after the scheme: `name=token` or `name="quoted
+// string"`, separated by commas, with backslash escapes in quoted strings
+fn parse_parameters(text: &str) -> Result<HashMap<String, String>, DigestAuthError> {
+    let mut parameters = HashMap::new();
+    let mut characters = text.chars().peekable();
+
+    loop {
+        while let Some(character) = characters.peek() {
+            if character.is_whitespace() || *character == ',' {
+                characters.next();
+            } else {
+                break;
+            }
+        }
+        if characters.peek().is_none() {
+            return Ok(parameters);
+        }
+
+        let mut name = String::new();
+        while let Some(character) = characters.peek() {
+            if *character == '=' || character.is_whitespace() {
+                break;
+            }
+            name.push(*character);
+            characters.next();
+        }
+        while characters
+            .peek()
+            .is_some_and(|character| character.is_whitespace())
+        {
+            characters.next();
+        }
+        if name.is_empty() || characters.next() != Some('=') {
+            return Err(DigestAuthError::Malformed);
+        }
+        while characters
+            .peek()
+            .is_some_and(|character| character.is_whitespace())
+        {
+            characters.next();
+        }
+
+        let mut value = String::new();
+        if characters.peek() == Some(&'"') {
+            characters.next();
+            loop {
+                match characters.next() {
+                    Some('"') => break,
+                    Some('\\') => value.push(characters.next().ok_or(DigestAuthError::Malformed)?),
+                    Some(character) if is_forbidden(character) => {
+                        return Err(DigestAuthError::Malformed)
+                    }
+                    Some(character) => value.push(character),
+                    None => return Err(DigestAuthError::Malformed),
+                }
+            }
+        } else {
+            while let Some(character) = characters.peek() {
+                if *character == ',' || character.is_whitespace() {
+                    break;
+                }
+                if is_forbidden(*character) {
+                    return Err(DigestAuthError::Malformed);
+                }
+                value.push(*character);
+                characters.next();
+            }
+        }
+
+        // whatever follows a value has to be the end or a comma
+        while characters
+            .peek()
+            .is_some_and(|character| character.is_whitespace())
+        {
+            characters.next();
+        }
+        if !matches!(characters.peek(), None | Some(',')) {
+            return Err(DigestAuthError::Malformed);
+        }
+
+        parameters.insert(name.to_ascii_lowercase(), value);
+    }
+}
+
+fn strip_scheme(header: &str) -> Result<&str, DigestAuthError> {
+    let header = header.trim_start();
+    let scheme_end = header.find(char::is_whitespace).unwrap_or(header.len());
+    if header[..scheme_end].eq_ignore_ascii_case("Digest") {
+        Ok(&header[scheme_end..])
+    } else {
+        Err(DigestAuthError::NotDigest)
+    }
+}
+
+fn required(
+    parameters: &mut HashMap<String, String>,
+    name: &'static str,
+) -> Result<String, DigestAuthError> {
+    parameters
+        .remove(name)
+        .ok_or(DigestAuthError::MissingParameter(name))
+}
+
+// Control characters can't be escaped in a quoted string: a CR or LF would
+// end the header early, and let whatever follows be read as another header.
+// Tabs are the one exception.
+fn is_forbidden(character: char) -> bool {
+    character.is_control() && character != '\t'
+}
+
+fn check_characters(values: &[(&'static str, &str)]) -> Result<(), DigestAuthError> {
+    match values
+        .iter()
+        .find(|(_, value)| value.chars().any(is_forbidden))
+    {
+        Some((name, _)) => Err(DigestAuthError::InvalidCharacter(name)),
+        None => Ok(()),
+    }
+}
+
+// the fields are public, so formatting checks them again rather than trust
+// that they came through `new` or `authorize`
+fn quote(name: &'static str, value: &str) -> Result<String, DigestAuthError> {
+    check_characters(&[(name, value)])?;
+    Ok(format!(
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+/// The parts of an HTTP request a client's response covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Request<'a> {
+    pub method: &'a str,
+    /// The request target, exactly as it appears in the request line.
+    pub uri: &'a str,
+    /// Only hashed for `auth-int`.
+    pub body: &'a [u8],
+}
+
+impl<'a> Request<'a> {
+    pub fn new(method: &'a str, uri: &'a str) -> Self {
+        Request {
+            method,
+            uri,
+            body: &[],
+        }
+    }
+
+    pub fn with_body(self, body: &'a [u8]) -> Self {
+        Request { body, ..self }
+    }
+}
+
+/// A `WWW-Authenticate: Digest ...` challenge.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: Algorithm,
+    /// Empty for an RFC 2069 server, which predates `qop`.
+    pub qop: Vec<Qop>,
+    /// The nonce has expired, but the credentials were right, so retrying
+    /// with the new nonce doesn't need to ask the user again.
+    pub stale: bool,
+    /// The username should be sent hashed (RFC 7616, section 3.4.4).
+    pub userhash: bool,
+}
+
+impl Challenge {
+    /// A challenge offering `qop=auth`.
+    pub fn new(realm: &str, nonce: &str, algorithm: Algorithm) -> Result<Self, DigestAuthError> {
+        check_characters(&[("realm", realm), ("nonce", nonce)])?;
+        Ok(Challenge {
+            realm: realm.to_string(),
+            nonce: nonce.to_string(),
+            opaque: None,
+            algorithm,
+            qop: vec![Qop::Auth],
+            stale: false,
+            userhash: false,
+        })
+    }
+
+    /// Answers the challenge for one request, with the client nonce and
+    /// nonce count given. `auth` is used if the server offers it, `auth-int`
+    /// if that's all it offers. None of the values that end up in the header
+    /// can have control characters in them.
+    pub fn authorize(
+        &self,
+        username: &str,
+        password: &str,
+        request: &Request,
+        cnonce: &str,
+        nonce_count: u32,
+    ) -> Result<Authorization, DigestAuthError> {
+        let qop = if self.qop.contains(&Qop::Auth) {
+            Some(Qop::Auth)
+        } else {
+            self.qop.first().copied()
+        };
+        let sent_username = if self.userhash {
+            self.algorithm.hash_fields(&[username, &self.realm])
+        } else {
+            username.to_string()
+        };
+        check_characters(&[
+            ("username", &sent_username),
+            ("realm", &self.realm),
+            ("nonce", &self.nonce),
+            ("uri", request.uri),
+            ("cnonce", cnonce),
+            ("opaque", self.opaque.as_deref().unwrap_or("")),
+        ])?;
+
+        let mut authorization = Authorization {
+            username: sent_username,
+            realm: self.realm.clone(),
+            nonce: self.nonce.clone(),
+            uri: request.uri.to_string(),
+            response: String::new(),
+            algorithm: self.algorithm,
+            cnonce: qop.map(|_| cnonce.to_string()),
+            opaque: self.opaque.clone(),
+            qop,
+            nonce_count: qop.map(|_| nonce_count),
+            userhash: self.userhash,
+        };
+        authorization.response = authorization.expected_response(
+            &ha1(self.algorithm, username, &self.realm, password),
+            request.method,
+            request.body,
+        );
+        Ok(authorization)
+    }
+
+    /// The value of the `WWW-Authenticate` header. The fields are public, so
+    /// this is where a control character that was put in one after `new` is
+    /// caught.
+    pub fn to_header(&self) -> Result<String, DigestAuthError> {
+        let mut header = format!("Digest realm={}", quote("realm", &self.realm)?);
+        if !self.qop.is_empty() {
+            let qop = self
+                .qop
+                .iter()
+                .map(|qop| qop.name())
+                .collect::<Vec<&str>>()
+                .join(", ");
+            header += &format!(", qop={}", quote("qop", &qop)?);
+        }
+        header += &format!(
+            ", algorithm={}, nonce={}",
+            self.algorithm.name(),
+            quote("nonce", &self.nonce)?
+        );
+        if let Some(opaque) = &self.opaque {
+            header += &format!(", opaque={}", quote("opaque", opaque)?);
+        }
+        if self.stale {
+            header += ", stale=true";
+        }
+        if self.userhash {
+            header += ", userhash=true";
+        }
+        Ok(header)
+    }
+}
+
+impl FromStr for Challenge {
+    type Err = DigestAuthError;
+
+    fn from_str(header: &str) -> Result<Self, Self::Err> {
+        let mut parameters = parse_parameters(strip_scheme(header)?)?;
+
+        let qop = match parameters.remove("qop") {
+            None => Vec::new(),
+            Some(offered) => {
+                // options the client doesn't know are for it to ignore
+                let known = offered
+                    .split(',')
+                    .filter_map(|option| option.trim().parse::<Qop>().ok())
+                    .collect::<Vec<Qop>>();
+                if known.is_empty() {
+                    return Err(DigestAuthError::UnsupportedQop(offered));
+                }
+                known
+            }
+        };
+
+        let flag =
+            |value: Option<String>| value.is_some_and(|value| value.eq_ignore_ascii_case("true"));
+        Ok(Challenge {
+            realm: required(&mut parameters, "realm")?,
+            nonce: required(&mut parameters, "nonce")?,
+            opaque: parameters.remove("opaque"),
+            algorithm: match parameters.remove("algorithm") {
+                Some(name) => name.parse()?,
+                None => Algorithm::default(),
+            },
+            qop,
+            stale: flag(parameters.remove("stale")),
+            userhash: flag(parameters.remove("userhash")),
+        })
+    }
+}
+
+/// An `Authorization: Digest ...` header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Authorization {
+    /// `H(username ":" realm)` if `userhash` is set.
+    pub username: String,
+    pub realm: String,
+    pub nonce: String,
+    pub uri: String,
+    pub response: String,
+    pub algorithm: Algorithm,
+    pub cnonce: Option<String>,
+    pub opaque: Option<String>,
+    pub qop: Option<Qop>,
+    pub nonce_count: Option<u32>,
+    pub userhash: bool,
+}
+
+impl Authorization {
+    /// The response this request should have, given the user's HA1.
+    pub fn expected_response(&self, ha1: &str, method: &str, body: &[u8]) -> String {
+        let algorithm = self.algorithm;
+        let cnonce = self.cnonce.as_deref().unwrap_or("");
+        let ha1 = if algorithm.is_session() {
+            algorithm.hash_fields(&[ha1, &self.nonce, cnonce])
+        } else {
+            ha1.to_string()
+        };
+        let ha2 = ha2(algorithm, self.qop, method, &self.uri, body);
+
+        match (self.qop, self.nonce_count) {
+            (Some(qop), Some(nonce_count)) => algorithm.hash_fields(&[
+                &ha1,
+                &self.nonce,
+                &format!("{:08x}", nonce_count),
+                cnonce,
+                qop.name(),
+                &ha2,
+            ]),
+            _ => algorithm.hash_fields(&[&ha1, &self.nonce, &ha2]),
+        }
+    }
+
+    /// Checks the response against the user's HA1 (see `ha1`). The `uri`
+    /// should be compared with the request's own target as well. This says
+    /// nothing about whether the nonce is current or the request a replay;
+    /// that's what `NonceTracker` is for.
+    pub fn verify(&self, ha1: &str, method: &str, body: &[u8]) -> bool {
+        let expected = self.expected_response(ha1, method, body);
+        constant_time_eq(
+            expected.as_bytes(),
+            self.response.to_ascii_lowercase().as_bytes(),
+        )
+    }
+
+    /// The value of the `Authorization` header, which fails the same way as
+    /// `Challenge::to_header`.
+    pub fn to_header(&self) -> Result<String, DigestAuthError> {
+        let mut header = format!(
+            "Digest username={}, realm={}, uri={}, algorithm={}, nonce={}",
+            quote("username", &self.username)?,
+            quote("realm", &self.realm)?,
+            quote("uri", &self.uri)?,
+            self.algorithm.name(),
+            quote("nonce", &self.nonce)?
+        );
+        if let (Some(qop), Some(nonce_count), Some(cnonce)) =
+            (self.qop, self.nonce_count, &self.cnonce)
+        {
+            header += &format!(
+                ", nc={:08x}, cnonce={}, qop={}",
+                nonce_count,
+                quote("cnonce", cnonce)?,
+                qop.name()
+            );
+        }
+        header += &format!(", response={}", quote("response", &self.response)?);
+        if let Some(opaque) = &self.opaque {
+            header += &format!(", opaque={}", quote("opaque", opaque)?);
+        }
+        if self.userhash {
+            header += ", userhash=true";
+        }
+        Ok(header)
+    }
+}
+
+impl FromStr for Authorization {
+    type Err = DigestAuthError;
+
+    fn from_str(header: &str) -> Result<Self, Self::Err> {
+        let mut parameters = parse_parameters(strip_scheme(header)?)?;
+
+        let qop = parameters
+            .remove("qop")
+            .map(|qop| qop.parse::<Qop>())
+            .transpose()?;
+        let (cnonce, nonce_count) = match qop {
+            None => (None, None),
+            Some(_) => {
+                let cnonce = required(&mut parameters, "cnonce")?;
+                let count = required(&mut parameters, "nc")?;
+                if count.len() != 8 || !count.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                    return Err(DigestAuthError::InvalidNonceCount(count));
+                }
+                let count = u32::from_str_radix(&count, 16)
+                    .map_err(|_| DigestAuthError::InvalidNonceCount(count))?;
+                (Some(cnonce), Some(count))
+            }
+        };
+
+        Ok(Authorization {
+            username: required(&mut parameters, "username")?,
+            realm: required(&mut parameters, "realm")?,
+            nonce: required(&mut parameters, "nonce")?,
+            uri: required(&mut parameters, "uri")?,
+            response: required(&mut parameters, "response")?,
+            algorithm: match parameters.remove("algorithm") {
+                Some(name) => name.parse()?,
+                None => Algorithm::default(),
+            },
+            cnonce,
+            opaque: parameters.remove("opaque"),
+            qop,
+            nonce_count,
+            userhash: parameters
+                .remove("userhash")
+                .is_some_and(|value| value.eq_ignore_ascii_case("true")),
+        })
+    }
+}
+
+/// The client's side of a Digest session: answers requests under the latest
+/// challenge, counting up the nonce count as it goes.
+#[derive(Clone)]
+pub struct DigestClient {
+    challenge: Challenge,
+    username: String,
+    password: String,
+    nonce_count: u32,
+}
+
+// everything but the password, so that logging a client doesn't leak it
+impl fmt::Debug for DigestClient {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("DigestClient")
+            .field("challenge", &self.challenge)
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .field("nonce_count", &self.nonce_count)
+            .finish()
+    }
+}
+
+impl DigestClient {
+    pub fn new(challenge: Challenge, username: &str, password: &str) -> Self {
+        DigestClient {
+            challenge,
+            username: username.to_string(),
+            password: password.to_string(),
+            nonce_count: 0,
+        }
+    }
+
+    /// A new challenge (after a 401, or a `stale=true` one) means a new
+    /// nonce, so the count starts over.
+    pub fn set_challenge(&mut self, challenge: Challenge) {
+        self.challenge = challenge;
+        self.nonce_count = 0;
+    }
+
+    /// The nonce count of the last request made.
+    pub fn nonce_count(&self) -> u32 {
+        self.nonce_count
+    }
+
+    /// The header for the next request. `cnonce` should be fresh and random
+    /// for each one.
+    pub fn authorize(
+        &mut self,
+        request: &Request,
+        cnonce: &str,
+    ) -> Result<Authorization, DigestAuthError> {
+        let authorization = self.challenge.authorize(
+            &self.username,
+            &self.password,
+            request,
+            cnonce,
+            self.nonce_count + 1,
+        )?;
+        self.nonce_count += 1;
+        Ok(authorization)
+    }
+}
+
+/// The server's record of the nonces it's handed out, and the highest nonce
+/// count it's seen for each, so that a replayed request can be turned away.
+#[derive(Clone, Debug, Default)]
+pub struct NonceTracker {
+    nonce_counts: HashMap<String, u32>,
+}
+
+impl NonceTracker {
+    /// Starts accepting a nonce that's just been sent out in a challenge.
+    pub fn issue(&mut self, nonce: &str) {
+        self.nonce_counts.insert(nonce.to_string(), 0);
+    }
+
+    /// Stops accepting a nonce, once it's expired.
+    pub fn retire(&mut self, nonce: &str) {
+        self.nonce_counts.remove(nonce);
+    }
+
+    /// Whether the request's nonce was issued here and its nonce count is
+    /// higher than any seen before; if so, that count becomes the one to
+    /// beat. Only call this once the response has been verified, or anyone
+    /// could burn through the counts. Requests without a `qop` have no nonce
+    /// count to check, so a nonce can only be used for one of them.
+    pub fn check(&mut self, authorization: &Authorization) -> bool {
+        let last = match self.nonce_counts.get_mut(&authorization.nonce) {
+            Some(last) => last,
+            None => return false,
+        };
+
+        match authorization.nonce_count {
+            Some(count) if count > *last => {
+                *last = count;
+                true
+            }
+            Some(_) => false,
+            None => {
+                self.nonce_counts.remove(&authorization.nonce);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONCE: &str = "7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v";
+    const OPAQUE: &str = "FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS";
+    const CNONCE: &str = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+
+    // RFC 7616, section 3.9.1
+    #[test]
+    fn test_rfc7616_example() {
+        let header = "Digest\r\n    realm=\"http-auth@example.org\",\r\n    qop=\"auth, auth-int\",\r\n    algorithm=SHA-256,\r\n    nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\",\r\n    opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+        let mut challenge = header.parse::<Challenge>().unwrap();
+        assert_eq!("http-auth@example.org", challenge.realm);
+        assert_eq!(Algorithm::Sha256, challenge.algorithm);
+        assert_eq!(vec![Qop::Auth, Qop::AuthInt], challenge.qop);
+        assert_eq!(Some(OPAQUE), challenge.opaque.as_deref());
+        assert_eq!(
+            Ok(challenge.clone()),
+            challenge.to_header().unwrap().parse()
+        );
+
+        let authorization = challenge
+            .authorize(
+                "Mufasa",
+                "Circle of Life",
+                &Request::new("GET", "/dir/index.html"),
+                CNONCE,
+                1,
+            )
+            .unwrap();
+        assert_eq!(
+            "753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1",
+            authorization.response
+        );
+
+        challenge.algorithm = Algorithm::Md5;
+        let authorization = challenge
+            .authorize(
+                "Mufasa",
+                "Circle of Life",
+                &Request::new("GET", "/dir/index.html"),
+                CNONCE,
+                1,
+            )
+            .unwrap();
+        assert_eq!(
+            "Digest username=\"Mufasa\", realm=\"http-auth@example.org\", uri=\"/dir/index.html\", algorithm=MD5, nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", nc=00000001, cnonce=\"f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ\", qop=auth, response=\"8ca523f5e9506fed4657c9700eebdbec\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+            authorization.to_header().unwrap()
+        );
+        assert_eq!(
+            Ok(authorization.clone()),
+            authorization.to_header().unwrap().parse()
+        );
+
+        let stored = ha1(
+            Algorithm::Md5,
+            "Mufasa",
+            "http-auth@example.org",
+            "Circle of Life",
+        );
+        assert!(authorization.verify(&stored, "GET", b""));
+        assert!(!authorization.verify(&stored, "POST", b""));
+    }
+
+    // RFC 2617, section 3.5, and the same request from an RFC 2069 server
+    #[test]
+    fn test_rfc2617_example() {
+        let header = "Digest username=\"Mufasa\", realm=\"testrealm@host.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", uri=\"/dir/index.html\", qop=auth, nc=00000001, cnonce=\"0a4f113b\", response=\"6629fae49393a05397450978507c4ef1\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"";
+        let mut authorization = header.parse::<Authorization>().unwrap();
+        assert_eq!(Algorithm::Md5, authorization.algorithm);
+        assert_eq!(Some(1), authorization.nonce_count);
+
+        let stored = ha1(
+            Algorithm::Md5,
+            "Mufasa",
+            "testrealm@host.com",
+            "Circle Of Life",
+        );
+        assert!(authorization.verify(&stored, "GET", b""));
+
+        authorization.qop = None;
+        assert_eq!(
+            "670fd8c2df070c60b045671b8b24ff02",
+            authorization.expected_response(&stored, "GET", b"")
+        );
+    }
+
+    #[test]
+    fn test_session_and_auth_int() {
+        let mut challenge =
+            Challenge::new("http-auth@example.org", NONCE, Algorithm::Md5Sess).unwrap();
+        let authorization = challenge
+            .authorize(
+                "Mufasa",
+                "Circle of Life",
+                &Request::new("GET", "/dir/index.html"),
+                CNONCE,
+                2,
+            )
+            .unwrap();
+        assert_eq!("6914b51e16f9459d9abc967ad41c4599", authorization.response);
+
+        challenge.algorithm = Algorithm::Sha512_256;
+        challenge.qop = vec![Qop::AuthInt];
+        let body = b"{\"enabled\":true}";
+        let authorization = challenge
+            .authorize(
+                "Mufasa",
+                "Circle of Life",
+                &Request::new("POST", "/api/config").with_body(body),
+                CNONCE,
+                1,
+            )
+            .unwrap();
+        assert_eq!(Some(Qop::AuthInt), authorization.qop);
+        assert_eq!(
+            "125598fdb6704ab1e9202e1c056c01cc4eec347f5f1de206c7889a61fb9521d9",
+            authorization.response
+        );
+
+        // auth-int covers the body
+        let stored = ha1(
+            Algorithm::Sha512_256,
+            "Mufasa",
+            "http-auth@example.org",
+            "Circle of Life",
+        );
+        assert!(authorization.verify(&stored, "POST", body));
+        assert!(!authorization.verify(&stored, "POST", b"{\"enabled\":false}"));
+    }
+
+    #[test]
+    fn test_userhash() {
+        let mut challenge =
+            Challenge::new("http-auth@example.org", NONCE, Algorithm::Sha512_256).unwrap();
+        challenge.userhash = true;
+        assert!(challenge.to_header().unwrap().ends_with(", userhash=true"));
+
+        let authorization = challenge
+            .authorize(
+                "Mufasa",
+                "Circle of Life",
+                &Request::new("GET", "/"),
+                CNONCE,
+                1,
+            )
+            .unwrap();
+        assert_eq!(
+            "e2dfabd1a96ddf867710b653b6e6857d1f147086de7d7ef79dcd249859872570",
+            authorization.username
+        );
+        assert!(authorization.userhash);
+        assert_eq!(
+            Ok(authorization.clone()),
+            authorization.to_header().unwrap().parse()
+        );
+    }
+
+    #[test]
+    fn test_nonce_counts() {
+        let challenge = Challenge::new("cameras", NONCE, Algorithm::Sha256).unwrap();
+        let stored = ha1(Algorithm::Sha256, "admin", "cameras", "12345");
+
+        let mut tracker = NonceTracker::default();
+        tracker.issue(NONCE);
+
+        let mut client = DigestClient::new(challenge.clone(), "admin", "12345");
+        let first = client
+            .authorize(&Request::new("GET", "/snapshot.jpg"), "a1")
+            .unwrap();
+        let second = client
+            .authorize(&Request::new("GET", "/snapshot.jpg"), "b2")
+            .unwrap();
+        assert_eq!(Some(1), first.nonce_count);
+        assert_eq!(Some(2), second.nonce_count);
+        assert!(second.to_header().unwrap().contains("nc=00000002"));
+
+        for authorization in [&first, &second].iter() {
+            assert!(authorization.verify(&stored, "GET", b""));
+            assert!(tracker.check(authorization));
+        }
+        // a replay, or a request that arrives late
+        assert!(!tracker.check(&second));
+        assert!(!tracker.check(&first));
+
+        let challenge = Challenge::new("cameras", "another nonce", Algorithm::Sha256).unwrap();
+        client.set_challenge(challenge);
+        let fresh = client
+            .authorize(&Request::new("GET", "/snapshot.jpg"), "c3")
+            .unwrap();
+        assert_eq!(Some(1), fresh.nonce_count);
+        assert!(!tracker.check(&fresh));
+        tracker.issue("another nonce");
+        assert!(tracker.check(&fresh));
+
+        tracker.retire("another nonce");
+        assert!(!tracker.check(&client.authorize(&Request::new("GET", "/"), "d4").unwrap()));
+        assert_eq!(2, client.nonce_count());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Err(DigestAuthError::NotDigest),
+            "Basic realm=\"cameras\"".parse::<Challenge>()
+        );
+        assert_eq!(
+            Err(DigestAuthError::MissingParameter("nonce")),
+            "Digest realm=\"cameras\"".parse::<Challenge>()
+        );
+        assert_eq!(
+            Err(DigestAuthError::Malformed),
+            "Digest realm=\"cameras".parse::<Challenge>()
+        );
+        assert_eq!(
+            Err(DigestAuthError::UnknownAlgorithm(String::from("SHA-1"))),
+            "Digest realm=\"a\", nonce=\"b\", algorithm=SHA-1".parse::<Challenge>()
+        );
+        assert_eq!(
+            Err(DigestAuthError::UnsupportedQop(String::from("auth-conf"))),
+            "Digest realm=\"a\", nonce=\"b\", qop=\"auth-conf\"".parse::<Challenge>()
+        );
+        assert_eq!(
+            Err(DigestAuthError::InvalidNonceCount(String::from("1"))),
+            "Digest username=\"a\", realm=\"b\", nonce=\"c\", uri=\"/\", response=\"d\", qop=auth, nc=1, cnonce=\"e\"".parse::<Authorization>()
+        );
+
+        // escapes in quoted strings go both ways
+        let challenge = Challenge::new("the \"back\\office\"", NONCE, Algorithm::Md5).unwrap();
+        assert!(challenge
+            .to_header()
+            .unwrap()
+            .contains(r#"realm="the \"back\\office\"""#));
+        assert_eq!(
+            Ok(challenge.clone()),
+            challenge.to_header().unwrap().parse()
+        );
+    }
+
+    #[test]
+    fn test_header_injection() {
+        assert_eq!(
+            Some(DigestAuthError::InvalidCharacter("realm")),
+            Challenge::new("cameras\r\nSet-Cookie: a=b", NONCE, Algorithm::Md5).err()
+        );
+
+        let mut challenge = Challenge::new("cameras", NONCE, Algorithm::Md5).unwrap();
+        assert_eq!(
+            Some(DigestAuthError::InvalidCharacter("uri")),
+            challenge
+                .authorize("admin", "12345", &Request::new("GET", "/\n"), "a1", 1)
+                .err()
+        );
+        assert_eq!(
+            Some(DigestAuthError::InvalidCharacter("username")),
+            challenge
+                .authorize("admin\r", "12345", &Request::new("GET", "/"), "a1", 1)
+                .err()
+        );
+
+        // a tab is fine, and hashed usernames can be anything
+        assert!(challenge
+            .authorize("admin\t", "12345", &Request::new("GET", "/"), "a1", 1)
+            .is_ok());
+        challenge.userhash = true;
+        assert!(challenge
+            .authorize("admin\r", "12345", &Request::new("GET", "/"), "a1", 1)
+            .is_ok());
+
+        // the fields are public, so formatting won't trust them either
+        challenge.opaque = Some(String::from("x\r\nSet-Cookie: a=b"));
+        assert_eq!(
+            Err(DigestAuthError::InvalidCharacter("opaque")),
+            challenge.to_header()
+        );
+
+        let mut client = DigestClient::new(challenge, "admin", "12345");
+        assert!(!format!("{:?}", client).contains("12345"));
+        assert!(client.authorize(&Request::new("GET", "/"), "a1").is_err());
+        assert_eq!(0, client.nonce_count());
+
+        assert_eq!(
+            Err(DigestAuthError::Malformed),
+            "Digest realm=\"a\rb\", nonce=\"c\"".parse::<Challenge>()
+        );
+        assert_eq!(
+            Err(DigestAuthError::Malformed),
+            "Digest realm=\"a\", nonce=c\x00d".parse::<Challenge>()
+        );
+    }
+
+}
@@ -0,0 +1,229 @@
+//! KangarooTwelve (KT128 in RFC 9861): a tree hash on top of TurboSHAKE128.
+//!
+//! Short messages are just hashed with TurboSHAKE128. Long ones get cut into
+//! 8 KiB chunks: every chunk after the first is hashed on its own down to a
+//! 32-byte *chaining value*, and the first chunk is followed by all of those
+//! chaining values into one final TurboSHAKE128. The leaves don't depend on
+//! each other, so a big file could be hashed on several cores (or with SIMD)
+//! -- this version does them one after another, but gives the same answer.
+//!
+//! A customization string can be mixed in after the message, so that one
+//! application's hashes never collide with another's.
+
+use crate::keccak::TurboShake128;
+use crate::utils::bytes_to_hex;
+
+const CHUNK_LENGTH: usize = 8192;
+const CHAINING_VALUE_LENGTH: usize = 32;
+
+// the domain bytes that tell the different kinds of TurboSHAKE call apart
+const SINGLE_NODE: u8 = 0x07;
+const FINAL_NODE: u8 = 0x06;
+const LEAF: u8 = 0x0B;
+
+// goes after the first chunk when there are leaves (the bits 110 followed by
+// 62 zeroes)
+const FIRST_CHUNK_SUFFIX: [u8; 8] = [0x03, 0, 0, 0, 0, 0, 0, 0];
+
+/// `length_encode` from the spec: the value big endian, without leading zero
+/// bytes, then a byte saying how many bytes that took. Zero is just `00`.
+fn length_encode(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let leading_zeroes = bytes.iter().take_while(|byte| **byte == 0).count();
+
+    let mut encoded = bytes[leading_zeroes..].to_vec();
+    encoded.push(encoded.len() as u8);
+    encoded
+}
+
+#[derive(Clone)]
+pub struct KangarooTwelve {
+    customization: Vec<u8>,
+    // held back until we know whether the message fits in one chunk
+    first_chunk: Vec<u8>,
+    // once it doesn't, the final node is under way...
+    final_node: Option<TurboShake128>,
+    // ...along with the leaf currently being filled
+    leaf: TurboShake128,
+    leaf_length: usize,
+    leaf_count: u64,
+    // set on the first squeeze
+    output: Option<TurboShake128>,
+}
+
+impl Default for KangarooTwelve {
+    fn default() -> Self {
+        KangarooTwelve::new(&[])
+    }
+}
+
+impl KangarooTwelve {
+    pub fn new(customization: &[u8]) -> Self {
+        KangarooTwelve {
+            customization: customization.to_vec(),
+            first_chunk: Vec::with_capacity(CHUNK_LENGTH),
+            final_node: None,
+            leaf: TurboShake128::new(LEAF),
+            leaf_length: 0,
+            leaf_count: 0,
+            output: None,
+        }
+    }
+
+    fn finish_leaf(&mut self) {
+        let mut leaf = std::mem::replace(&mut self.leaf, TurboShake128::new(LEAF));
+
+        let mut chaining_value = [0u8; CHAINING_VALUE_LENGTH];
+        leaf.squeeze(&mut chaining_value);
+
+        self.final_node
+            .as_mut()
+            .expect("leaves only exist once the final node has started")
+            .update(&chaining_value);
+        self.leaf_length = 0;
+        self.leaf_count += 1;
+    }
+
+    // everything that goes through here is part of S = message || customization
+    // || length_encode(customization length)
+    fn absorb(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            match self.final_node {
+                None if self.first_chunk.len() < CHUNK_LENGTH => {
+                    let take = bytes.len().min(CHUNK_LENGTH - self.first_chunk.len());
+                    self.first_chunk.extend_from_slice(&bytes[..take]);
+                    bytes = &bytes[take..];
+                }
+                None => {
+                    // a byte beyond the first chunk: this is a tree after all
+                    let mut final_node = TurboShake128::new(FINAL_NODE);
+                    final_node.update(&self.first_chunk);
+                    final_node.update(&FIRST_CHUNK_SUFFIX);
+                    self.final_node = Some(final_node);
+                }
+                Some(_) => {
+                    if self.leaf_length == CHUNK_LENGTH {
+                        self.finish_leaf();
+                    }
+
+                    let take = bytes.len().min(CHUNK_LENGTH - self.leaf_length);
+                    self.leaf.update(&bytes[..take]);
+                    self.leaf_length += take;
+                    bytes = &bytes[take..];
+                }
+            }
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        assert!(
+            self.output.is_none(),
+            "can't absorb once squeezing has started"
+        );
+        self.absorb(bytes);
+    }
+
+    fn finish(&mut self) -> TurboShake128 {
+        let customization = std::mem::take(&mut self.customization);
+        self.absorb(&customization);
+        self.absorb(&length_encode(customization.len() as u64));
+
+        if self.final_node.is_none() {
+            let mut single_node = TurboShake128::new(SINGLE_NODE);
+            single_node.update(&self.first_chunk);
+            return single_node;
+        }
+
+        self.finish_leaf();
+
+        let mut final_node = self.final_node.take().unwrap();
+        final_node.update(&length_encode(self.leaf_count));
+        final_node.update(&[0xFF, 0xFF]);
+        final_node
+    }
+
+    /// Can be called over and over for more output.
+    pub fn squeeze(&mut self, output: &mut [u8]) {
+        if self.output.is_none() {
+            self.output = Some(self.finish());
+        }
+
+        self.output.as_mut().unwrap().squeeze(output);
+    }
+}
+
+/// `output_length` is in bytes.
+pub fn kangarootwelve(raw_message: &str, customization: &str, output_length: usize) -> String {
+    let mut k12 = KangarooTwelve::new(customization.as_bytes());
+    k12.update(raw_message.as_bytes());
+
+    let mut output = vec![0u8; output_length];
+    k12.squeeze(&mut output);
+    bytes_to_hex(&output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the test messages in RFC 9861 are this pattern, cut to length
+    fn pattern(length: usize) -> Vec<u8> {
+        (0..length).map(|index| (index % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_length_encode() {
+        assert_eq!(vec![0x00], length_encode(0));
+        assert_eq!(vec![0x0C, 0x01], length_encode(12));
+        assert_eq!(vec![0xFF, 0xFF, 0x02], length_encode(65535));
+    }
+
+    #[test]
+    fn test_kangarootwelve() {
+        assert_eq!(
+            "1ac2d450fc3b4205d19da7bfca1b37513c0803577ac7167f06fe2ce1f0ef39e5",
+            kangarootwelve("", "", 32)
+        );
+    }
+
+    #[test]
+    fn test_tree_mode() {
+        // RFC 9861: long enough for ten leaves
+        let message = pattern(83521);
+        let mut k12 = KangarooTwelve::default();
+        let mut output = [0u8; 32];
+        k12.update(&message);
+        k12.squeeze(&mut output);
+        assert_eq!(
+            "8701045e22205345ff4dda05555cbb5c3af1a771c2b89baef37db43d9998b9fe",
+            bytes_to_hex(&output)
+        );
+
+        // and the same again fed in pieces that don't line up with the chunks
+        let mut k12 = KangarooTwelve::default();
+        for piece in message.chunks(1000) {
+            k12.update(piece);
+        }
+        let mut streamed = [0u8; 32];
+        k12.squeeze(&mut streamed);
+        assert_eq!(output, streamed);
+    }
+
+    #[test]
+    fn test_customization() {
+        let mut k12 = KangarooTwelve::new(&pattern(289));
+        let mut output = [0u8; 32];
+        k12.update(&[0xFF; 3]);
+        k12.squeeze(&mut output);
+        assert_eq!(
+            "a3d59de329f7c778a3e42796f2175b38a5e37b8da20947f59ed9a5e64115db58",
+            bytes_to_hex(&output)
+        );
+
+        assert_ne!(
+            kangarootwelve("abc", "", 32),
+            kangarootwelve("abc", "x", 32)
+        );
+    }
+
+}
@@ -0,0 +1,332 @@
+use crate::digest::Digest;
+use crate::utils::bytes_to_hex;
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+// rho and pi done together: walk the lanes in the order pi visits them
+// (starting from lane 1), rotating each by its rho offset on the way
+const RHO_OFFSETS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+const PI_LANES: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+pub const STATE_LANES: usize = 25;
+
+/// Keccak-p[1600]: the permutation underneath everything in the SHA-3 family.
+///
+/// The state is 25 lanes of 64 bits -- a 5x5 grid, with lane `x + 5 * y`
+/// sitting at column `x`, row `y`. Each round is five steps:
+///
+/// * theta: xor every lane with the parity of two neighbouring columns
+/// * rho: rotate every lane by a fixed, lane-specific amount
+/// * pi: shuffle the lanes around the grid
+/// * chi: the only nonlinear step -- `a ^ (!b & c)` along each row
+/// * iota: xor a round constant into lane 0 so the rounds aren't all alike
+///
+/// Keccak-f[1600] is the full 24 rounds. Reduced-round variants run the
+/// *last* `rounds` rounds, so they use the tail of the round constants.
+pub fn keccak_p(state: &mut [u64; STATE_LANES], rounds: usize) {
+    assert!(rounds <= ROUND_CONSTANTS.len());
+
+    for round_constant in ROUND_CONSTANTS[ROUND_CONSTANTS.len() - rounds..].iter() {
+        // theta
+        let mut parities = [0u64; 5];
+        for (x, parity) in parities.iter_mut().enumerate() {
+            *parity = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let effect = parities[(x + 4) % 5] ^ parities[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[x + 5 * y] ^= effect;
+            }
+        }
+
+        // rho + pi
+        let mut carried = state[1];
+        for (lane, offset) in PI_LANES.iter().zip(RHO_OFFSETS.iter()) {
+            let displaced = state[*lane];
+            state[*lane] = carried.rotate_left(*offset);
+            carried = displaced;
+        }
+
+        // chi
+        for row in state.chunks_exact_mut(5) {
+            let original = [row[0], row[1], row[2], row[3], row[4]];
+            for x in 0..5 {
+                row[x] = original[x] ^ (!original[(x + 1) % 5] & original[(x + 2) % 5]);
+            }
+        }
+
+        // iota
+        state[0] ^= round_constant;
+    }
+}
+
+pub fn keccak_f(state: &mut [u64; STATE_LANES]) {
+    keccak_p(state, 24);
+}
+
+/// A sponge built on Keccak-p[1600].
+///
+/// The 200-byte state is split in two: the first `rate` bytes are where
+/// message bytes get xored in (and where output is read back out), the rest
+/// -- the capacity -- is never touched directly, and is where the security
+/// comes from.
+///
+/// Once all of the message is in, a padding byte goes in right after it and
+/// `0x80` goes into the last byte of the rate. The padding byte is the only
+/// thing that tells the members of the family apart: the original Keccak
+/// submission (what Ethereum calls Keccak-256) pads with `0x01`, while
+/// FIPS 202 adds domain separation bits and pads SHA3 with `0x06` and SHAKE
+/// with `0x1F`.
+#[derive(Clone)]
+pub struct Sponge {
+    state: [u64; STATE_LANES],
+    rate: usize,
+    rounds: usize,
+    padding: u8,
+    position: usize,
+    squeezing: bool,
+}
+
+impl Sponge {
+    pub fn new(rate: usize, padding: u8, rounds: usize) -> Self {
+        assert!(rate > 0 && rate < STATE_LANES * 8);
+
+        Sponge {
+            state: [0; STATE_LANES],
+            rate,
+            rounds,
+            padding,
+            position: 0,
+            squeezing: false,
+        }
+    }
+
+    fn xor_byte(&mut self, index: usize, byte: u8) {
+        // lanes are little endian: byte 0 of the state is the low byte of lane 0
+        self.state[index / 8] ^= u64::from(byte) << (8 * (index % 8));
+    }
+
+    fn get_byte(&self, index: usize) -> u8 {
+        (self.state[index / 8] >> (8 * (index % 8))) as u8
+    }
+
+    fn permute(&mut self) {
+        keccak_p(&mut self.state, self.rounds);
+        self.position = 0;
+    }
+
+    pub fn absorb(&mut self, bytes: &[u8]) {
+        assert!(!self.squeezing, "can't absorb once squeezing has started");
+
+        for byte in bytes {
+            self.xor_byte(self.position, *byte);
+            self.position += 1;
+
+            if self.position == self.rate {
+                self.permute();
+            }
+        }
+    }
+
+    fn pad(&mut self) {
+        self.xor_byte(self.position, self.padding);
+        self.xor_byte(self.rate - 1, 0x80);
+        self.permute();
+        self.squeezing = true;
+    }
+
+    pub fn squeeze(&mut self, output: &mut [u8]) {
+        if !self.squeezing {
+            self.pad();
+        }
+
+        for byte in output.iter_mut() {
+            if self.position == self.rate {
+                self.permute();
+            }
+
+            *byte = self.get_byte(self.position);
+            self.position += 1;
+        }
+    }
+}
+
+const KECCAK_256_RATE: usize = 136; // (1600 - 2 * 256) / 8
+
+/// Keccak-256 as submitted to the SHA-3 competition, before NIST changed the
+/// padding. This is the hash Ethereum calls `keccak256` (and `sha3` in older
+/// Solidity) -- it is *not* the same as FIPS 202 SHA3-256, and the two give
+/// completely different digests for the same input.
+#[derive(Clone)]
+pub struct Keccak256 {
+    sponge: Sponge,
+}
+
+impl Default for Keccak256 {
+    fn default() -> Self {
+        Keccak256 {
+            sponge: Sponge::new(KECCAK_256_RATE, 0x01, 24),
+        }
+    }
+}
+
+impl Digest for Keccak256 {
+    const BLOCK_LENGTH: usize = KECCAK_256_RATE;
+    const OUTPUT_LENGTH: usize = 32;
+
+    type Output = [u8; 32];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.sponge.absorb(bytes);
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let mut output = [0u8; 32];
+        self.sponge.squeeze(&mut output);
+        output
+    }
+}
+
+pub fn keccak256(raw_message: &str) -> String {
+    bytes_to_hex(&Keccak256::digest(raw_message.as_bytes()))
+}
+
+pub const TURBOSHAKE_128_RATE: usize = 168; // (1600 - 2 * 128) / 8
+pub const TURBOSHAKE_256_RATE: usize = 136; // (1600 - 2 * 256) / 8
+
+/// TurboSHAKE: SHAKE with the rounds cut from 24 to 12, for twice the speed.
+///
+/// The padding byte is the *domain separation* byte, and callers can pick it
+/// (anything from `0x01` to `0x7F`) so that different uses of TurboSHAKE never
+/// give the same output for the same input. `0x1F` is the default, matching
+/// SHAKE.
+#[derive(Clone)]
+pub struct TurboShake<const RATE: usize> {
+    sponge: Sponge,
+}
+
+pub type TurboShake128 = TurboShake<TURBOSHAKE_128_RATE>;
+pub type TurboShake256 = TurboShake<TURBOSHAKE_256_RATE>;
+
+impl<const RATE: usize> Default for TurboShake<RATE> {
+    fn default() -> Self {
+        TurboShake::new(0x1F)
+    }
+}
+
+impl<const RATE: usize> TurboShake<RATE> {
+    pub fn new(domain: u8) -> Self {
+        assert!(
+            (0x01..=0x7F).contains(&domain),
+            "TurboSHAKE's domain byte must be between 0x01 and 0x7F"
+        );
+
+        TurboShake {
+            sponge: Sponge::new(RATE, domain, 12),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.sponge.absorb(bytes);
+    }
+
+    /// Can be called over and over for more output, but nothing can be
+    /// absorbed once it has been.
+    pub fn squeeze(&mut self, output: &mut [u8]) {
+        self.sponge.squeeze(output);
+    }
+}
+
+/// `output_length` is in bytes.
+pub fn turboshake128(raw_message: &str, output_length: usize) -> String {
+    let mut output = vec![0u8; output_length];
+    let mut turboshake = TurboShake128::default();
+    turboshake.update(raw_message.as_bytes());
+    turboshake.squeeze(&mut output);
+    bytes_to_hex(&output)
+}
+
+/// `output_length` is in bytes.
+pub fn turboshake256(raw_message: &str, output_length: usize) -> String {
+    let mut output = vec![0u8; output_length];
+    let mut turboshake = TurboShake256::default();
+    turboshake.update(raw_message.as_bytes());
+    turboshake.squeeze(&mut output);
+    bytes_to_hex(&output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            keccak256(""),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+
+        assert_eq!(
+            keccak256("abc"),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+
+        assert_eq!(
+            keccak256("The quick brown fox jumps over the lazy dog"),
+            "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15"
+        );
+    }
+
+    #[test]
+    fn test_function_selector() {
+        // the first four bytes of the hash of the signature pick out an ABI function
+        let digest = Keccak256::digest(b"transfer(address,uint256)");
+        assert_eq!("a9059cbb", bytes_to_hex(&digest[..4]));
+    }
+
+    #[test]
+    fn test_keccak256_streaming() {
+        // long enough to need more than one permutation on the way in
+        let message = "a".repeat(300);
+
+        let mut hasher = Keccak256::default();
+        for piece in message.as_bytes().chunks(7) {
+            hasher.update(piece);
+        }
+
+        let expected = "5b7e0e47a96f32a88b4f14ca177982790807c40e1a105742ba0fc1babe1ef826";
+        assert_eq!(expected, keccak256(&message));
+        assert_eq!(expected, bytes_to_hex(&hasher.finalize()));
+    }
+
+    #[test]
+    fn test_turboshake() {
+        // RFC 9861, section 5: the empty message with the default domain byte
+        assert_eq!(
+            "1e415f1c5983aff2169217277d17bb538cd945a397ddec541f1ce41af2c1b74c",
+            turboshake128("", 32)
+        );
+        assert_eq!(
+            "367a329dafea871c7802ec67f905ae13c57695dc2c6663c61035f59a18f8e7db11edc0e12e91ea60eb6b32df06dd7f002fbafabb6e13ec1cc20d995547600db0",
+            turboshake256("", 64)
+        );
+
+        // a different domain byte is a different function
+        let mut output = [0u8; 32];
+        let mut turboshake = TurboShake128::new(0x07);
+        turboshake.squeeze(&mut output);
+        assert_ne!(turboshake128("", 32), bytes_to_hex(&output));
+    }
+
+}
@@ -0,0 +1,267 @@
+//! Length-extension attacks on MD5, SHA-1 and SHA-256.
+//!
+//! These hashes are Merkle–Damgård constructions: the message is padded out
+//! to whole blocks, the blocks are fed one after another through a compression
+//! function, and the digest is simply the chaining state left at the end. So
+//! anyone holding `H(secret || message)` holds the state the hash was in after
+//! `secret || message || padding`, and can carry on hashing from there --
+//! without knowing the secret, only its length.
+//!
+//! That makes `H(secret || message)` useless as a MAC: given one valid tag, an
+//! attacker can make a valid tag for `message || padding || anything`. (The
+//! server has to put up with the "glue" padding in the middle of the message,
+//! which is binary junk, but plenty of parsers will.) HMAC (see `hmac`) exists
+//! because of this.
+//!
+//! `extend` does the whole attack; the `length-extension` binary does the same
+//! from the command line.
+
+use crate::digest::Digest;
+use crate::md5::Md5;
+use crate::sha2::Sha256;
+use crate::utils::md_padding;
+use crate::Sha1;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+const BLOCK_LENGTH: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl Algorithm {
+    pub fn digest_length(self) -> usize {
+        match self {
+            Algorithm::Md5 => Md5::OUTPUT_LENGTH,
+            Algorithm::Sha1 => Sha1::OUTPUT_LENGTH,
+            Algorithm::Sha256 => Sha256::OUTPUT_LENGTH,
+        }
+    }
+
+    fn little_endian(self) -> bool {
+        self == Algorithm::Md5
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = LengthExtensionError;
+
+    /// `md5`, `sha1` or `sha256`, in any case, with or without a dash.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().replace('-', "").as_str() {
+            "md5" => Ok(Algorithm::Md5),
+            "sha1" => Ok(Algorithm::Sha1),
+            "sha256" => Ok(Algorithm::Sha256),
+            _ => Err(LengthExtensionError::UnknownAlgorithm(name.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LengthExtensionError {
+    UnknownAlgorithm(String),
+    InvalidDigestLength {
+        expected: usize,
+        actual: usize,
+    },
+    /// The secret, message, glue and extension together are more bytes than
+    /// the hash can count.
+    LengthTooLarge,
+}
+
+impl fmt::Display for LengthExtensionError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LengthExtensionError::UnknownAlgorithm(name) => {
+                write!(formatter, "unknown algorithm {:?}", name)
+            }
+            LengthExtensionError::InvalidDigestLength { expected, actual } => write!(
+                formatter,
+                "the digest should be {} bytes, but it's {}",
+                expected, actual
+            ),
+            LengthExtensionError::LengthTooLarge => {
+                write!(formatter, "the forged message would be too long to hash")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LengthExtensionError {}
+
+/// What to send in place of the original message, and the tag that goes with
+/// it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Extension {
+    /// The original message, then the glue padding, then the extension. The
+    /// server prepends the secret to this.
+    pub message: Vec<u8>,
+    /// Just the part added to the original message: the glue padding and the
+    /// extension.
+    pub suffix: Vec<u8>,
+    /// The digest of `secret || message`.
+    pub digest: Vec<u8>,
+}
+
+/// The padding the hash added after `length` bytes -- what has to sit between
+/// the original message and the extension.
+pub fn glue_padding(algorithm: Algorithm, length: u64) -> Vec<u8> {
+    md_padding(length, BLOCK_LENGTH, algorithm.little_endian())
+}
+
+/// The chaining state words written out in a digest.
+fn state_words<const WORDS: usize>(digest: &[u8], little_endian: bool) -> [u32; WORDS] {
+    let mut words = [0u32; WORDS];
+    for (word, bytes) in words.iter_mut().zip(digest.chunks_exact(4)) {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        *word = if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        };
+    }
+    words
+}
+
+fn finish<D: Digest>(mut hasher: D, extension: &[u8]) -> Vec<u8> {
+    hasher.update(extension);
+    hasher.finalize().as_ref().to_vec()
+}
+
+/// Takes `digest = H(secret || message)` for a secret of `secret_length`
+/// bytes, and forges the digest of `secret || message || glue || extension`.
+pub fn extend(
+    algorithm: Algorithm,
+    digest: &[u8],
+    secret_length: usize,
+    message: &[u8],
+    extension: &[u8],
+) -> Result<Extension, LengthExtensionError> {
+    if digest.len() != algorithm.digest_length() {
+        return Err(LengthExtensionError::InvalidDigestLength {
+            expected: algorithm.digest_length(),
+            actual: digest.len(),
+        });
+    }
+
+    let original_length = secret_length
+        .checked_add(message.len())
+        .and_then(|length| u64::try_from(length).ok())
+        .ok_or(LengthExtensionError::LengthTooLarge)?;
+    let mut suffix = glue_padding(algorithm, original_length);
+    let hashed_length = original_length
+        .checked_add(suffix.len() as u64)
+        .filter(|length| length.checked_add(extension.len() as u64).is_some())
+        .ok_or(LengthExtensionError::LengthTooLarge)?;
+
+    let little_endian = algorithm.little_endian();
+    let forged = match algorithm {
+        Algorithm::Md5 => finish(
+            Md5::from_state(state_words(digest, little_endian), hashed_length),
+            extension,
+        ),
+        Algorithm::Sha1 => finish(
+            Sha1::from_state(state_words(digest, little_endian), hashed_length),
+            extension,
+        ),
+        Algorithm::Sha256 => finish(
+            Sha256::from_state(state_words(digest, little_endian), hashed_length),
+            extension,
+        ),
+    };
+
+    suffix.extend_from_slice(extension);
+    let mut forged_message = message.to_vec();
+    forged_message.extend_from_slice(&suffix);
+
+    Ok(Extension {
+        message: forged_message,
+        suffix,
+        digest: forged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bytes_to_hex;
+
+    const SECRET: &[u8] = b"a secret the attacker never sees";
+    const MESSAGE: &[u8] = b"user=guest&expires=2030-01-01";
+    const EXTENSION: &[u8] = b"&admin=true";
+
+    fn signed<D: Digest>(message: &[u8]) -> Vec<u8> {
+        let mut keyed = SECRET.to_vec();
+        keyed.extend_from_slice(message);
+        D::digest(&keyed).as_ref().to_vec()
+    }
+
+    fn check<D: Digest>(algorithm: Algorithm) {
+        let tag = signed::<D>(MESSAGE);
+        let forged = extend(algorithm, &tag, SECRET.len(), MESSAGE, EXTENSION).unwrap();
+
+        // the server, which does know the secret, agrees with the forgery
+        assert_eq!(signed::<D>(&forged.message), forged.digest);
+        assert!(forged.message.starts_with(MESSAGE));
+        assert!(forged.message.ends_with(EXTENSION));
+        assert_eq!(
+            0,
+            (SECRET.len() + forged.message.len() - EXTENSION.len()) % 64
+        );
+
+        // guessing the wrong secret length gives the wrong answer
+        let wrong = extend(algorithm, &tag, SECRET.len() + 1, MESSAGE, EXTENSION).unwrap();
+        assert_ne!(signed::<D>(&wrong.message), wrong.digest);
+    }
+
+    #[test]
+    fn test_extend() {
+        check::<Md5>(Algorithm::Md5);
+        check::<Sha1>(Algorithm::Sha1);
+        check::<Sha256>(Algorithm::Sha256);
+    }
+
+    #[test]
+    fn test_glue_padding() {
+        // the classic example: secret || "count=10&lat=37.351&user_id=1&long=-119.827&waffle=eggo"
+        // with a 14-byte secret is 69 bytes, so the glue fills out two blocks
+        let glue = glue_padding(Algorithm::Sha1, 69);
+        assert_eq!(128 - 69, glue.len());
+        assert_eq!("0000000000000228", bytes_to_hex(&glue[glue.len() - 8..]));
+
+        // MD5 writes the length little endian
+        let glue = glue_padding(Algorithm::Md5, 3);
+        assert_eq!("1800000000000000", bytes_to_hex(&glue[glue.len() - 8..]));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Ok(Algorithm::Sha256), "SHA-256".parse());
+        assert_eq!(
+            Err(LengthExtensionError::UnknownAlgorithm(String::from("sha3"))),
+            "sha3".parse::<Algorithm>()
+        );
+        assert_eq!(
+            Err(LengthExtensionError::InvalidDigestLength {
+                expected: 20,
+                actual: 16
+            }),
+            extend(Algorithm::Sha1, &[0u8; 16], 8, b"", b"")
+        );
+        assert_eq!(
+            Err(LengthExtensionError::LengthTooLarge),
+            extend(Algorithm::Sha1, &[0u8; 20], usize::MAX, b"a", b"b")
+        );
+        // no room left for the glue padding
+        assert_eq!(
+            Err(LengthExtensionError::LengthTooLarge),
+            extend(Algorithm::Md5, &[0u8; 16], usize::MAX - 10, b"", b"")
+        );
+    }
+
+}
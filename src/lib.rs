@@ -1,12 +1,51 @@
 #![allow(clippy::unreadable_literal, clippy::many_single_char_names)]
 
-mod md5;
-mod sha2;
+pub mod ascon;
+pub mod checksum;
+pub mod crypt;
+pub mod digest;
+pub mod drbg;
+pub mod hkdf;
+pub mod hmac;
+pub mod htpasswd;
+pub mod http_digest;
+pub mod kangarootwelve;
+pub mod keccak;
+pub mod length_extension;
+pub mod lms;
+// newer clippy lints that md5.rs's original code trips; it's kept as written
+#[allow(
+    clippy::doc_overindented_list_items,
+    clippy::empty_line_after_doc_comments,
+    clippy::get_first,
+    clippy::manual_repeat_n,
+    clippy::needless_as_bytes,
+    clippy::unnecessary_cast
+)]
+pub mod md5;
+pub mod merkle;
+pub mod mmr;
+pub mod noncrypto;
+pub mod otp;
+pub mod ots;
+pub mod pbkdf;
+pub mod pkcs1;
+pub mod sha1dc;
+pub mod sha2;
+pub mod siphash;
+pub mod skein;
+pub mod tls;
+pub mod uuid;
+pub mod xmss;
 
 mod utils;
 
+pub use crate::utils::{bytes_to_hex, hex_to_bytes};
+
+use crate::digest::Digest;
 use crate::utils::preprocess;
 use crate::utils::to_hex_string;
+use crate::utils::BlockBuffer;
 use crate::utils::BLOCK_SIZE;
 
 pub fn demo() {
@@ -22,7 +61,7 @@ pub fn demo() {
 
 const K: [u32; 4] = [0x5A82_7999, 0x6ED9_EBA1, 0x8F1B_BCDC, 0xCA62_C1D6];
 fn get_k(index: usize) -> u32 {
-    K[(index / 20) as usize]
+    K[index / 20]
 }
 
 fn calculate_f(index: usize, b: u32, c: u32, d: u32) -> u32 {
@@ -34,13 +73,22 @@ fn calculate_f(index: usize, b: u32, c: u32, d: u32) -> u32 {
     }
 }
 
+/// SHA-0 and SHA-1 are the same algorithm except for one thing: SHA-1 rotates
+/// each new word of the message schedule left by one, and SHA-0 doesn't. That
+/// single missing rotation is what makes SHA-0 so much weaker.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Variant {
+    Sha0,
+    Sha1,
+}
+
 // chunk : &[char]
 // so a slice of that is &[&[char]]
-fn get_upcoming_block(chunk: &[char]) -> Vec<u32> {
+fn get_upcoming_block(chunk: &[char], variant: Variant) -> Vec<u32> {
     // chunks_exact is on a slice and returns slices
     // but then we gather those up into a vec
     // which is how we end up with Vec<&[char]>
-    let mut block_units: Vec<u32> = chunk
+    let block_units: Vec<u32> = chunk
         .chunks_exact(32)
         .map(|block| {
             let int_bits = block.iter().collect::<String>();
@@ -48,6 +96,10 @@ fn get_upcoming_block(chunk: &[char]) -> Vec<u32> {
         })
         .collect();
 
+    expand_block(block_units, variant)
+}
+
+fn expand_block(mut block_units: Vec<u32>, variant: Variant) -> Vec<u32> {
     assert_eq!(16, block_units.len());
 
     let mut upcoming_block: Vec<u32> = Vec::with_capacity(80);
@@ -56,11 +108,15 @@ fn get_upcoming_block(chunk: &[char]) -> Vec<u32> {
 
     for index in 16..80 {
         // -3, -8, -14, -16
-        let term: u32 = (upcoming_block[index - 3]
+        let term: u32 = upcoming_block[index - 3]
             ^ upcoming_block[index - 8]
             ^ upcoming_block[index - 14]
-            ^ upcoming_block[index - 16])
-            .rotate_left(1);
+            ^ upcoming_block[index - 16];
+
+        let term = match variant {
+            Variant::Sha0 => term,
+            Variant::Sha1 => term.rotate_left(1),
+        };
 
         upcoming_block.push(term);
     }
@@ -68,20 +124,66 @@ fn get_upcoming_block(chunk: &[char]) -> Vec<u32> {
     upcoming_block
 }
 
+const INITIAL_HASH_STATE: [u32; 5] = [
+    0x6745_2301,
+    0xEFCD_AB89,
+    0x98BA_DCFE,
+    0x1032_5476,
+    0xC3D2_E1F0,
+];
+
+fn compress(hash_state: &mut [u32; 5], upcoming_block: &[u32], should_debug: bool) {
+    if should_debug {
+        for v in upcoming_block {
+            println!("{:x}", v);
+        }
+    }
+
+    let mut a: u32 = hash_state[0];
+    let mut b: u32 = hash_state[1];
+    let mut c: u32 = hash_state[2];
+    let mut d: u32 = hash_state[3];
+    let mut e: u32 = hash_state[4];
+
+    for (index, current_item) in upcoming_block.iter().enumerate() {
+        let constant_k = get_k(index); // <- independent
+
+        let f_value = calculate_f(index, b, c, d);
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f_value)
+            .wrapping_add(e)
+            .wrapping_add(constant_k)
+            .wrapping_add(*current_item); // independent
+
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+
+        if should_debug {
+            println!(
+                "t={:>2}: {:08X} {:08X} {:08X} {:08X} {:08X}",
+                index, a, b, c, d, e
+            )
+        }
+    }
+
+    hash_state[0] = a.wrapping_add(hash_state[0]);
+    hash_state[1] = b.wrapping_add(hash_state[1]);
+    hash_state[2] = c.wrapping_add(hash_state[2]);
+    hash_state[3] = d.wrapping_add(hash_state[3]);
+    hash_state[4] = e.wrapping_add(hash_state[4]);
+}
+
 // * message length is defined as a u64
 // * all other variables are u32
 // * the final result itself is 160-bits aka 5 u32 values
-#[allow(clippy::many_single_char_names)]
-pub fn sha1(raw_message: &str) -> String {
+fn hash_message(raw_message: &str, variant: Variant) -> String {
     let should_debug = std::env::var("SHOULD_DEBUG").is_ok();
 
-    let mut hash_state: [u32; 5] = [
-        0x6745_2301,
-        0xEFCD_AB89,
-        0x98BA_DCFE,
-        0x1032_5476,
-        0xC3D2_E1F0,
-    ];
+    let mut hash_state: [u32; 5] = INITIAL_HASH_STATE;
 
     let message = preprocess(raw_message.to_string());
     let blocks = message.chars().collect::<Vec<char>>();
@@ -96,49 +198,9 @@ pub fn sha1(raw_message: &str) -> String {
        chunk <- blocks.chunks_exact  : yields &[char]
        so chunk is a &[char] -- a char slice
     */
-    for upcoming_block in blocks.chunks_exact(BLOCK_SIZE).map(&get_upcoming_block) {
-        if should_debug {
-            for v in &upcoming_block {
-                println!("{:x}", v);
-            }
-        }
-
-        let mut a: u32 = hash_state[0];
-        let mut b: u32 = hash_state[1];
-        let mut c: u32 = hash_state[2];
-        let mut d: u32 = hash_state[3];
-        let mut e: u32 = hash_state[4];
-
-        for (index, current_item) in upcoming_block.iter().enumerate() {
-            let constant_k = get_k(index); // <- independent
-
-            let f_value = calculate_f(index, b, c, d);
-            let temp = a
-                .rotate_left(5)
-                .wrapping_add(f_value)
-                .wrapping_add(e)
-                .wrapping_add(constant_k)
-                .wrapping_add(*current_item); // independent
-
-            e = d;
-            d = c;
-            c = b.rotate_left(30);
-            b = a;
-            a = temp;
-
-            if should_debug {
-                println!(
-                    "t={:>2}: {:08X} {:08X} {:08X} {:08X} {:08X}",
-                    index, a, b, c, d, e
-                )
-            }
-        }
-
-        hash_state[0] = a.wrapping_add(hash_state[0]);
-        hash_state[1] = b.wrapping_add(hash_state[1]);
-        hash_state[2] = c.wrapping_add(hash_state[2]);
-        hash_state[3] = d.wrapping_add(hash_state[3]);
-        hash_state[4] = e.wrapping_add(hash_state[4]);
+    for chunk in blocks.chunks_exact(BLOCK_SIZE) {
+        let upcoming_block = get_upcoming_block(chunk, variant);
+        compress(&mut hash_state, &upcoming_block, should_debug);
     }
 
     hash_state
@@ -147,6 +209,151 @@ pub fn sha1(raw_message: &str) -> String {
         .collect::<String>()
 }
 
+pub fn sha1(raw_message: &str) -> String {
+    hash_message(raw_message, Variant::Sha1)
+}
+
+/// SHA-0, the original 1993 version of SHA-1.
+///
+/// **SHA-0 is broken.** Full collisions have been found by hand since 2004,
+/// and NSA withdrew it shortly after publication. It's here so the collision
+/// attacks can be studied side by side with SHA-1 (including with
+/// `SHOULD_DEBUG` tracing); never use it to protect anything.
+pub fn sha0(raw_message: &str) -> String {
+    hash_message(raw_message, Variant::Sha0)
+}
+
+#[derive(Clone)]
+struct Engine {
+    variant: Variant,
+    hash_state: [u32; 5],
+    buffer: BlockBuffer<64>,
+    should_debug: bool,
+}
+
+impl Engine {
+    fn new(variant: Variant) -> Self {
+        Engine {
+            variant,
+            hash_state: INITIAL_HASH_STATE,
+            buffer: BlockBuffer::default(),
+            should_debug: std::env::var("SHOULD_DEBUG").is_ok(),
+        }
+    }
+
+    fn compress_block(
+        hash_state: &mut [u32; 5],
+        block: &[u8; 64],
+        variant: Variant,
+        should_debug: bool,
+    ) {
+        let block_units = block
+            .chunks_exact(4)
+            .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+            .collect::<Vec<u32>>();
+
+        compress(hash_state, &expand_block(block_units, variant), should_debug);
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        let Engine {
+            variant,
+            hash_state,
+            buffer,
+            should_debug,
+        } = self;
+
+        buffer.update(bytes, |block| {
+            Engine::compress_block(hash_state, block, *variant, *should_debug)
+        });
+    }
+
+    fn finalize(mut self) -> [u8; 20] {
+        let Engine {
+            variant,
+            hash_state,
+            buffer,
+            should_debug,
+        } = &mut self;
+
+        buffer.finish(false, |block| {
+            Engine::compress_block(hash_state, block, *variant, *should_debug)
+        });
+
+        let mut output = [0u8; 20];
+        for (bytes, value) in output.chunks_exact_mut(4).zip(hash_state.iter()) {
+            bytes.copy_from_slice(&value.to_be_bytes());
+        }
+
+        output
+    }
+}
+
+/// SHA-1 over bytes, fed in a piece at a time. `sha1` is the same hash for a
+/// whole `&str` at once.
+#[derive(Clone)]
+pub struct Sha1(Engine);
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Sha1(Engine::new(Variant::Sha1))
+    }
+}
+
+impl Sha1 {
+    /// Picks up a hash part way through: `hash_state` is the chaining state
+    /// after the first `length` bytes, which must be a whole number of blocks.
+    ///
+    /// A digest is nothing more than the chaining state after the padding
+    /// block, so this can carry on from any digest -- see `length_extension`.
+    pub fn from_state(hash_state: [u32; 5], length: u64) -> Self {
+        let mut engine = Engine::new(Variant::Sha1);
+        engine.hash_state = hash_state;
+        engine.buffer = BlockBuffer::resume(length);
+        Sha1(engine)
+    }
+}
+
+impl Digest for Sha1 {
+    const BLOCK_LENGTH: usize = 64;
+    const OUTPUT_LENGTH: usize = 20;
+
+    type Output = [u8; 20];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.0.finalize()
+    }
+}
+
+/// SHA-0 over bytes. See `sha0` -- this is broken, and only here for study.
+#[derive(Clone)]
+pub struct Sha0(Engine);
+
+impl Default for Sha0 {
+    fn default() -> Self {
+        Sha0(Engine::new(Variant::Sha0))
+    }
+}
+
+impl Digest for Sha0 {
+    const BLOCK_LENGTH: usize = 64;
+    const OUTPUT_LENGTH: usize = 20;
+
+    type Output = [u8; 20];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.0.finalize()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -159,6 +366,35 @@ mod tests {
         assert_eq!("a9993e364706816aba3e25717850c26c9cd0d89d", output);
     }
 
+    #[test]
+    fn test_sha1_streaming() {
+        let message = "The quick brown fox jumps over the lazy dog".repeat(5);
+
+        let mut hasher = Sha1::default();
+        for piece in message.as_bytes().chunks(13) {
+            hasher.update(piece);
+        }
+
+        let expected = "809972720e1e917140f4ed7dbddad41f382a3a59";
+        assert_eq!(expected, sha1(&message));
+        assert_eq!(expected, utils::bytes_to_hex(&hasher.finalize()));
+        assert_eq!(
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            utils::bytes_to_hex(&Sha1::digest(b""))
+        );
+    }
+
+    #[test]
+    fn test_sha0() {
+        assert_eq!("f96cea198ad1dd5617ac084a3d92c6107708c0ef", sha0(""));
+        assert_eq!("0164b8a914cd2a5e74c4f7ff082c4d97f1edf880", sha0("abc"));
+
+        assert_eq!(
+            "0164b8a914cd2a5e74c4f7ff082c4d97f1edf880",
+            utils::bytes_to_hex(&Sha0::digest(b"abc"))
+        );
+    }
+
     #[test]
     fn test_md5() {
         assert_eq!(md5::md5("1"), "c4ca4238a0b923820dcc509a6f75849b");
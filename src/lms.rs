@@ -0,0 +1,1112 @@
+//! LMS and HSS, the Leighton-Micali hash-based signatures from RFC 8554,
+//! with SHA-256.
+//!
+//! An LMS key is a Merkle tree over `2 ** h` LM-OTS one-time keys (which work
+//! like WOTS+, see `ots`). The public key is the root of the tree, and a
+//! signature is a one-time signature plus the path up the tree from that
+//! one-time key. HSS stacks up to eight LMS trees: each tree signs the public
+//! key of the tree below it, and only the bottom one signs messages, so the
+//! key can sign far more messages than a single tree could have leaves
+//! without having to build one huge tree up front.
+//!
+//! **These signatures are stateful.** Every signature uses up a one-time key,
+//! and signing twice with the same one gives away enough to forge signatures.
+//! `sign` moves the private key on to the next one-time key before handing
+//! back the signature, and refuses to sign at all once every key has been
+//! used. The state lives in the private key, so after every signature it has
+//! to be saved (with `to_bytes`) *before* the signature is released: a signer
+//! that crashes and restarts from an older copy of the state will reuse keys.
+//! For the same reason, never load the same saved state into two signers.
+//!
+//! Everything secret is derived from a 32-byte seed the way RFC 8554,
+//! appendix A, suggests, so that the private key itself stays small. The
+//! randomizer `C` in each one-time signature, and the seeds of the lower HSS
+//! trees, are derived from the seed in the same way; the RFC leaves how to
+//! make those up to the implementation.
+
+use crate::digest::Digest;
+use crate::sha2::Sha256;
+use std::fmt;
+
+const N: usize = 32;
+
+type Hash = [u8; N];
+
+// the domain separation values from RFC 8554, section 4.3
+const D_PBLC: u16 = 0x8080;
+const D_MESG: u16 = 0x8181;
+const D_LEAF: u16 = 0x8282;
+const D_INTR: u16 = 0x8383;
+
+// the other values of the 16-bit index used when deriving things from the
+// seed: each one-time key only uses indices below 265
+const INDEX_RANDOMIZER: u16 = 0xfffd;
+const INDEX_CHILD_SEED: u16 = 0xfffe;
+const INDEX_CHILD_IDENTIFIER: u16 = 0xffff;
+
+const PRIVATE_KEY_LENGTH: usize = 4 + 4 + 16 + N + 4;
+const PUBLIC_KEY_LENGTH: usize = 4 + 4 + 16 + N;
+
+const MAXIMUM_LEVELS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LmsError {
+    /// Every one-time key has been used.
+    Exhausted,
+    /// A type code that isn't one of the SHA-256 parameter sets.
+    UnknownType(u32),
+    /// A key or signature that's the wrong length for its types.
+    InvalidLength,
+    /// HSS needs between 1 and 8 levels.
+    InvalidLevels,
+    /// A saved HSS private key whose trees don't fit together.
+    InvalidState,
+    /// The signature doesn't match the message and the public key.
+    InvalidSignature,
+}
+
+impl fmt::Display for LmsError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LmsError::Exhausted => write!(formatter, "every one-time key has been used"),
+            LmsError::UnknownType(code) => write!(formatter, "unknown type {:#010x}", code),
+            LmsError::InvalidLength => write!(formatter, "invalid length"),
+            LmsError::InvalidLevels => write!(formatter, "HSS needs between 1 and 8 levels"),
+            LmsError::InvalidState => write!(formatter, "inconsistent private key state"),
+            LmsError::InvalidSignature => write!(formatter, "invalid signature"),
+        }
+    }
+}
+
+impl std::error::Error for LmsError {}
+
+/// The LM-OTS parameter sets: how many bits of the digest each hash chain
+/// signs. Wider chains make smaller signatures and slower keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LmotsType {
+    Sha256N32W1,
+    Sha256N32W2,
+    Sha256N32W4,
+    Sha256N32W8,
+}
+
+impl LmotsType {
+    pub fn code(self) -> u32 {
+        match self {
+            LmotsType::Sha256N32W1 => 1,
+            LmotsType::Sha256N32W2 => 2,
+            LmotsType::Sha256N32W4 => 3,
+            LmotsType::Sha256N32W8 => 4,
+        }
+    }
+
+    pub fn from_code(code: u32) -> Result<Self, LmsError> {
+        match code {
+            1 => Ok(LmotsType::Sha256N32W1),
+            2 => Ok(LmotsType::Sha256N32W2),
+            3 => Ok(LmotsType::Sha256N32W4),
+            4 => Ok(LmotsType::Sha256N32W8),
+            _ => Err(LmsError::UnknownType(code)),
+        }
+    }
+
+    fn winternitz(self) -> usize {
+        match self {
+            LmotsType::Sha256N32W1 => 1,
+            LmotsType::Sha256N32W2 => 2,
+            LmotsType::Sha256N32W4 => 4,
+            LmotsType::Sha256N32W8 => 8,
+        }
+    }
+
+    /// `p`: how many chains, for the digest and the checksum together.
+    fn chains(self) -> usize {
+        match self {
+            LmotsType::Sha256N32W1 => 265,
+            LmotsType::Sha256N32W2 => 133,
+            LmotsType::Sha256N32W4 => 67,
+            LmotsType::Sha256N32W8 => 34,
+        }
+    }
+
+    /// `ls`: how far the checksum is shifted to line it up with the chains.
+    fn checksum_shift(self) -> u32 {
+        match self {
+            LmotsType::Sha256N32W1 => 7,
+            LmotsType::Sha256N32W2 => 6,
+            LmotsType::Sha256N32W4 => 4,
+            LmotsType::Sha256N32W8 => 0,
+        }
+    }
+
+    fn signature_length(self) -> usize {
+        4 + N + self.chains() * N
+    }
+}
+
+/// The LMS parameter sets: how tall the tree is, and so how many signatures
+/// one tree can make.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LmsType {
+    Sha256M32H5,
+    Sha256M32H10,
+    Sha256M32H15,
+    Sha256M32H20,
+    Sha256M32H25,
+}
+
+impl LmsType {
+    pub fn code(self) -> u32 {
+        match self {
+            LmsType::Sha256M32H5 => 5,
+            LmsType::Sha256M32H10 => 6,
+            LmsType::Sha256M32H15 => 7,
+            LmsType::Sha256M32H20 => 8,
+            LmsType::Sha256M32H25 => 9,
+        }
+    }
+
+    pub fn from_code(code: u32) -> Result<Self, LmsError> {
+        match code {
+            5 => Ok(LmsType::Sha256M32H5),
+            6 => Ok(LmsType::Sha256M32H10),
+            7 => Ok(LmsType::Sha256M32H15),
+            8 => Ok(LmsType::Sha256M32H20),
+            9 => Ok(LmsType::Sha256M32H25),
+            _ => Err(LmsError::UnknownType(code)),
+        }
+    }
+
+    pub fn height(self) -> u32 {
+        match self {
+            LmsType::Sha256M32H5 => 5,
+            LmsType::Sha256M32H10 => 10,
+            LmsType::Sha256M32H15 => 15,
+            LmsType::Sha256M32H20 => 20,
+            LmsType::Sha256M32H25 => 25,
+        }
+    }
+
+    fn leaves(self) -> u32 {
+        1 << self.height()
+    }
+}
+
+fn signature_length(lms_type: LmsType, ots_type: LmotsType) -> usize {
+    4 + ots_type.signature_length() + 4 + lms_type.height() as usize * N
+}
+
+fn hash(parts: &[&[u8]]) -> Hash {
+    let mut hasher = Sha256::default();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, LmsError> {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(
+        bytes
+            .get(offset..offset + 4)
+            .ok_or(LmsError::InvalidLength)?,
+    );
+    Ok(u32::from_be_bytes(word))
+}
+
+fn read_hash(bytes: &[u8], offset: usize) -> Result<Hash, LmsError> {
+    let mut hash = [0u8; N];
+    hash.copy_from_slice(
+        bytes
+            .get(offset..offset + N)
+            .ok_or(LmsError::InvalidLength)?,
+    );
+    Ok(hash)
+}
+
+/// `x_q[i] = H(I || u32str(q) || u16str(i) || u8str(0xff) || SEED)`, from
+/// RFC 8554, appendix A.
+fn derive(identifier: &[u8; 16], q: u32, index: u16, seed: &Hash) -> Hash {
+    hash(&[
+        identifier,
+        &q.to_be_bytes(),
+        &index.to_be_bytes(),
+        &[0xff],
+        seed,
+    ])
+}
+
+/// `coef(S, i, w)`: the `i`th `w`-bit digit of `S`.
+fn coefficient(bytes: &[u8], index: usize, winternitz: usize) -> usize {
+    let digits_per_byte = 8 / winternitz;
+    let byte = bytes[index / digits_per_byte];
+    let shift = 8 - winternitz * (index % digits_per_byte + 1);
+    usize::from(byte >> shift) & ((1 << winternitz) - 1)
+}
+
+/// The digits each chain signs: the digest `Q`, then its checksum.
+fn ots_digits(ots_type: LmotsType, digest: &Hash) -> Vec<usize> {
+    let winternitz = ots_type.winternitz();
+    let maximum = (1 << winternitz) - 1;
+
+    let checksum: usize = (0..N * 8 / winternitz)
+        .map(|index| maximum - coefficient(digest, index, winternitz))
+        .sum();
+    let checksum = ((checksum as u16) << ots_type.checksum_shift()).to_be_bytes();
+
+    let mut extended = digest.to_vec();
+    extended.extend_from_slice(&checksum);
+
+    (0..ots_type.chains())
+        .map(|index| coefficient(&extended, index, winternitz))
+        .collect()
+}
+
+/// Walks chain `index` of one-time key `q` from step `start` to step `end`.
+fn ots_chain(
+    identifier: &[u8; 16],
+    q: u32,
+    index: usize,
+    value: &Hash,
+    start: usize,
+    end: usize,
+) -> Hash {
+    let mut value = *value;
+    for step in start..end {
+        value = hash(&[
+            identifier,
+            &q.to_be_bytes(),
+            &(index as u16).to_be_bytes(),
+            &[step as u8],
+            &value,
+        ]);
+    }
+    value
+}
+
+/// The one-time public key `K` for the chain ends `y`.
+fn ots_compress(identifier: &[u8; 16], q: u32, ends: &[Hash]) -> Hash {
+    let mut hasher = Sha256::default();
+    hasher.update(identifier);
+    hasher.update(&q.to_be_bytes());
+    hasher.update(&D_PBLC.to_be_bytes());
+    for end in ends {
+        hasher.update(end);
+    }
+    hasher.finalize()
+}
+
+fn ots_message_digest(identifier: &[u8; 16], q: u32, randomizer: &Hash, message: &[u8]) -> Hash {
+    hash(&[
+        identifier,
+        &q.to_be_bytes(),
+        &D_MESG.to_be_bytes(),
+        randomizer,
+        message,
+    ])
+}
+
+fn ots_public_key(ots_type: LmotsType, identifier: &[u8; 16], q: u32, seed: &Hash) -> Hash {
+    let maximum = (1 << ots_type.winternitz()) - 1;
+
+    let ends = (0..ots_type.chains())
+        .map(|index| {
+            let start = derive(identifier, q, index as u16, seed);
+            ots_chain(identifier, q, index, &start, 0, maximum)
+        })
+        .collect::<Vec<_>>();
+
+    ots_compress(identifier, q, &ends)
+}
+
+fn ots_sign(
+    ots_type: LmotsType,
+    identifier: &[u8; 16],
+    q: u32,
+    seed: &Hash,
+    message: &[u8],
+) -> Vec<u8> {
+    let randomizer = derive(identifier, q, INDEX_RANDOMIZER, seed);
+    let digest = ots_message_digest(identifier, q, &randomizer, message);
+
+    let mut signature = Vec::with_capacity(ots_type.signature_length());
+    signature.extend_from_slice(&ots_type.code().to_be_bytes());
+    signature.extend_from_slice(&randomizer);
+
+    for (index, digit) in ots_digits(ots_type, &digest).into_iter().enumerate() {
+        let start = derive(identifier, q, index as u16, seed);
+        signature.extend_from_slice(&ots_chain(identifier, q, index, &start, 0, digit));
+    }
+
+    signature
+}
+
+/// Algorithm 4b of RFC 8554: the one-time public key a signature would have
+/// to come from. `signature` must be exactly one LM-OTS signature.
+fn ots_candidate(
+    ots_type: LmotsType,
+    identifier: &[u8; 16],
+    q: u32,
+    signature: &[u8],
+    message: &[u8],
+) -> Result<Hash, LmsError> {
+    if signature.len() != ots_type.signature_length() {
+        return Err(LmsError::InvalidLength);
+    }
+    if read_u32(signature, 0)? != ots_type.code() {
+        return Err(LmsError::InvalidSignature);
+    }
+
+    let randomizer = read_hash(signature, 4)?;
+    let digest = ots_message_digest(identifier, q, &randomizer, message);
+    let maximum = (1 << ots_type.winternitz()) - 1;
+
+    let ends = ots_digits(ots_type, &digest)
+        .into_iter()
+        .enumerate()
+        .map(|(index, digit)| {
+            let value = read_hash(signature, 4 + N + index * N)?;
+            Ok(ots_chain(identifier, q, index, &value, digit, maximum))
+        })
+        .collect::<Result<Vec<_>, LmsError>>()?;
+
+    Ok(ots_compress(identifier, q, &ends))
+}
+
+fn leaf_hash(identifier: &[u8; 16], node: u32, ots_public_key: &Hash) -> Hash {
+    hash(&[
+        identifier,
+        &node.to_be_bytes(),
+        &D_LEAF.to_be_bytes(),
+        ots_public_key,
+    ])
+}
+
+fn interior_hash(identifier: &[u8; 16], node: u32, left: &Hash, right: &Hash) -> Hash {
+    hash(&[
+        identifier,
+        &node.to_be_bytes(),
+        &D_INTR.to_be_bytes(),
+        left,
+        right,
+    ])
+}
+
+/// An LMS private key, along with its whole tree so that signing doesn't have
+/// to rebuild it. Building the tree hashes every one-time public key, so making
+/// (or loading) a key is slow for the taller trees: a million one-time keys for
+/// `Sha256M32H20`.
+///
+/// It isn't `Clone`: a copy would sign with the same one-time keys as the
+/// original. Saving it with `to_bytes` is the only way to duplicate it, and
+/// whatever does that has to make sure only one copy is ever used.
+pub struct LmsPrivateKey {
+    lms_type: LmsType,
+    ots_type: LmotsType,
+    identifier: [u8; 16],
+    seed: Hash,
+    next: u32,
+    // node r is at index r, as in the RFC: the root is 1, and the children of
+    // r are 2r and 2r + 1
+    nodes: Vec<Hash>,
+}
+
+impl LmsPrivateKey {
+    /// `identifier` (`I`) tells keys apart, and should be random (or at least
+    /// unique); `seed` is the secret everything else comes from, and must be
+    /// random.
+    pub fn new(lms_type: LmsType, ots_type: LmotsType, identifier: [u8; 16], seed: Hash) -> Self {
+        let leaves = lms_type.leaves();
+        let mut nodes = vec![[0u8; N]; 2 * leaves as usize];
+
+        for q in 0..leaves {
+            let node = leaves + q;
+            let ots_public_key = ots_public_key(ots_type, &identifier, q, &seed);
+            nodes[node as usize] = leaf_hash(&identifier, node, &ots_public_key);
+        }
+        for node in (1..leaves).rev() {
+            let (left, right) = (nodes[2 * node as usize], nodes[2 * node as usize + 1]);
+            nodes[node as usize] = interior_hash(&identifier, node, &left, &right);
+        }
+
+        LmsPrivateKey {
+            lms_type,
+            ots_type,
+            identifier,
+            seed,
+            next: 0,
+            nodes,
+        }
+    }
+
+    pub fn public_key(&self) -> LmsPublicKey {
+        LmsPublicKey {
+            lms_type: self.lms_type,
+            ots_type: self.ots_type,
+            identifier: self.identifier,
+            root: self.nodes[1],
+        }
+    }
+
+    /// How many more signatures the key can make.
+    pub fn remaining(&self) -> u32 {
+        self.lms_type.leaves() - self.next
+    }
+
+    /// Signs `message` with the next unused one-time key, and marks it used.
+    /// Save the key's state before releasing the signature.
+    pub fn sign(&mut self, message: &[u8]) -> Result<Vec<u8>, LmsError> {
+        if self.remaining() == 0 {
+            return Err(LmsError::Exhausted);
+        }
+
+        let q = self.next;
+        self.next += 1;
+
+        Ok(self.sign_with(q, message))
+    }
+
+    /// The LMS signature with one-time key `q`, used or not.
+    fn sign_with(&self, q: u32, message: &[u8]) -> Vec<u8> {
+        let mut signature = Vec::with_capacity(signature_length(self.lms_type, self.ots_type));
+        signature.extend_from_slice(&q.to_be_bytes());
+        signature.extend(ots_sign(
+            self.ots_type,
+            &self.identifier,
+            q,
+            &self.seed,
+            message,
+        ));
+        signature.extend_from_slice(&self.lms_type.code().to_be_bytes());
+
+        let mut node = self.lms_type.leaves() + q;
+        while node > 1 {
+            signature.extend_from_slice(&self.nodes[(node ^ 1) as usize]);
+            node /= 2;
+        }
+
+        signature
+    }
+
+    /// The LMS key that one-time key `q` of this one signs in HSS, derived
+    /// from this key's seed.
+    fn child(&self, q: u32, lms_type: LmsType, ots_type: LmotsType) -> LmsPrivateKey {
+        let seed = derive(&self.identifier, q, INDEX_CHILD_SEED, &self.seed);
+        let mut identifier = [0u8; 16];
+        identifier.copy_from_slice(
+            &derive(&self.identifier, q, INDEX_CHILD_IDENTIFIER, &self.seed)[..16],
+        );
+
+        LmsPrivateKey::new(lms_type, ots_type, identifier, seed)
+    }
+
+    /// The state to save: the types, `I`, the seed and the index of the next
+    /// unused one-time key, in 60 bytes. **This includes the seed**, so it must
+    /// be kept as secret as the seed itself.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PRIVATE_KEY_LENGTH);
+        bytes.extend_from_slice(&self.lms_type.code().to_be_bytes());
+        bytes.extend_from_slice(&self.ots_type.code().to_be_bytes());
+        bytes.extend_from_slice(&self.identifier);
+        bytes.extend_from_slice(&self.seed);
+        bytes.extend_from_slice(&self.next.to_be_bytes());
+        bytes
+    }
+
+    /// Loads saved state, rebuilding the tree.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LmsError> {
+        if bytes.len() != PRIVATE_KEY_LENGTH {
+            return Err(LmsError::InvalidLength);
+        }
+
+        let lms_type = LmsType::from_code(read_u32(bytes, 0)?)?;
+        let ots_type = LmotsType::from_code(read_u32(bytes, 4)?)?;
+        let mut identifier = [0u8; 16];
+        identifier.copy_from_slice(&bytes[8..24]);
+        let seed = read_hash(bytes, 24)?;
+        let next = read_u32(bytes, 24 + N)?;
+        if next > lms_type.leaves() {
+            return Err(LmsError::InvalidState);
+        }
+
+        let mut key = LmsPrivateKey::new(lms_type, ots_type, identifier, seed);
+        key.next = next;
+        Ok(key)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LmsPublicKey {
+    lms_type: LmsType,
+    ots_type: LmotsType,
+    identifier: [u8; 16],
+    root: Hash,
+}
+
+impl LmsPublicKey {
+    /// `u32str(type) || u32str(otstype) || I || T[1]`, as in the RFC.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PUBLIC_KEY_LENGTH);
+        bytes.extend_from_slice(&self.lms_type.code().to_be_bytes());
+        bytes.extend_from_slice(&self.ots_type.code().to_be_bytes());
+        bytes.extend_from_slice(&self.identifier);
+        bytes.extend_from_slice(&self.root);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LmsError> {
+        if bytes.len() != PUBLIC_KEY_LENGTH {
+            return Err(LmsError::InvalidLength);
+        }
+
+        let mut identifier = [0u8; 16];
+        identifier.copy_from_slice(&bytes[8..24]);
+
+        Ok(LmsPublicKey {
+            lms_type: LmsType::from_code(read_u32(bytes, 0)?)?,
+            ots_type: LmotsType::from_code(read_u32(bytes, 4)?)?,
+            identifier,
+            root: read_hash(bytes, 24)?,
+        })
+    }
+
+    fn signature_length(&self) -> usize {
+        signature_length(self.lms_type, self.ots_type)
+    }
+
+    /// Algorithm 6 of RFC 8554.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), LmsError> {
+        if signature.len() != self.signature_length() {
+            return Err(LmsError::InvalidLength);
+        }
+
+        let q = read_u32(signature, 0)?;
+        if q >= self.lms_type.leaves() {
+            return Err(LmsError::InvalidSignature);
+        }
+
+        let ots_end = 4 + self.ots_type.signature_length();
+        let candidate = ots_candidate(
+            self.ots_type,
+            &self.identifier,
+            q,
+            &signature[4..ots_end],
+            message,
+        )?;
+        if read_u32(signature, ots_end)? != self.lms_type.code() {
+            return Err(LmsError::InvalidSignature);
+        }
+
+        let mut node = self.lms_type.leaves() + q;
+        let mut value = leaf_hash(&self.identifier, node, &candidate);
+        let mut offset = ots_end + 4;
+        while node > 1 {
+            let sibling = read_hash(signature, offset)?;
+            value = if node & 1 == 1 {
+                interior_hash(&self.identifier, node / 2, &sibling, &value)
+            } else {
+                interior_hash(&self.identifier, node / 2, &value, &sibling)
+            };
+            node /= 2;
+            offset += N;
+        }
+
+        if value != self.root {
+            return Err(LmsError::InvalidSignature);
+        }
+
+        Ok(())
+    }
+}
+
+/// An HSS private key: one LMS key per level, where each one has signed the
+/// public key of the one below it. Lower trees are derived from the one above
+/// them, and a new one is made whenever the bottom tree runs out. Like
+/// `LmsPrivateKey`, it can't be cloned.
+pub struct HssPrivateKey {
+    keys: Vec<LmsPrivateKey>,
+    // signatures[i] is keys[i]'s signature on keys[i + 1]'s public key
+    signatures: Vec<Vec<u8>>,
+}
+
+impl HssPrivateKey {
+    /// `levels` lists the parameter sets from the top tree down. A key can
+    /// make as many signatures as the product of the levels' leaf counts.
+    pub fn new(
+        levels: &[(LmsType, LmotsType)],
+        identifier: [u8; 16],
+        seed: Hash,
+    ) -> Result<Self, LmsError> {
+        if levels.is_empty() || levels.len() > MAXIMUM_LEVELS {
+            return Err(LmsError::InvalidLevels);
+        }
+
+        let (lms_type, ots_type) = levels[0];
+        let mut keys = vec![LmsPrivateKey::new(lms_type, ots_type, identifier, seed)];
+        let mut signatures = Vec::new();
+
+        for (lms_type, ots_type) in levels[1..].iter() {
+            let parent = keys.last_mut().unwrap();
+            let child = parent.child(parent.next, *lms_type, *ots_type);
+            signatures.push(parent.sign(&child.public_key().to_bytes())?);
+            keys.push(child);
+        }
+
+        Ok(HssPrivateKey { keys, signatures })
+    }
+
+    pub fn public_key(&self) -> HssPublicKey {
+        HssPublicKey {
+            levels: self.keys.len() as u32,
+            root: self.keys[0].public_key(),
+        }
+    }
+
+    /// Signs `message`, moving on to the next one-time key. Save the key's
+    /// state before releasing the signature.
+    pub fn sign(&mut self, message: &[u8]) -> Result<Vec<u8>, LmsError> {
+        let bottom = self.keys.len() - 1;
+
+        if self.keys[bottom].remaining() == 0 {
+            // the lowest tree that still has keys left replaces every tree
+            // below it
+            let level = (0..bottom)
+                .rev()
+                .find(|level| self.keys[*level].remaining() > 0)
+                .ok_or(LmsError::Exhausted)?;
+
+            for level in level + 1..=bottom {
+                let (lms_type, ots_type) = (self.keys[level].lms_type, self.keys[level].ots_type);
+                let parent = &mut self.keys[level - 1];
+                let child = parent.child(parent.next, lms_type, ots_type);
+                self.signatures[level - 1] = parent.sign(&child.public_key().to_bytes())?;
+                self.keys[level] = child;
+            }
+        }
+
+        let message_signature = self.keys[bottom].sign(message)?;
+
+        let mut signature = (bottom as u32).to_be_bytes().to_vec();
+        for (signed, key) in self.signatures.iter().zip(self.keys[1..].iter()) {
+            signature.extend_from_slice(signed);
+            signature.extend(key.public_key().to_bytes());
+        }
+        signature.extend(message_signature);
+
+        Ok(signature)
+    }
+
+    /// How many levels there are, then each level's LMS state, top first.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = (self.keys.len() as u32).to_be_bytes().to_vec();
+        for key in self.keys.iter() {
+            bytes.extend(key.to_bytes());
+        }
+        bytes
+    }
+
+    /// Loads saved state, rebuilding every tree. The signatures between the
+    /// levels are made again with the same one-time keys as before -- which
+    /// is safe, since they sign exactly the same public keys and come out
+    /// exactly the same.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LmsError> {
+        let levels = read_u32(bytes, 0)? as usize;
+        if levels == 0 || levels > MAXIMUM_LEVELS {
+            return Err(LmsError::InvalidLevels);
+        }
+        if bytes.len() != 4 + levels * PRIVATE_KEY_LENGTH {
+            return Err(LmsError::InvalidLength);
+        }
+
+        let keys = bytes[4..]
+            .chunks_exact(PRIVATE_KEY_LENGTH)
+            .map(LmsPrivateKey::from_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut signatures = Vec::new();
+        for pair in keys.windows(2) {
+            let (parent, child) = (&pair[0], &pair[1]);
+            if parent.next == 0 {
+                return Err(LmsError::InvalidState);
+            }
+
+            let q = parent.next - 1;
+            let expected = parent.child(q, child.lms_type, child.ots_type);
+            if expected.identifier != child.identifier || expected.seed != child.seed {
+                return Err(LmsError::InvalidState);
+            }
+
+            signatures.push(parent.sign_with(q, &child.public_key().to_bytes()));
+        }
+
+        Ok(HssPrivateKey { keys, signatures })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HssPublicKey {
+    levels: u32,
+    root: LmsPublicKey,
+}
+
+impl HssPublicKey {
+    /// `u32str(L) || pub[0]`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.levels.to_be_bytes().to_vec();
+        bytes.extend(self.root.to_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, LmsError> {
+        let levels = read_u32(bytes, 0)?;
+        if levels == 0 || levels as usize > MAXIMUM_LEVELS {
+            return Err(LmsError::InvalidLevels);
+        }
+
+        Ok(HssPublicKey {
+            levels,
+            root: LmsPublicKey::from_bytes(&bytes[4..])?,
+        })
+    }
+
+    /// Algorithm 8 of RFC 8554: check each tree's signature on the next
+    /// tree's public key, and then the bottom tree's signature on the message.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), LmsError> {
+        if read_u32(signature, 0)? != self.levels - 1 {
+            return Err(LmsError::InvalidSignature);
+        }
+
+        let mut key = self.root.clone();
+        let mut offset = 4;
+        for _ in 1..self.levels {
+            let signed_end = offset + key.signature_length();
+            let signed = signature
+                .get(offset..signed_end)
+                .ok_or(LmsError::InvalidLength)?;
+            let next = signature
+                .get(signed_end..signed_end + PUBLIC_KEY_LENGTH)
+                .ok_or(LmsError::InvalidLength)?;
+
+            key.verify(next, signed)?;
+            key = LmsPublicKey::from_bytes(next)?;
+            offset = signed_end + PUBLIC_KEY_LENGTH;
+        }
+
+        key.verify(message, &signature[offset..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{bytes_to_hex, hex_to_bytes};
+
+    const IDENTIFIER: [u8; 16] = *b"an example key I";
+    const SEED: Hash = [7u8; 32];
+
+    #[test]
+    fn test_coefficients() {
+        // the example in RFC 8554, section 3.1.3
+        let bytes = [0x12, 0x34];
+        assert_eq!(
+            vec![0, 0, 0, 1, 0, 0, 1, 0],
+            (0..8)
+                .map(|index| coefficient(&bytes, index, 1))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![0, 1, 0, 2],
+            (0..4)
+                .map(|index| coefficient(&bytes, index, 2))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            (0..4)
+                .map(|index| coefficient(&bytes, index, 4))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(0x12, coefficient(&bytes, 0, 8));
+
+        // the checksum of an all-zero digest with w = 8 is 32 * 255, which
+        // fills the last two of the 34 chains
+        let digits = ots_digits(LmotsType::Sha256N32W8, &[0u8; 32]);
+        assert_eq!([0x1f, 0xe0], digits[32..]);
+    }
+
+    #[test]
+    fn test_lms() {
+        let mut private_key = LmsPrivateKey::new(
+            LmsType::Sha256M32H5,
+            LmotsType::Sha256N32W4,
+            IDENTIFIER,
+            SEED,
+        );
+        let public_key = private_key.public_key();
+        assert_eq!(32, private_key.remaining());
+
+        let first = private_key.sign(b"firmware manifest v1").unwrap();
+        let second = private_key.sign(b"firmware manifest v2").unwrap();
+        assert_eq!(4 + 2180 + 4 + 5 * 32, first.len());
+        assert_eq!(Ok(()), public_key.verify(b"firmware manifest v1", &first));
+        assert_eq!(Ok(()), public_key.verify(b"firmware manifest v2", &second));
+        assert_eq!(
+            Err(LmsError::InvalidSignature),
+            public_key.verify(b"firmware manifest v2", &first)
+        );
+
+        let mut tampered = second.clone();
+        tampered[100] ^= 1;
+        assert!(public_key
+            .verify(b"firmware manifest v2", &tampered)
+            .is_err());
+        assert_eq!(
+            Err(LmsError::InvalidLength),
+            public_key.verify(b"firmware manifest v2", &second[1..])
+        );
+
+        let public_key = LmsPublicKey::from_bytes(&public_key.to_bytes()).unwrap();
+        assert_eq!(Ok(()), public_key.verify(b"firmware manifest v1", &first));
+    }
+
+    #[test]
+    fn test_lms_state() {
+        let mut private_key = LmsPrivateKey::new(
+            LmsType::Sha256M32H5,
+            LmotsType::Sha256N32W4,
+            IDENTIFIER,
+            SEED,
+        );
+        private_key.sign(b"one").unwrap();
+
+        // the saved state picks up where the key left off, and never reuses
+        // the first one-time key
+        let mut restored = LmsPrivateKey::from_bytes(&private_key.to_bytes()).unwrap();
+        assert_eq!(31, restored.remaining());
+        let signature = restored.sign(b"two").unwrap();
+        assert_eq!(1, read_u32(&signature, 0).unwrap());
+        assert_eq!(Ok(()), private_key.public_key().verify(b"two", &signature));
+
+        while restored.remaining() > 0 {
+            restored.sign(b"more").unwrap();
+        }
+        assert_eq!(Err(LmsError::Exhausted), restored.sign(b"one too many"));
+
+        let mut saved = restored.to_bytes();
+        saved[59] = 33;
+        assert_eq!(
+            Some(LmsError::InvalidState),
+            LmsPrivateKey::from_bytes(&saved).err()
+        );
+        saved[3] = 42;
+        assert_eq!(
+            Some(LmsError::UnknownType(42)),
+            LmsPrivateKey::from_bytes(&saved).err()
+        );
+    }
+
+    #[test]
+    fn test_hss() {
+        let levels = [
+            (LmsType::Sha256M32H5, LmotsType::Sha256N32W4),
+            (LmsType::Sha256M32H5, LmotsType::Sha256N32W8),
+        ];
+        let mut private_key = HssPrivateKey::new(&levels[..1], IDENTIFIER, SEED).unwrap();
+        private_key.keys[0].next = 31;
+        let public_key = private_key.public_key();
+        let signature = private_key.sign(b"the last one").unwrap();
+        assert_eq!(Ok(()), public_key.verify(b"the last one", &signature));
+        assert_eq!(Err(LmsError::Exhausted), private_key.sign(b"one too many"));
+
+        let levels = [
+            (LmsType::Sha256M32H5, LmotsType::Sha256N32W4),
+            (LmsType::Sha256M32H5, LmotsType::Sha256N32W4),
+        ];
+        let mut private_key = HssPrivateKey::new(&levels, IDENTIFIER, SEED).unwrap();
+        let public_key = HssPublicKey::from_bytes(&private_key.public_key().to_bytes()).unwrap();
+
+        // use up the bottom tree, so that the next signature needs a new one
+        private_key.keys[1].next = 31;
+        let last = private_key.sign(b"last in the first tree").unwrap();
+        let first = private_key.sign(b"first in the second tree").unwrap();
+        assert_eq!(Ok(()), public_key.verify(b"last in the first tree", &last));
+        assert_eq!(
+            Ok(()),
+            public_key.verify(b"first in the second tree", &first)
+        );
+        assert_ne!(last[4..64], first[4..64]);
+        assert_eq!(
+            Err(LmsError::InvalidSignature),
+            public_key.verify(b"last in the first tree", &first)
+        );
+
+        let mut restored = HssPrivateKey::from_bytes(&private_key.to_bytes()).unwrap();
+        let next = restored.sign(b"after a restart").unwrap();
+        assert_eq!(Ok(()), public_key.verify(b"after a restart", &next));
+        assert_eq!(private_key.sign(b"after a restart").unwrap(), next);
+
+        assert_eq!(
+            Some(LmsError::InvalidLevels),
+            HssPrivateKey::new(&[], IDENTIFIER, SEED).err()
+        );
+    }
+
+    #[test]
+    fn test_rfc_8554() {
+        // the private keys of test case 2 in appendix F were made from these
+        // seeds as appendix A describes, so the public keys should come out
+        // the same
+        let top = LmsPrivateKey::new(
+            LmsType::Sha256M32H10,
+            LmotsType::Sha256N32W4,
+            [
+                0xd0, 0x8f, 0xab, 0xd4, 0xa2, 0x09, 0x1f, 0xf0, 0xa8, 0xcb, 0x4e, 0xd8, 0x34, 0xe7,
+                0x45, 0x34,
+            ],
+            [
+                0x55, 0x8b, 0x89, 0x66, 0xc4, 0x8a, 0xe9, 0xcb, 0x89, 0x8b, 0x42, 0x3c, 0x83, 0x44,
+                0x3a, 0xae, 0x01, 0x4a, 0x72, 0xf1, 0xb1, 0xab, 0x5c, 0xc8, 0x5c, 0xf1, 0xd8, 0x92,
+                0x90, 0x3b, 0x54, 0x39,
+            ],
+        );
+        let public_key = HssPublicKey {
+            levels: 2,
+            root: top.public_key(),
+        };
+        assert_eq!(
+            "000000020000000600000003d08fabd4a2091ff0a8cb4ed834e74534\
+             32a58885cd9ba0431235466bff9651c6c92124404d45fa53cf161c28f1ad5a8e",
+            bytes_to_hex(&public_key.to_bytes())
+        );
+
+        let bottom = LmsPrivateKey::new(
+            LmsType::Sha256M32H5,
+            LmotsType::Sha256N32W8,
+            [
+                0x21, 0x5f, 0x83, 0xb7, 0xcc, 0xb9, 0xac, 0xbc, 0xd0, 0x8d, 0xb9, 0x7b, 0x0d, 0x04,
+                0xdc, 0x2b,
+            ],
+            [
+                0xa1, 0xc4, 0x69, 0x6e, 0x26, 0x08, 0x03, 0x5a, 0x88, 0x61, 0x00, 0xd0, 0x5c, 0xd9,
+                0x99, 0x45, 0xeb, 0x33, 0x70, 0x73, 0x18, 0x84, 0xa8, 0x23, 0x5e, 0x2f, 0xb3, 0xd4,
+                0xd7, 0x1f, 0x25, 0x47,
+            ],
+        );
+        assert_eq!(
+            "0000000500000004215f83b7ccb9acbcd08db97b0d04dc2b\
+             a1cd035833e0e90059603f26e07ad2aad152338e7a5e5984bcd5f7bb4eba40b7",
+            bytes_to_hex(&bottom.public_key().to_bytes())
+        );
+    }
+
+    #[test]
+    fn test_rfc_8554_signature() {
+        // test case 1 of appendix F: a two-level HSS signature, each level
+        // LMS_SHA256_M32_H5 with LMOTS_SHA256_N32_W8
+        const PUBLIC_KEY: &str = concat!(
+            "000000020000000500000004",
+            "61a5d57d37f5e46bfb7520806b07a1b8",
+            "50650e3b31fe4a773ea29a07f09cf2ea30e579f0df58ef8e298da0434cb2b878",
+        );
+        const MESSAGE: &[u8] = b"The powers not delegated to the United States by the \
+            Constitution, nor prohibited by it to the States, are reserved to the States \
+            respectively, or to the people.\n";
+        const SIGNATURE: &str = concat!(
+            "000000010000000500000004d32b56671d7eb98833c49b433c272586bc4a1c8a",
+            "8970528ffa04b966f9426eb9965a25bfd37f196b9073f3d4a232feb69128ec45",
+            "146f86292f9dff9610a7bf95a64c7f60f6261a62043f86c70324b7707f5b4a8a",
+            "6e19c114c7be866d488778a0e05fd5c6509a6e61d559cf1a77a970de927d60c7",
+            "0d3de31a7fa0100994e162a2582e8ff1b10cd99d4e8e413ef469559f7d7ed12c",
+            "838342f9b9c96b83a4943d1681d84b15357ff48ca579f19f5e71f18466f2bbef",
+            "4bf660c2518eb20de2f66e3b14784269d7d876f5d35d3fbfc7039a462c716bb9",
+            "f6891a7f41ad133e9e1f6d9560b960e7777c52f060492f2d7c660e1471e07e72",
+            "655562035abc9a701b473ecbc3943c6b9c4f2405a3cb8bf8a691ca51d3f6ad2f",
+            "428bab6f3a30f55dd9625563f0a75ee390e385e3ae0b906961ecf41ae073a059",
+            "0c2eb6204f44831c26dd768c35b167b28ce8dc988a3748255230cef99ebf14e7",
+            "30632f27414489808afab1d1e783ed04516de012498682212b07810579b25036",
+            "5941bcc98142da13609e9768aaf65de7620dabec29eb82a17fde35af15ad238c",
+            "73f81bdb8dec2fc0e7f932701099762b37f43c4a3c20010a3d72e2f606be108d",
+            "310e639f09ce7286800d9ef8a1a40281cc5a7ea98d2adc7c7400c2fe5a101552",
+            "df4e3cccfd0cbf2ddf5dc6779cbbc68fee0c3efe4ec22b83a2caa3e48e0809a0",
+            "a750b73ccdcf3c79e6580c154f8a58f7f24335eec5c5eb5e0cf01dcf44394240",
+            "95fceb077f66ded5bec73b27c5b9f64a2a9af2f07c05e99e5cf80f00252e39db",
+            "32f6c19674f190c9fbc506d826857713afd2ca6bb85cd8c107347552f30575a5",
+            "417816ab4db3f603f2df56fbc413e7d0acd8bdd81352b2471fc1bc4f1ef296fe",
+            "a1220403466b1afe78b94f7ecf7cc62fb92be14f18c2192384ebceaf8801afdf",
+            "947f698ce9c6ceb696ed70e9e87b0144417e8d7baf25eb5f70f09f016fc925b4",
+            "db048ab8d8cb2a661ce3b57ada67571f5dd546fc22cb1f97e0ebd1a65926b123",
+            "4fd04f171cf469c76b884cf3115cce6f792cc84e36da58960c5f1d760f32c12f",
+            "aef477e94c92eb75625b6a371efc72d60ca5e908b3a7dd69fef0249150e3eebd",
+            "fed39cbdc3ce9704882a2072c75e13527b7a581a556168783dc1e97545e31865",
+            "ddc46b3c957835da252bb7328d3ee2062445dfb85ef8c35f8e1f3371af34023c",
+            "ef626e0af1e0bc017351aae2ab8f5c612ead0b729a1d059d02bfe18efa971b73",
+            "00e882360a93b025ff97e9e0eec0f3f3f13039a17f88b0cf808f488431606cb1",
+            "3f9241f40f44e537d302c64a4f1f4ab949b9feefadcb71ab50ef27d6d6ca8510",
+            "f150c85fb525bf25703df7209b6066f09c37280d59128d2f0f637c7d7d7fad4e",
+            "d1c1ea04e628d221e3d8db77b7c878c9411cafc5071a34a00f4cf07738912753",
+            "dfce48f07576f0d4f94f42c6d76f7ce973e9367095ba7e9a3649b7f461d9f9ac",
+            "1332a4d1044c96aefee67676401b64457c54d65fef6500c59cdfb69af7b6dddf",
+            "cb0f086278dd8ad0686078dfb0f3f79cd893d314168648499898fbc0ced5f95b",
+            "74e8ff14d735cdea968bee7400000005d8b8112f9200a5e50c4a262165bd342c",
+            "d800b8496810bc716277435ac376728d129ac6eda839a6f357b5a04387c5ce97",
+            "382a78f2a4372917eefcbf93f63bb59112f5dbe400bd49e4501e859f885bf073",
+            "6e90a509b30a26bfac8c17b5991c157eb5971115aa39efd8d564a6b90282c316",
+            "8af2d30ef89d51bf14654510a12b8a144cca1848cf7da59cc2b3d9d0692dd2a2",
+            "0ba3863480e25b1b85ee860c62bf51360000000500000004d2f14ff6346af964",
+            "569f7d6cb880a1b66c5004917da6eafe4d9ef6c6407b3db0e5485b122d9ebe15",
+            "cda93cfec582d7ab0000000a000000040703c491e7558b35011ece3592eaa5da",
+            "4d918786771233e8353bc4f62323185c95cae05b899e35dffd71705470620998",
+            "8ebfdf6e37960bb5c38d7657e8bffeef9bc042da4b4525650485c66d0ce19b31",
+            "7587c6ba4bffcc428e25d08931e72dfb6a120c5612344258b85efdb7db1db9e1",
+            "865a73caf96557eb39ed3e3f426933ac9eeddb03a1d2374af7bf771855774562",
+            "37f9de2d60113c23f846df26fa942008a698994c0827d90e86d43e0df7f4bfcd",
+            "b09b86a373b98288b7094ad81a0185ac100e4f2c5fc38c003c1ab6fea479eb2f",
+            "5ebe48f584d7159b8ada03586e65ad9c969f6aecbfe44cf356888a7b15a3ff07",
+            "4f771760b26f9c04884ee1faa329fbf4e61af23aee7fa5d4d9a5dfcf43c4c26c",
+            "e8aea2ce8a2990d7ba7b57108b47dabfbeadb2b25b3cacc1ac0cef346cbb90fb",
+            "044beee4fac2603a442bdf7e507243b7319c9944b1586e899d431c7f91bcccc8",
+            "690dbf59b28386b2315f3d36ef2eaa3cf30b2b51f48b71b003dfb08249484201",
+            "043f65f5a3ef6bbd61ddfee81aca9ce60081262a00000480dcbc9a3da6fbef5c",
+            "1c0a55e48a0e729f9184fcb1407c31529db268f6fe50032a363c9801306837fa",
+            "fabdf957fd97eafc80dbd165e435d0e2dfd836a28b354023924b6fb7e48bc0b3",
+            "ed95eea64c2d402f4d734c8dc26f3ac591825daef01eae3c38e3328d00a77dc6",
+            "57034f287ccb0f0e1c9a7cbdc828f627205e4737b84b58376551d44c12c3c215",
+            "c812a0970789c83de51d6ad787271963327f0a5fbb6b5907dec02c9a90934af5",
+            "a1c63b72c82653605d1dcce51596b3c2b45696689f2eb382007497557692caac",
+            "4d57b5de9f5569bc2ad0137fd47fb47e664fcb6db4971f5b3e07aceda9ac130e",
+            "9f38182de994cff192ec0e82fd6d4cb7f3fe00812589b7a7ce51544045643301",
+            "6b84a59bec6619a1c6c0b37dd1450ed4f2d8b584410ceda8025f5d2d8dd0d217",
+            "6fc1cf2cc06fa8c82bed4d944e71339ece780fd025bd41ec34ebff9d4270a322",
+            "4e019fcb444474d482fd2dbe75efb20389cc10cd600abb54c47ede93e08c114e",
+            "db04117d714dc1d525e11bed8756192f929d15462b939ff3f52f2252da2ed64d",
+            "8fae88818b1efa2c7b08c8794fb1b214aa233db3162833141ea4383f1a6f120b",
+            "e1db82ce3630b3429114463157a64e91234d475e2f79cbf05e4db6a9407d72c6",
+            "bff7d1198b5c4d6aad2831db61274993715a0182c7dc8089e32c8531deed4f74",
+            "31c07c02195eba2ef91efb5613c37af7ae0c066babc69369700e1dd26eddc0d2",
+            "16c781d56e4ce47e3303fa73007ff7b949ef23be2aa4dbf25206fe45c20dd888",
+            "395b2526391a724996a44156beac808212858792bf8e74cba49dee5e8812e019",
+            "da87454bff9e847ed83db07af313743082f880a278f682c2bd0ad6887cb59f65",
+            "2e155987d61bbf6a88d36ee93b6072e6656d9ccbaae3d655852e38deb3a2dcf8",
+            "058dc9fb6f2ab3d3b3539eb77b248a661091d05eb6e2f297774fe6053598457c",
+            "c61908318de4b826f0fc86d4bb117d33e865aa805009cc2918d9c2f840c4da43",
+            "a703ad9f5b5806163d7161696b5a0adc00000005d5c0d1bebb06048ed6fe2ef2",
+            "c6cef305b3ed633941ebc8b3bec9738754cddd60e1920ada52f43d055b5031ce",
+            "e6192520d6a5115514851ce7fd448d4a39fae2ab2335b525f484e9b40d6a4a96",
+            "9394843bdcf6d14c48e8015e08ab92662c05c6e9f90b65a7a6201689999f32bf",
+            "d368e5e3ec9cb70ac7b8399003f175c40885081a09ab3034911fe125631051df",
+            "0408b3946b0bde790911e8978ba07dd56c73e7ee",
+        );
+
+        let public_key = HssPublicKey::from_bytes(&hex_to_bytes(PUBLIC_KEY).unwrap()).unwrap();
+        let mut signature = hex_to_bytes(SIGNATURE).unwrap();
+        assert_eq!(Ok(()), public_key.verify(MESSAGE, &signature));
+
+        let last = signature.len() - 1;
+        signature[last] ^= 1;
+        assert_eq!(
+            Err(LmsError::InvalidSignature),
+            public_key.verify(MESSAGE, &signature)
+        );
+    }
+
+}
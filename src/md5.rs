@@ -1,7 +1,9 @@
 // #![allow(clippy::unreadable_literal)]
 
-use crate::utils::to_hex_string;
+use crate::digest::Digest;
 use crate::utils::preprocess_little_endian;
+use crate::utils::to_hex_string;
+use crate::utils::BlockBuffer;
 use crate::utils::BLOCK_SIZE;
 use std::convert::TryInto;
 
@@ -106,21 +108,95 @@ fn build_rotations() -> Vec<u32> {
         .collect::<Vec<u32>>()
 }
 
-pub fn md5(raw_message: &str) -> String {
-    let should_debug = std::env::var("SHOULD_DEBUG").is_ok();
+const INITIAL_HASH_STATE: [u32; 4] = [
+    0x6745_2301u32,
+    0xefcd_ab89u32,
+    0x98ba_dcfeu32,
+    0x1032_5476u32,
+];
 
-    const INITIAL_HASH_STATE: [u32; 4] = [
-        0x6745_2301u32,
-        0xefcd_ab89u32,
-        0x98ba_dcfeu32,
-        0x1032_5476u32,
-    ];
+const OPERATIONS: [fn(u32, u32, u32) -> u32; 4] = [round_0_op, round_1_op, round_2_op, round_3_op];
 
-    const OPERATIONS: [fn(u32, u32, u32) -> u32; 4] =
-        [round_0_op, round_1_op, round_2_op, round_3_op];
+fn compress(hash_state: &mut [u32; 4], words: &[u32], should_debug: bool) {
+    // 16 * 32 = 512, hence our 16 words for enumeration.
+    assert_eq!(16, words.len());
 
     let rotations: Vec<u32> = build_rotations();
 
+    // every block starts over from the hash state so far
+    let mut state = State::new(*hash_state);
+
+    // Because we have 64 rotations, this will go round 64x per words-block
+    for (index, rotation) in rotations.iter().enumerate() {
+        // --- varies over index, invariant over message ---
+        // both our operation and how we index into the word are based on an
+        // array of four values
+        let round_index = (index / 16usize) % 4usize;
+        if should_debug && index % 16 == 0 {
+            println!("\nRound {}", round_index + 1);
+        }
+
+        let operation = OPERATIONS[round_index];
+        let word_index: usize = match round_index {
+            0 => index,
+            1 => (5 * index + 1) % 16,
+            2 => (3 * index + 5) % 16,
+            3 => (7 * index) % 16,
+            _ => panic!("Indexing broke"),
+        };
+
+        state.index = index;
+        state.round_index = round_index;
+        state.word_index = word_index;
+        state.word_index_one = state.word_index + 1;
+
+        // `try_into.unwrap` is us saying "no seriously, this is totally not
+        // bigger than a u32 I promise be nice"
+        // since `i` varies between 0 and 63, that's a solid bet
+        let constant = get_md5_k(index.try_into().unwrap());
+        state.constant = constant;
+
+        // --- variant over message ---
+        let word: u32 = words[word_index];
+
+        let operation_result = operation(state.slots[1], state.slots[2], state.slots[3]);
+
+        let intermediate_value = operation_result
+            .wrapping_add(state.slots[0])
+            .wrapping_add(constant)
+            .wrapping_add(word)
+            .rotate_left(*rotation);
+
+        /*
+         assign to the first slot ('a') here + rotate the set *after*
+
+         this is equivalent to:
+              1. assigning this value to a temp variable
+              2. rotating everything rightwards by 1
+              3. assigning the temp variable to the first slot
+
+        (this is also why some impls look like they assign the
+        calculated value to `b` and others to `a`)
+        */
+        state.slots[0] = state.slots[1].wrapping_add(intermediate_value);
+
+        if should_debug {
+            state.pretty_print();
+        }
+
+        // rotate names after we print
+        state.rotate_right();
+    }
+
+    hash_state[0] = hash_state[0].wrapping_add(state.slots[0]);
+    hash_state[1] = hash_state[1].wrapping_add(state.slots[1]);
+    hash_state[2] = hash_state[2].wrapping_add(state.slots[2]);
+    hash_state[3] = hash_state[3].wrapping_add(state.slots[3]);
+}
+
+pub fn md5(raw_message: &str) -> String {
+    let should_debug = std::env::var("SHOULD_DEBUG").is_ok();
+
     let mut hash_state: [u32; 4] = INITIAL_HASH_STATE;
 
     let message = preprocess_little_endian(raw_message.to_string());
@@ -128,82 +204,11 @@ pub fn md5(raw_message: &str) -> String {
 
     assert_eq!(0, message_as_chars.len() % BLOCK_SIZE);
 
-    let mut state = State::new(hash_state);
-
     for chunk in message_as_chars.chunks_exact(BLOCK_SIZE) {
         // Each chunk is 512 bits
         // `to_md5_words` is a vec of 32-bit words
         let words = to_md5_words(chunk);
-
-        // 16 * 32 = 512, hence our 16 words for enumeration.
-        assert_eq!(16, words.len());
-
-        // Because we have 64 rotations, this will go round 64x per words-block
-        for (index, rotation) in rotations.iter().enumerate() {
-            // --- varies over index, invariant over message ---
-            // both our operation and how we index into the word are based on an
-            // array of four values
-            let round_index = (index / 16usize) % 4usize;
-            if should_debug && index % 16 == 0 {
-                println!("\nRound {}", round_index + 1);
-            }
-
-            let operation = OPERATIONS[round_index];
-            let word_index: usize = match round_index {
-                0 => index,
-                1 => (5 * index + 1) % 16,
-                2 => (3 * index + 5) % 16,
-                3 => (7 * index) % 16,
-                _ => panic!("Indexing broke"),
-            };
-
-            state.index = index;
-            state.round_index = round_index;
-            state.word_index = word_index;
-            state.word_index_one = state.word_index + 1;
-
-            // `try_into.unwrap` is us saying "no seriously, this is totally not
-            // bigger than a u32 I promise be nice"
-            // since `i` varies between 0 and 63, that's a solid bet
-            let constant = get_md5_k(index.try_into().unwrap());
-            state.constant = constant;
-
-            // --- variant over message ---
-            let word: u32 = words[word_index];
-
-            let operation_result = operation(state.slots[1], state.slots[2], state.slots[3]);
-
-            let intermediate_value = operation_result
-                .wrapping_add(state.slots[0])
-                .wrapping_add(constant)
-                .wrapping_add(word)
-                .rotate_left(*rotation);
-
-            /*
-             assign to the first slot ('a') here + rotate the set *after*
-
-             this is equivalent to:
-                  1. assigning this value to a temp variable
-                  2. rotating everything rightwards by 1
-                  3. assigning the temp variable to the first slot
-
-            (this is also why some impls look like they assign the
-            calculated value to `b` and others to `a`)
-            */
-            state.slots[0] = state.slots[1].wrapping_add(intermediate_value);
-
-            if should_debug {
-                state.pretty_print();
-            }
-
-            // rotate names after we print
-            state.rotate_right();
-        }
-
-        hash_state[0] = hash_state[0].wrapping_add(state.slots[0]);
-        hash_state[1] = hash_state[1].wrapping_add(state.slots[1]);
-        hash_state[2] = hash_state[2].wrapping_add(state.slots[2]);
-        hash_state[3] = hash_state[3].wrapping_add(state.slots[3]);
+        compress(&mut hash_state, &words, should_debug);
     }
 
     hash_state
@@ -212,6 +217,87 @@ pub fn md5(raw_message: &str) -> String {
         .collect::<String>()
 }
 
+/// MD5 over bytes, fed in a piece at a time. `md5` is the same hash for a
+/// whole `&str` at once.
+///
+/// MD5 is little endian all the way through: the message words, the length in
+/// the padding, and the digest itself.
+#[derive(Clone)]
+pub struct Md5 {
+    hash_state: [u32; 4],
+    buffer: BlockBuffer<64>,
+    should_debug: bool,
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Md5 {
+            hash_state: INITIAL_HASH_STATE,
+            buffer: BlockBuffer::default(),
+            should_debug: std::env::var("SHOULD_DEBUG").is_ok(),
+        }
+    }
+}
+
+impl Md5 {
+    /// The same as `Sha1::from_state`, with MD5's four words of state (which
+    /// the digest holds little endian).
+    pub fn from_state(hash_state: [u32; 4], length: u64) -> Self {
+        Md5 {
+            hash_state,
+            buffer: BlockBuffer::resume(length),
+            ..Md5::default()
+        }
+    }
+
+    fn compress_block(hash_state: &mut [u32; 4], block: &[u8; 64], should_debug: bool) {
+        let words = block
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect::<Vec<u32>>();
+
+        compress(hash_state, &words, should_debug);
+    }
+}
+
+impl Digest for Md5 {
+    const BLOCK_LENGTH: usize = 64;
+    const OUTPUT_LENGTH: usize = 16;
+
+    type Output = [u8; 16];
+
+    fn update(&mut self, bytes: &[u8]) {
+        let Md5 {
+            hash_state,
+            buffer,
+            should_debug,
+        } = self;
+
+        buffer.update(bytes, |block| {
+            Md5::compress_block(hash_state, block, *should_debug)
+        });
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let Md5 {
+            hash_state,
+            buffer,
+            should_debug,
+        } = &mut self;
+
+        buffer.finish(true, |block| {
+            Md5::compress_block(hash_state, block, *should_debug)
+        });
+
+        let mut output = [0u8; 16];
+        for (bytes, value) in output.chunks_exact_mut(4).zip(hash_state.iter()) {
+            bytes.copy_from_slice(&value.to_le_bytes());
+        }
+
+        output
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -247,4 +333,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_md5_streaming() {
+        // long enough for more than one block, which needs the working state
+        // reset from the hash state at the start of each block
+        let message = "a".repeat(100);
+        let expected = "36a92cc94a9e0fa21f625f8bfb007adf";
+        assert_eq!(expected, md5(&message));
+
+        let mut hasher = Md5::default();
+        for piece in message.as_bytes().chunks(7) {
+            hasher.update(piece);
+        }
+        assert_eq!(expected, crate::utils::bytes_to_hex(&hasher.finalize()));
+    }
+
 }
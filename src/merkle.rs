@@ -0,0 +1,461 @@
+//! Merkle trees as used by Certificate Transparency (RFC 6962), with the
+//! proof verification algorithms from RFC 9162.
+//!
+//! A Merkle tree hashes a list of entries into a single root hash, in a way
+//! that lets a log prove two things cheaply -- with a logarithmic number of
+//! hashes, rather than the whole log:
+//!
+//! * an *audit path* (inclusion proof) shows that an entry is in the tree
+//! * a *consistency proof* shows that an older, smaller tree is a prefix of
+//!   a newer one, so nothing was changed or taken out in between
+//!
+//! Leaves are hashed as `SHA-256(0x00 || entry)` and inner nodes as
+//! `SHA-256(0x01 || left || right)`. Without the different prefixes, an inner
+//! node could be passed off as a leaf (a second-preimage attack).
+//!
+//! Trees don't have to be a power of two in size: the left subtree always
+//! holds the largest power of two entries smaller than the tree, and the right
+//! subtree holds the rest.
+
+use crate::digest::Digest;
+use crate::sha2::Sha256;
+use std::fmt;
+
+pub type Hash = [u8; 32];
+
+pub fn leaf_hash(entry: &[u8]) -> Hash {
+    let mut hasher = Sha256::default();
+    hasher.update(&[0x00]);
+    hasher.update(entry);
+    hasher.finalize()
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::default();
+    hasher.update(&[0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MerkleError {
+    /// The leaf index isn't inside the tree, or the tree sizes are out of
+    /// order.
+    OutOfRange,
+    /// The proof has the wrong number of hashes for the tree sizes.
+    WrongProofLength,
+    /// The proof is well-formed, but leads to a different root.
+    RootMismatch,
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MerkleError::OutOfRange => write!(formatter, "index or tree size out of range"),
+            MerkleError::WrongProofLength => write!(formatter, "the proof has the wrong length"),
+            MerkleError::RootMismatch => write!(formatter, "the proof doesn't match the root"),
+        }
+    }
+}
+
+impl std::error::Error for MerkleError {}
+
+/// The largest power of two smaller than `size` (which must be at least 2):
+/// how many leaves go in the left subtree.
+fn split_point(size: usize) -> usize {
+    let mut split = 1;
+    while split << 1 < size {
+        split <<= 1;
+    }
+    split
+}
+
+/// MTH, the Merkle Tree Hash, over already-hashed leaves.
+fn subtree_root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::digest(b""),
+        1 => leaves[0],
+        size => {
+            let split = split_point(size);
+            node_hash(
+                &subtree_root(&leaves[..split]),
+                &subtree_root(&leaves[split..]),
+            )
+        }
+    }
+}
+
+/// PATH(m, D[n]) from RFC 6962, section 2.1.1.
+fn subtree_path(index: usize, leaves: &[Hash], path: &mut Vec<Hash>) {
+    if leaves.len() <= 1 {
+        return;
+    }
+
+    let split = split_point(leaves.len());
+    if index < split {
+        subtree_path(index, &leaves[..split], path);
+        path.push(subtree_root(&leaves[split..]));
+    } else {
+        subtree_path(index - split, &leaves[split..], path);
+        path.push(subtree_root(&leaves[..split]));
+    }
+}
+
+/// SUBPROOF(m, D[n], b) from RFC 6962, section 2.1.2. `whole` is `b`: whether
+/// the old tree is still the whole of the subtree, in which case its root is
+/// already known to the verifier and can be left out.
+fn subtree_consistency(old_size: usize, leaves: &[Hash], whole: bool, proof: &mut Vec<Hash>) {
+    if old_size == leaves.len() {
+        if !whole {
+            proof.push(subtree_root(leaves));
+        }
+        return;
+    }
+
+    let split = split_point(leaves.len());
+    if old_size <= split {
+        subtree_consistency(old_size, &leaves[..split], whole, proof);
+        proof.push(subtree_root(&leaves[split..]));
+    } else {
+        subtree_consistency(old_size - split, &leaves[split..], false, proof);
+        proof.push(subtree_root(&leaves[..split]));
+    }
+}
+
+/// An append-only log. Only the leaf hashes are kept, not the entries.
+#[derive(Clone, Debug, Default)]
+pub struct MerkleTree {
+    leaves: Vec<Hash>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        MerkleTree::default()
+    }
+
+    /// Appends an entry, and hands back its leaf index.
+    pub fn push(&mut self, entry: &[u8]) -> usize {
+        self.push_leaf_hash(leaf_hash(entry))
+    }
+
+    /// Appends a leaf that has already been through `leaf_hash`.
+    pub fn push_leaf_hash(&mut self, hash: Hash) -> usize {
+        self.leaves.push(hash);
+        self.leaves.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn leaf_hash(&self, index: usize) -> Option<&Hash> {
+        self.leaves.get(index)
+    }
+
+    pub fn root(&self) -> Hash {
+        subtree_root(&self.leaves)
+    }
+
+    /// The root the tree had when it held its first `size` leaves.
+    pub fn root_at(&self, size: usize) -> Option<Hash> {
+        self.leaves.get(..size).map(subtree_root)
+    }
+
+    /// The audit path for leaf `index` in the tree of the first `size` leaves.
+    pub fn audit_path(&self, index: usize, size: usize) -> Option<Vec<Hash>> {
+        if index >= size || size > self.leaves.len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        subtree_path(index, &self.leaves[..size], &mut path);
+        Some(path)
+    }
+
+    /// The proof that the tree of the first `old_size` leaves is a prefix of
+    /// the tree of the first `new_size`.
+    pub fn consistency_proof(&self, old_size: usize, new_size: usize) -> Option<Vec<Hash>> {
+        if old_size == 0 || old_size > new_size || new_size > self.leaves.len() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        subtree_consistency(old_size, &self.leaves[..new_size], true, &mut proof);
+        Some(proof)
+    }
+}
+
+/// Checks an audit path, following RFC 9162, section 2.1.3.2.
+///
+/// The path is walked from the leaf up. The bits of the leaf's index (and of
+/// the index of the last leaf) say at each level whether the next hash in the
+/// path goes on the left or the right -- and when the leaf is on the
+/// right-hand edge of the tree, which levels have no sibling at all.
+pub fn verify_inclusion(
+    leaf_hash: &Hash,
+    index: usize,
+    size: usize,
+    path: &[Hash],
+    root: &Hash,
+) -> Result<(), MerkleError> {
+    if index >= size {
+        return Err(MerkleError::OutOfRange);
+    }
+
+    let mut node = index;
+    let mut last = size - 1;
+    let mut hash = *leaf_hash;
+
+    for sibling in path {
+        if last == 0 {
+            return Err(MerkleError::WrongProofLength);
+        }
+
+        if node & 1 == 1 || node == last {
+            hash = node_hash(sibling, &hash);
+            if node & 1 == 0 {
+                // on the right-hand edge: climb past the levels where this
+                // subtree is a left child with nothing to its right
+                while node & 1 == 0 && node != 0 {
+                    node >>= 1;
+                    last >>= 1;
+                }
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+
+        node >>= 1;
+        last >>= 1;
+    }
+
+    if last != 0 {
+        return Err(MerkleError::WrongProofLength);
+    }
+
+    if hash != *root {
+        return Err(MerkleError::RootMismatch);
+    }
+
+    Ok(())
+}
+
+/// Checks a consistency proof, following RFC 9162, section 2.1.4.2.
+///
+/// The proof lets the verifier rebuild both the old root and the new one from
+/// the same hashes; if both come out right, the new tree must have the old one
+/// as its prefix.
+pub fn verify_consistency(
+    old_size: usize,
+    new_size: usize,
+    old_root: &Hash,
+    new_root: &Hash,
+    proof: &[Hash],
+) -> Result<(), MerkleError> {
+    if old_size == 0 || old_size > new_size {
+        return Err(MerkleError::OutOfRange);
+    }
+
+    if old_size == new_size {
+        if !proof.is_empty() {
+            return Err(MerkleError::WrongProofLength);
+        }
+        if old_root != new_root {
+            return Err(MerkleError::RootMismatch);
+        }
+        return Ok(());
+    }
+
+    // when the old tree is a complete subtree, its root is the starting point
+    // and the proof leaves it out
+    let mut hashes = Vec::with_capacity(proof.len() + 1);
+    if old_size.is_power_of_two() {
+        hashes.push(*old_root);
+    }
+    hashes.extend_from_slice(proof);
+
+    let (first, rest) = hashes.split_first().ok_or(MerkleError::WrongProofLength)?;
+
+    let mut node = old_size - 1;
+    let mut last = new_size - 1;
+    while node & 1 == 1 {
+        node >>= 1;
+        last >>= 1;
+    }
+
+    let mut old_hash = *first;
+    let mut new_hash = *first;
+
+    for sibling in rest {
+        if last == 0 {
+            return Err(MerkleError::WrongProofLength);
+        }
+
+        if node & 1 == 1 || node == last {
+            old_hash = node_hash(sibling, &old_hash);
+            new_hash = node_hash(sibling, &new_hash);
+            if node & 1 == 0 {
+                while node & 1 == 0 && node != 0 {
+                    node >>= 1;
+                    last >>= 1;
+                }
+            }
+        } else {
+            new_hash = node_hash(&new_hash, sibling);
+        }
+
+        node >>= 1;
+        last >>= 1;
+    }
+
+    if last != 0 {
+        return Err(MerkleError::WrongProofLength);
+    }
+
+    if old_hash != *old_root || new_hash != *new_root {
+        return Err(MerkleError::RootMismatch);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::bytes_to_hex;
+
+    // the test data from the Certificate Transparency reference code
+    fn reference_tree() -> MerkleTree {
+        let entries: [&[u8]; 8] = [
+            b"",
+            &[0x00],
+            &[0x10],
+            &[0x20, 0x21],
+            &[0x30, 0x31],
+            &[0x40, 0x41, 0x42, 0x43],
+            &[0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57],
+            &[
+                0x60, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x6b, 0x6c, 0x6d,
+                0x6e, 0x6f,
+            ],
+        ];
+
+        let mut tree = MerkleTree::new();
+        for entry in entries.iter() {
+            tree.push(entry);
+        }
+        tree
+    }
+
+    #[test]
+    fn test_roots() {
+        let expected = [
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+            "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+            "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+            "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+        ];
+
+        let tree = reference_tree();
+        for (size, root) in expected.iter().enumerate() {
+            assert_eq!(*root, bytes_to_hex(&tree.root_at(size + 1).unwrap()));
+        }
+
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            bytes_to_hex(&MerkleTree::new().root())
+        );
+    }
+
+    #[test]
+    fn test_audit_paths() {
+        let tree = reference_tree();
+
+        // every leaf, in every tree size it's part of
+        for size in 1..=tree.len() {
+            let root = tree.root_at(size).unwrap();
+            for index in 0..size {
+                let path = tree.audit_path(index, size).unwrap();
+                let leaf = tree.leaf_hash(index).unwrap();
+                assert_eq!(Ok(()), verify_inclusion(leaf, index, size, &path, &root));
+
+                // a wrong index or a missing hash must not pass
+                if size > 1 {
+                    let other = (index + 1) % size;
+                    assert!(verify_inclusion(leaf, other, size, &path, &root).is_err());
+                    assert!(verify_inclusion(leaf, index, size, &path[1..], &root).is_err());
+                }
+            }
+        }
+
+        assert_eq!(3, tree.audit_path(0, 8).unwrap().len());
+        assert_eq!(None, tree.audit_path(8, 8));
+        assert_eq!(
+            Err(MerkleError::OutOfRange),
+            verify_inclusion(&tree.root(), 8, 8, &[], &tree.root())
+        );
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let tree = reference_tree();
+
+        for new_size in 1..=tree.len() {
+            let new_root = tree.root_at(new_size).unwrap();
+            for old_size in 1..=new_size {
+                let old_root = tree.root_at(old_size).unwrap();
+                let proof = tree.consistency_proof(old_size, new_size).unwrap();
+                assert_eq!(
+                    Ok(()),
+                    verify_consistency(old_size, new_size, &old_root, &new_root, &proof)
+                );
+
+                if old_size < new_size {
+                    let wrong_root = leaf_hash(b"not the old root");
+                    assert!(
+                        verify_consistency(old_size, new_size, &wrong_root, &new_root, &proof)
+                            .is_err()
+                    );
+                }
+            }
+        }
+
+        // the examples in RFC 6962, section 2.1.3: [c, d, g, l], [l] and [i, j, k]
+        assert_eq!(4, tree.consistency_proof(3, 7).unwrap().len());
+        assert_eq!(1, tree.consistency_proof(4, 7).unwrap().len());
+        assert_eq!(3, tree.consistency_proof(6, 7).unwrap().len());
+        assert_eq!(None, tree.consistency_proof(0, 7));
+    }
+
+    #[test]
+    fn test_large_tree() {
+        let mut tree = MerkleTree::new();
+        for index in 0..1000u32 {
+            tree.push(&index.to_be_bytes());
+        }
+
+        let root = tree.root();
+        let path = tree.audit_path(777, 1000).unwrap();
+        assert_eq!(10, path.len());
+        assert_eq!(
+            Ok(()),
+            verify_inclusion(&leaf_hash(&777u32.to_be_bytes()), 777, 1000, &path, &root)
+        );
+
+        let proof = tree.consistency_proof(600, 1000).unwrap();
+        assert_eq!(
+            Ok(()),
+            verify_consistency(600, 1000, &tree.root_at(600).unwrap(), &root, &proof)
+        );
+    }
+
+}
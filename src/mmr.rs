@@ -0,0 +1,495 @@
+//! Merkle Mountain Ranges: an append-only accumulator built out of perfect
+//! binary Merkle trees.
+//!
+//! A plain Merkle tree (see `merkle`) has to rehash a path to the root on every
+//! append, and a proof is only good for one tree size. A Merkle Mountain Range
+//! is instead a row of perfect trees ("mountains") of shrinking height, one for
+//! each bit set in the number of leaves -- 11 leaves are mountains of 8, 2
+//! and 1 leaves. Appending a leaf only merges the mountains on the right that
+//! are the same height, like carrying when adding one to a binary number, so
+//! nothing already in the range is ever rehashed.
+//!
+//! The tops of the mountains are the *peaks*, and they are everything needed
+//! to keep appending: `Peaks` is the whole state of the accumulator in
+//! `log2(leaves)` hashes, and can be saved and loaded with `to_bytes` and
+//! `from_bytes`. To prove membership, though, the inner nodes are needed too,
+//! and `MerkleMountainRange` keeps all of them.
+//!
+//! The root is the peaks "bagged" together from right to left. Leaves are
+//! hashed as `H(0x00 || entry)` and every other node as
+//! `H(0x01 || left || right)`, the same as in RFC 6962, and any `Digest` in the
+//! crate can be `H`.
+
+use crate::digest::Digest;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+
+pub fn leaf_hash<D: Digest>(entry: &[u8]) -> D::Output {
+    let mut hasher = D::default();
+    hasher.update(&[0x00]);
+    hasher.update(entry);
+    hasher.finalize()
+}
+
+pub fn node_hash<D: Digest>(left: &D::Output, right: &D::Output) -> D::Output {
+    let mut hasher = D::default();
+    hasher.update(&[0x01]);
+    hasher.update(left.as_ref());
+    hasher.update(right.as_ref());
+    hasher.finalize()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MmrError {
+    /// The leaf index isn't inside the range.
+    OutOfRange,
+    /// Saved peaks that don't have the right length for their leaf count.
+    InvalidEncoding,
+    /// The proof has the wrong number of hashes for its leaf count.
+    WrongProofLength,
+    /// The proof is well-formed, but leads to a different root.
+    RootMismatch,
+}
+
+impl fmt::Display for MmrError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MmrError::OutOfRange => write!(formatter, "leaf index out of range"),
+            MmrError::InvalidEncoding => write!(formatter, "invalid encoding of the peaks"),
+            MmrError::WrongProofLength => write!(formatter, "the proof has the wrong length"),
+            MmrError::RootMismatch => write!(formatter, "the proof doesn't match the root"),
+        }
+    }
+}
+
+impl std::error::Error for MmrError {}
+
+/// The heights of the mountains for `leaf_count` leaves, tallest (leftmost)
+/// first.
+fn mountain_heights(leaf_count: u64) -> impl Iterator<Item = u32> {
+    (0..u64::BITS)
+        .rev()
+        .filter(move |height| leaf_count >> height & 1 == 1)
+}
+
+/// How many nodes a perfect tree with `2 ** height` leaves has.
+fn mountain_size(height: u32) -> usize {
+    (2 << height) - 1
+}
+
+fn bag_peaks<D: Digest>(peaks: &[D::Output]) -> D::Output {
+    match peaks.split_last() {
+        None => D::digest(b""),
+        Some((last, rest)) => rest
+            .iter()
+            .rev()
+            .fold(last.clone(), |bagged, peak| node_hash::<D>(peak, &bagged)),
+    }
+}
+
+/// Just the peaks of a range: enough to keep appending and to check proofs,
+/// but not to make them.
+pub struct Peaks<D: Digest> {
+    leaf_count: u64,
+    peaks: Vec<D::Output>,
+}
+
+impl<D: Digest> Clone for Peaks<D> {
+    fn clone(&self) -> Self {
+        Peaks {
+            leaf_count: self.leaf_count,
+            peaks: self.peaks.clone(),
+        }
+    }
+}
+
+impl<D: Digest> Default for Peaks<D> {
+    fn default() -> Self {
+        Peaks {
+            leaf_count: 0,
+            peaks: Vec::new(),
+        }
+    }
+}
+
+impl<D: Digest> Peaks<D> {
+    pub fn new() -> Self {
+        Peaks::default()
+    }
+
+    /// Appends an entry, and hands back its leaf index.
+    pub fn append(&mut self, entry: &[u8]) -> u64 {
+        let leaf_index = self.leaf_count;
+
+        let mut hash = leaf_hash::<D>(entry);
+        let mut carry = leaf_index;
+        while carry & 1 == 1 {
+            let left = self.peaks.pop().expect("a peak for every set bit");
+            hash = node_hash::<D>(&left, &hash);
+            carry >>= 1;
+        }
+
+        self.peaks.push(hash);
+        self.leaf_count += 1;
+
+        leaf_index
+    }
+
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+
+    /// Tallest mountain first.
+    pub fn peaks(&self) -> &[D::Output] {
+        &self.peaks
+    }
+
+    pub fn root(&self) -> D::Output {
+        bag_peaks::<D>(&self.peaks)
+    }
+
+    /// The leaf count as 8 big-endian bytes, followed by the peaks.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.peaks.len() * D::OUTPUT_LENGTH);
+        bytes.extend_from_slice(&self.leaf_count.to_be_bytes());
+        for peak in self.peaks.iter() {
+            bytes.extend_from_slice(peak.as_ref());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MmrError>
+    where
+        for<'a> D::Output: TryFrom<&'a [u8]>,
+    {
+        if bytes.len() < 8 {
+            return Err(MmrError::InvalidEncoding);
+        }
+
+        let (count, rest) = bytes.split_at(8);
+        let leaf_count = u64::from_be_bytes(count.try_into().unwrap());
+
+        if rest.len() != leaf_count.count_ones() as usize * D::OUTPUT_LENGTH {
+            return Err(MmrError::InvalidEncoding);
+        }
+
+        let peaks = rest
+            .chunks_exact(D::OUTPUT_LENGTH)
+            .map(|peak| D::Output::try_from(peak).map_err(|_| MmrError::InvalidEncoding))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Peaks { leaf_count, peaks })
+    }
+}
+
+/// A range that keeps every node, so that it can prove membership of any of
+/// its leaves.
+///
+/// The nodes are stored in post-order: each mountain left to right, and in a
+/// mountain, both children before their parent. Appending only ever adds nodes
+/// at the end.
+pub struct MerkleMountainRange<D: Digest> {
+    nodes: Vec<D::Output>,
+    leaf_count: u64,
+}
+
+impl<D: Digest> Clone for MerkleMountainRange<D> {
+    fn clone(&self) -> Self {
+        MerkleMountainRange {
+            nodes: self.nodes.clone(),
+            leaf_count: self.leaf_count,
+        }
+    }
+}
+
+impl<D: Digest> Default for MerkleMountainRange<D> {
+    fn default() -> Self {
+        MerkleMountainRange {
+            nodes: Vec::new(),
+            leaf_count: 0,
+        }
+    }
+}
+
+impl<D: Digest> MerkleMountainRange<D> {
+    pub fn new() -> Self {
+        MerkleMountainRange::default()
+    }
+
+    /// Appends an entry, and hands back its leaf index.
+    pub fn append(&mut self, entry: &[u8]) -> u64 {
+        let leaf_index = self.leaf_count;
+
+        let mut hash = leaf_hash::<D>(entry);
+        self.nodes.push(hash.clone());
+
+        // the left sibling of a node at `height` is a whole mountain of that
+        // height back
+        let mut height = 0;
+        let mut carry = leaf_index;
+        while carry & 1 == 1 {
+            let left = self.nodes.len() - 1 - mountain_size(height);
+            hash = node_hash::<D>(&self.nodes[left], &hash);
+            self.nodes.push(hash.clone());
+            height += 1;
+            carry >>= 1;
+        }
+
+        self.leaf_count += 1;
+
+        leaf_index
+    }
+
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+
+    /// How many nodes, leaves included, the range holds.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn peaks(&self) -> Peaks<D> {
+        let mut peaks = Vec::new();
+        let mut offset = 0;
+        for height in mountain_heights(self.leaf_count) {
+            offset += mountain_size(height);
+            peaks.push(self.nodes[offset - 1].clone());
+        }
+
+        Peaks {
+            leaf_count: self.leaf_count,
+            peaks,
+        }
+    }
+
+    pub fn root(&self) -> D::Output {
+        self.peaks().root()
+    }
+
+    /// Proves that leaf `leaf_index` is in the range as it stands now.
+    pub fn prove(&self, leaf_index: u64) -> Result<MembershipProof<D>, MmrError> {
+        if leaf_index >= self.leaf_count {
+            return Err(MmrError::OutOfRange);
+        }
+
+        let mut path = Vec::new();
+        let mut peaks = Vec::new();
+
+        let mut offset = 0;
+        let mut first_leaf = 0;
+        for height in mountain_heights(self.leaf_count) {
+            let size = mountain_size(height);
+            let leaves = 1u64 << height;
+
+            if (first_leaf..first_leaf + leaves).contains(&leaf_index) {
+                // walk down from the peak, picking up the sibling at each step
+                let mut position = offset + size - 1;
+                let mut local_index = leaf_index - first_leaf;
+                for child_height in (0..height).rev() {
+                    let right = position - 1;
+                    let left = right - mountain_size(child_height);
+                    if local_index >> child_height & 1 == 1 {
+                        path.push(self.nodes[left].clone());
+                        position = right;
+                    } else {
+                        path.push(self.nodes[right].clone());
+                        position = left;
+                    }
+                    local_index &= !(1 << child_height);
+                }
+                path.reverse();
+            } else {
+                peaks.push(self.nodes[offset + size - 1].clone());
+            }
+
+            offset += size;
+            first_leaf += leaves;
+        }
+
+        Ok(MembershipProof {
+            leaf_index,
+            leaf_count: self.leaf_count,
+            path,
+            peaks,
+        })
+    }
+}
+
+/// The proof that an entry is leaf `leaf_index` of a range of `leaf_count`
+/// leaves: the siblings on the way up its mountain (`path`, lowest first), and
+/// the peaks of all the other mountains.
+pub struct MembershipProof<D: Digest> {
+    pub leaf_index: u64,
+    pub leaf_count: u64,
+    pub path: Vec<D::Output>,
+    pub peaks: Vec<D::Output>,
+}
+
+impl<D: Digest> Clone for MembershipProof<D> {
+    fn clone(&self) -> Self {
+        MembershipProof {
+            leaf_index: self.leaf_index,
+            leaf_count: self.leaf_count,
+            path: self.path.clone(),
+            peaks: self.peaks.clone(),
+        }
+    }
+}
+
+impl<D: Digest> MembershipProof<D> {
+    /// Rebuilds the root from `entry` and the proof, and checks it against
+    /// `root` (from `MerkleMountainRange::root` or `Peaks::root`, for the same
+    /// leaf count as the proof).
+    pub fn verify(&self, entry: &[u8], root: &D::Output) -> Result<(), MmrError> {
+        if self.leaf_index >= self.leaf_count {
+            return Err(MmrError::OutOfRange);
+        }
+
+        let heights = mountain_heights(self.leaf_count).collect::<Vec<_>>();
+        if self.peaks.len() + 1 != heights.len() {
+            return Err(MmrError::WrongProofLength);
+        }
+
+        // find the leaf's mountain: the highest set bit of the leaf count
+        // where the leaf index and the count differ
+        let mountain = heights
+            .iter()
+            .position(|height| self.leaf_index >> height & 1 == 0)
+            .expect("a leaf index below the count differs from it somewhere");
+        let height = heights[mountain];
+        if self.path.len() != height as usize {
+            return Err(MmrError::WrongProofLength);
+        }
+
+        let mut hash = leaf_hash::<D>(entry);
+        for (level, sibling) in self.path.iter().enumerate() {
+            hash = if self.leaf_index >> level & 1 == 1 {
+                node_hash::<D>(sibling, &hash)
+            } else {
+                node_hash::<D>(&hash, sibling)
+            };
+        }
+
+        let mut peaks = self.peaks.clone();
+        peaks.insert(mountain, hash);
+
+        if bag_peaks::<D>(&peaks) != *root {
+            return Err(MmrError::RootMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::Sha256;
+    use crate::utils::bytes_to_hex;
+    use crate::Sha1;
+
+    fn entry(index: u64) -> Vec<u8> {
+        format!("entry {}", index).into_bytes()
+    }
+
+    #[test]
+    fn test_small_range() {
+        let mut range = MerkleMountainRange::<Sha256>::new();
+        for index in 0..3 {
+            range.append(&entry(index));
+        }
+
+        // a mountain of two leaves and a mountain of one
+        assert_eq!(4, range.node_count());
+        let left = node_hash::<Sha256>(
+            &leaf_hash::<Sha256>(&entry(0)),
+            &leaf_hash::<Sha256>(&entry(1)),
+        );
+        let right = leaf_hash::<Sha256>(&entry(2));
+        assert_eq!(vec![left, right], range.peaks().peaks());
+        assert_eq!(node_hash::<Sha256>(&left, &right), range.root());
+
+        // eight leaves make one perfect tree, with the same root as RFC 6962
+        let mut range = MerkleMountainRange::<Sha256>::new();
+        let mut tree = crate::merkle::MerkleTree::new();
+        for index in 0..8 {
+            range.append(&entry(index));
+            tree.push(&entry(index));
+        }
+        assert_eq!(1, range.peaks().peaks().len());
+        assert_eq!(tree.root(), range.root());
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            bytes_to_hex(&MerkleMountainRange::<Sha256>::new().root())
+        );
+    }
+
+    #[test]
+    fn test_membership_proofs() {
+        let mut range = MerkleMountainRange::<Sha256>::new();
+        let mut peaks = Peaks::<Sha256>::new();
+
+        for count in 1..=40 {
+            range.append(&entry(count - 1));
+            peaks.append(&entry(count - 1));
+            assert_eq!(peaks.root(), range.root());
+
+            let root = range.root();
+            for index in 0..count {
+                let proof = range.prove(index).unwrap();
+                assert_eq!(Ok(()), proof.verify(&entry(index), &root));
+                assert_eq!(
+                    Err(MmrError::RootMismatch),
+                    proof.verify(&entry(index + 1), &root)
+                );
+            }
+        }
+
+        assert!(matches!(range.prove(40), Err(MmrError::OutOfRange)));
+
+        // a proof is for one leaf count only
+        let mut proof = range.prove(17).unwrap();
+        proof.leaf_count = 39;
+        assert!(proof.verify(&entry(17), &range.root()).is_err());
+
+        let mut proof = range.prove(17).unwrap();
+        proof.path.pop();
+        assert_eq!(
+            Err(MmrError::WrongProofLength),
+            proof.verify(&entry(17), &range.root())
+        );
+    }
+
+    #[test]
+    fn test_persisted_peaks() {
+        let mut range = MerkleMountainRange::<Sha1>::new();
+        let mut peaks = Peaks::<Sha1>::new();
+        for index in 0..1000 {
+            range.append(&entry(index));
+            peaks.append(&entry(index));
+        }
+
+        // 1000 leaves: six mountains, one for each set bit
+        let saved = peaks.to_bytes();
+        assert_eq!(8 + 6 * 20, saved.len());
+
+        let mut restored = Peaks::<Sha1>::from_bytes(&saved).unwrap();
+        assert_eq!(1000, restored.leaf_count());
+        assert_eq!(range.root(), restored.root());
+
+        // carrying on from the saved peaks ends up where the full range does
+        for index in 1000..1100 {
+            range.append(&entry(index));
+            restored.append(&entry(index));
+        }
+        assert_eq!(range.root(), restored.root());
+
+        let proof = range.prove(1050).unwrap();
+        assert_eq!(Ok(()), proof.verify(&entry(1050), &restored.root()));
+
+        assert!(Peaks::<Sha1>::from_bytes(&saved[..saved.len() - 1]).is_err());
+        assert!(Peaks::<Sha1>::from_bytes(&saved[..4]).is_err());
+    }
+
+}
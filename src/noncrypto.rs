@@ -0,0 +1,21 @@
+//! Fast hashes that are **not** cryptographic: FNV-1a, MurmurHash3, xxHash64
+//! and XXH3.
+//!
+//! These are for spreading keys across shards, buckets and bloom filters,
+//! where speed matters and the inputs aren't hostile. They make no attempt at
+//! all to stop someone from finding collisions on purpose -- for that matter,
+//! most of them can be inverted or collided by hand. Never use them to check
+//! that data hasn't been tampered with; that's what `sha256` is for.
+//!
+//! They all stream through `Digest` like the real hashes do. Each one has a
+//! `value` method giving the hash as an integer, which is what you want for
+//! picking a shard; `finalize` gives the same value as bytes, in the order the
+//! reference implementation writes them out.
+
+mod fnv;
+mod murmur3;
+mod xxhash;
+
+pub use fnv::{Fnv1a32, Fnv1a64};
+pub use murmur3::{Murmur3_128, Murmur3_32};
+pub use xxhash::{Xxh3, Xxh64};
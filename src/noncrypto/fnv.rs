@@ -0,0 +1,111 @@
+use crate::digest::Digest;
+
+const FNV_32_OFFSET_BASIS: u32 = 0x811c9dc5;
+const FNV_32_PRIME: u32 = 0x01000193;
+
+const FNV_64_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_64_PRIME: u64 = 0x00000100000001b3;
+
+/// 32-bit FNV-1a: for each byte, xor it in and then multiply by the FNV
+/// prime. (Plain FNV-1 multiplies first, and spreads the last byte less well.)
+#[derive(Clone)]
+pub struct Fnv1a32 {
+    hash: u32,
+}
+
+impl Default for Fnv1a32 {
+    fn default() -> Self {
+        Fnv1a32 {
+            hash: FNV_32_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Fnv1a32 {
+    pub fn value(&self) -> u32 {
+        self.hash
+    }
+}
+
+impl Digest for Fnv1a32 {
+    const BLOCK_LENGTH: usize = 1;
+    const OUTPUT_LENGTH: usize = 4;
+
+    type Output = [u8; 4];
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash = (self.hash ^ u32::from(*byte)).wrapping_mul(FNV_32_PRIME);
+        }
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.value().to_be_bytes()
+    }
+}
+
+/// 64-bit FNV-1a, the same as `Fnv1a32` with a wider prime.
+#[derive(Clone)]
+pub struct Fnv1a64 {
+    hash: u64,
+}
+
+impl Default for Fnv1a64 {
+    fn default() -> Self {
+        Fnv1a64 {
+            hash: FNV_64_OFFSET_BASIS,
+        }
+    }
+}
+
+impl Fnv1a64 {
+    pub fn value(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Digest for Fnv1a64 {
+    const BLOCK_LENGTH: usize = 1;
+    const OUTPUT_LENGTH: usize = 8;
+
+    type Output = [u8; 8];
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash = (self.hash ^ u64::from(*byte)).wrapping_mul(FNV_64_PRIME);
+        }
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.value().to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fnv1a_32(message: &str) -> u32 {
+        let mut hasher = Fnv1a32::default();
+        hasher.update(message.as_bytes());
+        hasher.value()
+    }
+
+    fn fnv1a_64(message: &str) -> u64 {
+        let mut hasher = Fnv1a64::default();
+        hasher.update(message.as_bytes());
+        hasher.value()
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(0x811c9dc5, fnv1a_32(""));
+        assert_eq!(0xe40c292c, fnv1a_32("a"));
+        assert_eq!(0xbf9cf968, fnv1a_32("foobar"));
+
+        assert_eq!(0xcbf29ce484222325, fnv1a_64(""));
+        assert_eq!(0xaf63dc4c8601ec8c, fnv1a_64("a"));
+        assert_eq!(0x85944171f73967e8, fnv1a_64("foobar"));
+    }
+
+}
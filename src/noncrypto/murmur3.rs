@@ -0,0 +1,294 @@
+use crate::digest::Digest;
+
+const C1_32: u32 = 0xcc9e2d51;
+const C2_32: u32 = 0x1b873593;
+
+const C1_128: u64 = 0x87c37b91114253d5;
+const C2_128: u64 = 0x4cf5ad432745937f;
+
+// the finalizers: a few rounds of xorshift-multiply so every bit of the input
+// ends up affecting every bit of the output
+fn fmix32(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^ (h >> 16)
+}
+
+fn fmix64(mut k: u64) -> u64 {
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xff51afd7ed558ccd);
+    k ^= k >> 33;
+    k = k.wrapping_mul(0xc4ceb9fe1a85ec53);
+    k ^ (k >> 33)
+}
+
+fn scramble_32(k: u32) -> u32 {
+    k.wrapping_mul(C1_32).rotate_left(15).wrapping_mul(C2_32)
+}
+
+/// MurmurHash3_x86_32. Takes the message four bytes at a time (read little
+/// endian), mixes each word in, and runs the leftover bytes and the length
+/// through a final scramble.
+#[derive(Clone, Default)]
+pub struct Murmur3_32 {
+    hash: u32,
+    block: [u8; 4],
+    filled: usize,
+    length: u64,
+}
+
+impl Murmur3_32 {
+    pub fn with_seed(seed: u32) -> Self {
+        Murmur3_32 {
+            hash: seed,
+            ..Murmur3_32::default()
+        }
+    }
+
+    fn mix_block(&mut self, block: &[u8]) {
+        let k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+
+        self.hash ^= scramble_32(k);
+        self.hash = self
+            .hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe6546b64);
+    }
+
+    pub fn value(&self) -> u32 {
+        let mut hash = self.hash;
+
+        if self.filled > 0 {
+            let k = self.block[..self.filled]
+                .iter()
+                .rev()
+                .fold(0u32, |k, byte| (k << 8) | u32::from(*byte));
+            hash ^= scramble_32(k);
+        }
+
+        // the reference implementation takes the length as a 32-bit int
+        fmix32(hash ^ self.length as u32)
+    }
+}
+
+impl Digest for Murmur3_32 {
+    const BLOCK_LENGTH: usize = 4;
+    const OUTPUT_LENGTH: usize = 4;
+
+    type Output = [u8; 4];
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+
+        // top up a block left over from last time before going on to the
+        // whole blocks, which can be mixed in straight from `bytes`
+        if self.filled > 0 {
+            let taken = bytes.len().min(Self::BLOCK_LENGTH - self.filled);
+            self.block[self.filled..self.filled + taken].copy_from_slice(&bytes[..taken]);
+            self.filled += taken;
+            bytes = &bytes[taken..];
+
+            if self.filled < Self::BLOCK_LENGTH {
+                return;
+            }
+            let block = self.block;
+            self.mix_block(&block);
+            self.filled = 0;
+        }
+
+        let mut blocks = bytes.chunks_exact(Self::BLOCK_LENGTH);
+        for block in &mut blocks {
+            self.mix_block(block);
+        }
+
+        let rest = blocks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.value().to_le_bytes()
+    }
+}
+
+/// MurmurHash3_x64_128: two 64-bit halves, `h1` and `h2`, each taking one
+/// half of every 16-byte block and feeding into the other.
+///
+/// `value` puts `h2` in the high 64 bits and `h1` in the low, so that its
+/// little endian bytes are the 16 bytes the reference implementation outputs.
+#[derive(Clone, Default)]
+pub struct Murmur3_128 {
+    h1: u64,
+    h2: u64,
+    block: [u8; 16],
+    filled: usize,
+    length: u64,
+}
+
+fn scramble_k1(k1: u64) -> u64 {
+    k1.wrapping_mul(C1_128).rotate_left(31).wrapping_mul(C2_128)
+}
+
+fn scramble_k2(k2: u64) -> u64 {
+    k2.wrapping_mul(C2_128).rotate_left(33).wrapping_mul(C1_128)
+}
+
+fn read_partial_u64(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u64, |k, byte| (k << 8) | u64::from(*byte))
+}
+
+impl Murmur3_128 {
+    pub fn with_seed(seed: u32) -> Self {
+        Murmur3_128 {
+            h1: u64::from(seed),
+            h2: u64::from(seed),
+            ..Murmur3_128::default()
+        }
+    }
+
+    fn mix_block(&mut self, block: &[u8]) {
+        let k1 = read_partial_u64(&block[..8]);
+        let k2 = read_partial_u64(&block[8..]);
+
+        self.h1 ^= scramble_k1(k1);
+        self.h1 = self
+            .h1
+            .rotate_left(27)
+            .wrapping_add(self.h2)
+            .wrapping_mul(5)
+            .wrapping_add(0x52dce729);
+
+        self.h2 ^= scramble_k2(k2);
+        self.h2 = self
+            .h2
+            .rotate_left(31)
+            .wrapping_add(self.h1)
+            .wrapping_mul(5)
+            .wrapping_add(0x38495ab5);
+    }
+
+    pub fn value(&self) -> u128 {
+        let mut h1 = self.h1;
+        let mut h2 = self.h2;
+
+        if self.filled > 8 {
+            h2 ^= scramble_k2(read_partial_u64(&self.block[8..self.filled]));
+        }
+        if self.filled > 0 {
+            let low_half = self.filled.min(8);
+            h1 ^= scramble_k1(read_partial_u64(&self.block[..low_half]));
+        }
+
+        h1 ^= self.length;
+        h2 ^= self.length;
+
+        h1 = h1.wrapping_add(h2);
+        h2 = h2.wrapping_add(h1);
+
+        h1 = fmix64(h1);
+        h2 = fmix64(h2);
+
+        h1 = h1.wrapping_add(h2);
+        h2 = h2.wrapping_add(h1);
+
+        (u128::from(h2) << 64) | u128::from(h1)
+    }
+}
+
+impl Digest for Murmur3_128 {
+    const BLOCK_LENGTH: usize = 16;
+    const OUTPUT_LENGTH: usize = 16;
+
+    type Output = [u8; 16];
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+
+        // top up a block left over from last time before going on to the
+        // whole blocks, which can be mixed in straight from `bytes`
+        if self.filled > 0 {
+            let taken = bytes.len().min(Self::BLOCK_LENGTH - self.filled);
+            self.block[self.filled..self.filled + taken].copy_from_slice(&bytes[..taken]);
+            self.filled += taken;
+            bytes = &bytes[taken..];
+
+            if self.filled < Self::BLOCK_LENGTH {
+                return;
+            }
+            let block = self.block;
+            self.mix_block(&block);
+            self.filled = 0;
+        }
+
+        let mut blocks = bytes.chunks_exact(Self::BLOCK_LENGTH);
+        for block in &mut blocks {
+            self.mix_block(block);
+        }
+
+        let rest = blocks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.value().to_le_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn murmur3_32(message: &str, seed: u32) -> u32 {
+        let mut hasher = Murmur3_32::with_seed(seed);
+        hasher.update(message.as_bytes());
+        hasher.value()
+    }
+
+    fn murmur3_128(message: &str, seed: u32) -> u128 {
+        let mut hasher = Murmur3_128::with_seed(seed);
+        hasher.update(message.as_bytes());
+        hasher.value()
+    }
+
+    #[test]
+    fn test_murmur3_32() {
+        assert_eq!(0, murmur3_32("", 0));
+        assert_eq!(0x514e28b7, murmur3_32("", 1));
+        assert_eq!(
+            0x2e4ff723,
+            murmur3_32("The quick brown fox jumps over the lazy dog", 0)
+        );
+
+        let mut hasher = Murmur3_32::default();
+        for piece in ["T", "he", " quick brown fox", " jumps over the lazy dog"].iter() {
+            hasher.update(piece.as_bytes());
+        }
+        assert_eq!(0x2e4ff723, hasher.value());
+    }
+
+    #[test]
+    fn test_murmur3_128() {
+        let fox = "The quick brown fox jumps over the lazy dog";
+
+        assert_eq!(0, murmur3_128("", 0));
+        assert_eq!(0x7a433ca9c49a9347e34bbc7bbc071b6c, murmur3_128(fox, 0));
+        assert_eq!(0x625a21a4c967fa20e533566dbbd1e13e, murmur3_128(fox, 1));
+
+        // split so that the pieces don't line up with the 16-byte blocks
+        let mut hasher = Murmur3_128::default();
+        hasher.update(&fox.as_bytes()[..5]);
+        hasher.update(&fox.as_bytes()[5..]);
+        assert_eq!(
+            0x7a433ca9c49a9347e34bbc7bbc071b6cu128.to_le_bytes(),
+            hasher.finalize()
+        );
+    }
+
+}
@@ -0,0 +1,539 @@
+use crate::digest::Digest;
+
+const PRIME32_1: u64 = 0x9E3779B1;
+const PRIME32_2: u64 = 0x85EBCA77;
+const PRIME32_3: u64 = 0xC2B2AE3D;
+
+const PRIME64_1: u64 = 0x9E3779B185EBCA87;
+const PRIME64_2: u64 = 0xC2B2AE3D27D4EB4F;
+const PRIME64_3: u64 = 0x165667B19E3779F9;
+const PRIME64_4: u64 = 0x85EBCA77C2B2AE63;
+const PRIME64_5: u64 = 0x27D4EB2F165667C5;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(word)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut word = [0u8; 8];
+    word.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(word)
+}
+
+fn xxh64_round(accumulator: u64, input: u64) -> u64 {
+    accumulator
+        .wrapping_add(input.wrapping_mul(PRIME64_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME64_1)
+}
+
+fn xxh64_merge_round(accumulator: u64, value: u64) -> u64 {
+    (accumulator ^ xxh64_round(0, value))
+        .wrapping_mul(PRIME64_1)
+        .wrapping_add(PRIME64_4)
+}
+
+fn xxh64_avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME64_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME64_3);
+    hash ^ (hash >> 32)
+}
+
+const XXH64_STRIPE_LENGTH: usize = 32;
+
+/// xxHash64. Four 64-bit lanes each take one 8-byte word out of every 32-byte
+/// stripe; at the end the lanes are folded together, the leftover bytes are
+/// mixed in 8, 4 and then 1 at a time, and the result gets avalanched.
+#[derive(Clone)]
+pub struct Xxh64 {
+    seed: u64,
+    lanes: [u64; 4],
+    stripe: [u8; XXH64_STRIPE_LENGTH],
+    filled: usize,
+    length: u64,
+}
+
+impl Default for Xxh64 {
+    fn default() -> Self {
+        Xxh64::with_seed(0)
+    }
+}
+
+impl Xxh64 {
+    pub fn with_seed(seed: u64) -> Self {
+        Xxh64 {
+            seed,
+            lanes: [
+                seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2),
+                seed.wrapping_add(PRIME64_2),
+                seed,
+                seed.wrapping_sub(PRIME64_1),
+            ],
+            stripe: [0; XXH64_STRIPE_LENGTH],
+            filled: 0,
+            length: 0,
+        }
+    }
+
+    fn consume_stripe(&mut self, stripe: &[u8]) {
+        for (index, lane) in self.lanes.iter_mut().enumerate() {
+            *lane = xxh64_round(*lane, read_u64(stripe, 8 * index));
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        let mut hash = if self.length >= XXH64_STRIPE_LENGTH as u64 {
+            let [v1, v2, v3, v4] = self.lanes;
+            let hash = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+
+            self.lanes
+                .iter()
+                .fold(hash, |hash, lane| xxh64_merge_round(hash, *lane))
+        } else {
+            self.seed.wrapping_add(PRIME64_5)
+        };
+
+        hash = hash.wrapping_add(self.length);
+
+        let mut remaining = &self.stripe[..self.filled];
+        while remaining.len() >= 8 {
+            hash ^= xxh64_round(0, read_u64(remaining, 0));
+            hash = hash
+                .rotate_left(27)
+                .wrapping_mul(PRIME64_1)
+                .wrapping_add(PRIME64_4);
+            remaining = &remaining[8..];
+        }
+        if remaining.len() >= 4 {
+            hash ^= u64::from(read_u32(remaining, 0)).wrapping_mul(PRIME64_1);
+            hash = hash
+                .rotate_left(23)
+                .wrapping_mul(PRIME64_2)
+                .wrapping_add(PRIME64_3);
+            remaining = &remaining[4..];
+        }
+        for byte in remaining {
+            hash ^= u64::from(*byte).wrapping_mul(PRIME64_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+        }
+
+        xxh64_avalanche(hash)
+    }
+}
+
+impl Digest for Xxh64 {
+    const BLOCK_LENGTH: usize = XXH64_STRIPE_LENGTH;
+    const OUTPUT_LENGTH: usize = 8;
+
+    type Output = [u8; 8];
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+
+        if self.filled > 0 {
+            let taken = bytes.len().min(XXH64_STRIPE_LENGTH - self.filled);
+            self.stripe[self.filled..self.filled + taken].copy_from_slice(&bytes[..taken]);
+            self.filled += taken;
+            bytes = &bytes[taken..];
+
+            if self.filled < XXH64_STRIPE_LENGTH {
+                return;
+            }
+            let stripe = self.stripe;
+            self.consume_stripe(&stripe);
+            self.filled = 0;
+        }
+
+        let mut stripes = bytes.chunks_exact(XXH64_STRIPE_LENGTH);
+        for stripe in &mut stripes {
+            self.consume_stripe(stripe);
+        }
+
+        let rest = stripes.remainder();
+        self.stripe[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.value().to_be_bytes()
+    }
+}
+
+const XXH3_SECRET: [u8; 192] = [
+    0xb8, 0xfe, 0x6c, 0x39, 0x23, 0xa4, 0x4b, 0xbe, 0x7c, 0x01, 0x81, 0x2c, 0xf7, 0x21, 0xad, 0x1c,
+    0xde, 0xd4, 0x6d, 0xe9, 0x83, 0x90, 0x97, 0xdb, 0x72, 0x40, 0xa4, 0xa4, 0xb7, 0xb3, 0x67, 0x1f,
+    0xcb, 0x79, 0xe6, 0x4e, 0xcc, 0xc0, 0xe5, 0x78, 0x82, 0x5a, 0xd0, 0x7d, 0xcc, 0xff, 0x72, 0x21,
+    0xb8, 0x08, 0x46, 0x74, 0xf7, 0x43, 0x24, 0x8e, 0xe0, 0x35, 0x90, 0xe6, 0x81, 0x3a, 0x26, 0x4c,
+    0x3c, 0x28, 0x52, 0xbb, 0x91, 0xc3, 0x00, 0xcb, 0x88, 0xd0, 0x65, 0x8b, 0x1b, 0x53, 0x2e, 0xa3,
+    0x71, 0x64, 0x48, 0x97, 0xa2, 0x0d, 0xf9, 0x4e, 0x38, 0x19, 0xef, 0x46, 0xa9, 0xde, 0xac, 0xd8,
+    0xa8, 0xfa, 0x76, 0x3f, 0xe3, 0x9c, 0x34, 0x3f, 0xf9, 0xdc, 0xbb, 0xc7, 0xc7, 0x0b, 0x4f, 0x1d,
+    0x8a, 0x51, 0xe0, 0x4b, 0xcd, 0xb4, 0x59, 0x31, 0xc8, 0x9f, 0x7e, 0xc9, 0xd9, 0x78, 0x73, 0x64,
+    0xea, 0xc5, 0xac, 0x83, 0x34, 0xd3, 0xeb, 0xc3, 0xc5, 0x81, 0xa0, 0xff, 0xfa, 0x13, 0x63, 0xeb,
+    0x17, 0x0d, 0xdd, 0x51, 0xb7, 0xf0, 0xda, 0x49, 0xd3, 0x16, 0x55, 0x26, 0x29, 0xd4, 0x68, 0x9e,
+    0x2b, 0x16, 0xbe, 0x58, 0x7d, 0x47, 0xa1, 0xfc, 0x8f, 0xf8, 0xb8, 0xd1, 0x7a, 0xd0, 0x31, 0xce,
+    0x45, 0xcb, 0x3a, 0x8f, 0x95, 0x16, 0x04, 0x28, 0xaf, 0xd7, 0xfb, 0xca, 0xbb, 0x4b, 0x40, 0x7e,
+];
+
+const XXH3_STRIPE_LENGTH: usize = 64;
+const XXH3_SECRET_CONSUME_RATE: usize = 8;
+// each stripe in a block uses the secret 8 bytes further along, until it runs out
+const XXH3_STRIPES_PER_BLOCK: usize =
+    (XXH3_SECRET.len() - XXH3_STRIPE_LENGTH) / XXH3_SECRET_CONSUME_RATE;
+const XXH3_MID_SIZE_MAX: usize = 240;
+
+const XXH3_INITIAL_ACCUMULATORS: [u64; 8] = [
+    PRIME32_3, PRIME64_1, PRIME64_2, PRIME64_3, PRIME64_4, PRIME32_2, PRIME64_5, PRIME32_1,
+];
+
+fn xxh3_avalanche(mut hash: u64) -> u64 {
+    hash ^= hash >> 37;
+    hash = hash.wrapping_mul(0x165667919E3779F9);
+    hash ^ (hash >> 32)
+}
+
+fn rrmxmx(mut hash: u64, length: u64) -> u64 {
+    hash ^= hash.rotate_left(49) ^ hash.rotate_left(24);
+    hash = hash.wrapping_mul(0x9FB21C651E98DF25);
+    hash ^= (hash >> 35).wrapping_add(length);
+    hash = hash.wrapping_mul(0x9FB21C651E98DF25);
+    hash ^ (hash >> 28)
+}
+
+// multiply out to 128 bits, then fold the halves together
+fn mul128_fold64(left: u64, right: u64) -> u64 {
+    let product = u128::from(left) * u128::from(right);
+    (product as u64) ^ ((product >> 64) as u64)
+}
+
+fn mix16(input: &[u8], secret: &[u8], seed: u64) -> u64 {
+    let low = read_u64(input, 0) ^ read_u64(secret, 0).wrapping_add(seed);
+    let high = read_u64(input, 8) ^ read_u64(secret, 8).wrapping_sub(seed);
+
+    mul128_fold64(low, high)
+}
+
+fn xxh3_0_to_16(input: &[u8], secret: &[u8], seed: u64) -> u64 {
+    let length = input.len();
+
+    if length > 8 {
+        let flip_low = (read_u64(secret, 24) ^ read_u64(secret, 32)).wrapping_add(seed);
+        let flip_high = (read_u64(secret, 40) ^ read_u64(secret, 48)).wrapping_sub(seed);
+        let low = read_u64(input, 0) ^ flip_low;
+        let high = read_u64(input, length - 8) ^ flip_high;
+
+        let accumulator = (length as u64)
+            .wrapping_add(low.swap_bytes())
+            .wrapping_add(high)
+            .wrapping_add(mul128_fold64(low, high));
+
+        xxh3_avalanche(accumulator)
+    } else if length >= 4 {
+        let seed = seed ^ (u64::from((seed as u32).swap_bytes()) << 32);
+        let first = u64::from(read_u32(input, 0));
+        let last = u64::from(read_u32(input, length - 4));
+
+        let flip = (read_u64(secret, 8) ^ read_u64(secret, 16)).wrapping_sub(seed);
+        let combined = last.wrapping_add(first << 32);
+
+        rrmxmx(combined ^ flip, length as u64)
+    } else if length > 0 {
+        let combined = (u32::from(input[0]) << 16)
+            | (u32::from(input[length >> 1]) << 24)
+            | u32::from(input[length - 1])
+            | ((length as u32) << 8);
+
+        let flip = u64::from(read_u32(secret, 0) ^ read_u32(secret, 4)).wrapping_add(seed);
+
+        xxh64_avalanche(u64::from(combined) ^ flip)
+    } else {
+        xxh64_avalanche(seed ^ read_u64(secret, 56) ^ read_u64(secret, 64))
+    }
+}
+
+fn xxh3_17_to_128(input: &[u8], secret: &[u8], seed: u64) -> u64 {
+    let length = input.len();
+    let mut accumulator = (length as u64).wrapping_mul(PRIME64_1);
+
+    // pairs of 16-byte pieces, working in from both ends of the input
+    let pairs = (length - 1) / 32;
+    for pair in (0..=pairs).rev() {
+        accumulator = accumulator
+            .wrapping_add(mix16(&input[16 * pair..], &secret[32 * pair..], seed))
+            .wrapping_add(mix16(
+                &input[length - 16 * (pair + 1)..],
+                &secret[32 * pair + 16..],
+                seed,
+            ));
+    }
+
+    xxh3_avalanche(accumulator)
+}
+
+fn xxh3_129_to_240(input: &[u8], secret: &[u8], seed: u64) -> u64 {
+    const START_OFFSET: usize = 3;
+    const LAST_OFFSET: usize = 17;
+    const SECRET_SIZE_MIN: usize = 136;
+
+    let length = input.len();
+    let mut accumulator = (length as u64).wrapping_mul(PRIME64_1);
+
+    for round in 0..8 {
+        accumulator =
+            accumulator.wrapping_add(mix16(&input[16 * round..], &secret[16 * round..], seed));
+    }
+    accumulator = xxh3_avalanche(accumulator);
+
+    for round in 8..length / 16 {
+        accumulator = accumulator.wrapping_add(mix16(
+            &input[16 * round..],
+            &secret[16 * (round - 8) + START_OFFSET..],
+            seed,
+        ));
+    }
+
+    accumulator = accumulator.wrapping_add(mix16(
+        &input[length - 16..],
+        &secret[SECRET_SIZE_MIN - LAST_OFFSET..],
+        seed,
+    ));
+
+    xxh3_avalanche(accumulator)
+}
+
+fn accumulate_stripe(accumulators: &mut [u64; 8], stripe: &[u8], secret: &[u8]) {
+    for index in 0..8 {
+        let data = read_u64(stripe, 8 * index);
+        let keyed = data ^ read_u64(secret, 8 * index);
+
+        accumulators[index ^ 1] = accumulators[index ^ 1].wrapping_add(data);
+        accumulators[index] =
+            accumulators[index].wrapping_add((keyed & 0xFFFF_FFFF).wrapping_mul(keyed >> 32));
+    }
+}
+
+fn scramble_accumulators(accumulators: &mut [u64; 8], secret: &[u8]) {
+    for (index, accumulator) in accumulators.iter_mut().enumerate() {
+        let shifted = *accumulator ^ (*accumulator >> 47);
+        *accumulator = (shifted ^ read_u64(secret, 8 * index)).wrapping_mul(PRIME32_1);
+    }
+}
+
+/// XXH3, the 64-bit version. Short inputs (up to 240 bytes) each have their
+/// own hand-tuned path; longer ones go through eight accumulators that each
+/// stripe of 64 bytes gets mixed into, keyed by sliding along a 192-byte
+/// secret, with a scramble after every block of 16 stripes.
+///
+/// A seed changes the secret for long inputs and gets mixed in directly for
+/// short ones.
+#[derive(Clone)]
+pub struct Xxh3 {
+    seed: u64,
+    secret: [u8; 192],
+    accumulators: [u64; 8],
+    stripes_in_block: usize,
+    // bytes not consumed yet, plus (once we've started consuming stripes) the
+    // last stripe's worth of bytes before them -- the very last stripe of the
+    // input gets mixed in again at the end, so it has to be kept around
+    buffer: Vec<u8>,
+    consumed: usize,
+    length: u64,
+}
+
+impl Default for Xxh3 {
+    fn default() -> Self {
+        Xxh3::with_seed(0)
+    }
+}
+
+impl Xxh3 {
+    pub fn with_seed(seed: u64) -> Self {
+        let mut secret = XXH3_SECRET;
+        for (index, word) in secret.chunks_exact_mut(8).enumerate() {
+            let value = u64::from_le_bytes([
+                word[0], word[1], word[2], word[3], word[4], word[5], word[6], word[7],
+            ]);
+            let value = if index % 2 == 0 {
+                value.wrapping_add(seed)
+            } else {
+                value.wrapping_sub(seed)
+            };
+            word.copy_from_slice(&value.to_le_bytes());
+        }
+
+        Xxh3 {
+            seed,
+            secret,
+            accumulators: XXH3_INITIAL_ACCUMULATORS,
+            stripes_in_block: 0,
+            buffer: Vec::new(),
+            consumed: 0,
+            length: 0,
+        }
+    }
+
+    // Mix in every stripe that is definitely not the last one -- that is,
+    // every stripe with at least one byte after it.
+    fn consume_stripes(&mut self) {
+        while self.buffer.len() - self.consumed > XXH3_STRIPE_LENGTH {
+            let stripe = &self.buffer[self.consumed..self.consumed + XXH3_STRIPE_LENGTH];
+            let secret = &self.secret[self.stripes_in_block * XXH3_SECRET_CONSUME_RATE..];
+            accumulate_stripe(&mut self.accumulators, stripe, secret);
+
+            self.consumed += XXH3_STRIPE_LENGTH;
+            self.stripes_in_block += 1;
+
+            if self.stripes_in_block == XXH3_STRIPES_PER_BLOCK {
+                let secret = &self.secret[self.secret.len() - XXH3_STRIPE_LENGTH..];
+                scramble_accumulators(&mut self.accumulators, secret);
+                self.stripes_in_block = 0;
+            }
+        }
+
+        // only the last stripe's worth of consumed bytes still matters
+        if self.consumed > XXH3_STRIPE_LENGTH {
+            let drop = self.consumed - XXH3_STRIPE_LENGTH;
+            self.buffer.drain(..drop);
+            self.consumed -= drop;
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        if self.length <= XXH3_MID_SIZE_MAX as u64 {
+            let input = &self.buffer[..];
+
+            return if input.len() <= 16 {
+                xxh3_0_to_16(input, &XXH3_SECRET, self.seed)
+            } else if input.len() <= 128 {
+                xxh3_17_to_128(input, &XXH3_SECRET, self.seed)
+            } else {
+                xxh3_129_to_240(input, &XXH3_SECRET, self.seed)
+            };
+        }
+
+        const LAST_STRIPE_SECRET_OFFSET: usize = 7;
+        const MERGE_SECRET_OFFSET: usize = 11;
+
+        let mut accumulators = self.accumulators;
+        let last_stripe = &self.buffer[self.buffer.len() - XXH3_STRIPE_LENGTH..];
+        let secret =
+            &self.secret[self.secret.len() - XXH3_STRIPE_LENGTH - LAST_STRIPE_SECRET_OFFSET..];
+        accumulate_stripe(&mut accumulators, last_stripe, secret);
+
+        let merged = accumulators.chunks_exact(2).enumerate().fold(
+            self.length.wrapping_mul(PRIME64_1),
+            |merged, (index, pair)| {
+                let secret = &self.secret[MERGE_SECRET_OFFSET + 16 * index..];
+                merged.wrapping_add(mul128_fold64(
+                    pair[0] ^ read_u64(secret, 0),
+                    pair[1] ^ read_u64(secret, 8),
+                ))
+            },
+        );
+
+        xxh3_avalanche(merged)
+    }
+}
+
+impl Digest for Xxh3 {
+    const BLOCK_LENGTH: usize = XXH3_STRIPE_LENGTH;
+    const OUTPUT_LENGTH: usize = 8;
+
+    type Output = [u8; 8];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.length += bytes.len() as u64;
+        self.buffer.extend_from_slice(bytes);
+
+        // short inputs are hashed all at once, so don't start on the stripes
+        // until we know this isn't going to be one of those
+        if self.length > XXH3_MID_SIZE_MAX as u64 {
+            self.consume_stripes();
+        }
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.value().to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xxh64(message: &[u8], seed: u64) -> u64 {
+        let mut hasher = Xxh64::with_seed(seed);
+        hasher.update(message);
+        hasher.value()
+    }
+
+    fn xxh3(message: &[u8], seed: u64) -> u64 {
+        let mut hasher = Xxh3::with_seed(seed);
+        hasher.update(message);
+        hasher.value()
+    }
+
+    const FOX: &[u8] = b"The quick brown fox jumps over the lazy dog";
+
+    fn alphabet(length: usize) -> Vec<u8> {
+        b"abcdefghijklmnopqrstuvwxyz"
+            .iter()
+            .cycle()
+            .take(length)
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_xxh64() {
+        assert_eq!(0xef46db3751d8e999, xxh64(b"", 0));
+        assert_eq!(0x0b242d361fda71bc, xxh64(FOX, 0));
+        assert_eq!(0xcd00216d72d27465, xxh64(&alphabet(2000), 0));
+
+        // pieces that straddle the 32-byte stripes, and some that fill a
+        // partial stripe without finishing it
+        let message = alphabet(2000);
+        let mut hasher = Xxh64::default();
+        for piece in message.chunks(97).flat_map(|piece| piece.chunks(13)) {
+            hasher.update(piece);
+        }
+        assert_eq!(0xcd00216d72d27465, hasher.value());
+    }
+
+    #[test]
+    fn test_xxh3() {
+        // one for each of the different paths through XXH3
+        assert_eq!(0x2d06800538d394c2, xxh3(b"", 0));
+        assert_eq!(0xa873719c24d5735c, xxh3(&alphabet(2), 0));
+        assert_eq!(0x55c65158ee9e652d, xxh3(&alphabet(5), 0));
+        assert_eq!(0x52beba2086c3f6d7, xxh3(&alphabet(12), 0));
+        assert_eq!(0xce7d19a5418fb365, xxh3(FOX, 0));
+        assert_eq!(0x7f2b83f8e57a6e24, xxh3(&alphabet(100), 0));
+        assert_eq!(0xe12dae8ffe57bbc9, xxh3(&alphabet(200), 0));
+        assert_eq!(0x9e0defe18dce29f1, xxh3(&alphabet(2000), 0));
+        assert_eq!(0xfb4d68865d0fdd74, xxh3(&alphabet(2000), 42));
+    }
+
+    #[test]
+    fn test_xxh3_streaming() {
+        // the long path holds back the last stripe, and scrambles after every
+        // 16 stripes -- feeding it in odd-sized pieces exercises both
+        let message = alphabet(2000);
+
+        let mut hasher = Xxh3::with_seed(42);
+        for piece in message.chunks(97) {
+            hasher.update(piece);
+        }
+
+        assert_eq!(0xfb4d68865d0fdd74, hasher.value());
+        assert_eq!(0xfb4d68865d0fdd74u64.to_be_bytes(), hasher.finalize());
+    }
+
+}
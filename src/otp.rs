@@ -0,0 +1,615 @@
+//! One-time passwords: HOTP (RFC 4226) and TOTP (RFC 6238), the six-digit
+//! codes from authenticator apps.
+//!
+//! HOTP takes the HMAC of a counter under a shared secret and boils it down
+//! to a few decimal digits (*dynamic truncation*). TOTP is HOTP where the
+//! counter is the number of time steps (usually 30 seconds) since the Unix
+//! epoch, so the code changes on its own.
+//!
+//! Secrets are handed out to apps as `otpauth://` URIs, usually in a QR code:
+//!
+//! `otpauth://totp/Example:alice@example.com?secret=JBSWY3DPEHPK3PXP&issuer=Example`
+
+use crate::hmac::hmac;
+use crate::sha2::Sha256;
+use crate::utils::{base32_decode, base32_encode, constant_time_eq};
+use crate::Sha1;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// The HMAC hash. Most apps only support SHA-1, which is also the default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+}
+
+impl Algorithm {
+    fn mac(self, secret: &[u8], message: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Sha1 => hmac::<Sha1>(secret, message).to_vec(),
+            Algorithm::Sha256 => hmac::<Sha256>(secret, message).to_vec(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+        }
+    }
+}
+
+const MINIMUM_DIGITS: u32 = 6;
+const MAXIMUM_DIGITS: u32 = 10;
+
+// one-time passwords have from 6 to 10 digits
+fn check_digits(digits: u32) -> Result<u32, OtpError> {
+    if (MINIMUM_DIGITS..=MAXIMUM_DIGITS).contains(&digits) {
+        Ok(digits)
+    } else {
+        Err(OtpError::InvalidParameter(String::from("digits")))
+    }
+}
+
+/// Dynamic truncation: the low four bits of the last byte of the HMAC pick an
+/// offset, and the 31 bits starting there (the top bit is dropped so signed
+/// and unsigned readings agree) are the result.
+pub fn dynamic_truncation(mac: &[u8]) -> u32 {
+    let offset = (mac[mac.len() - 1] & 0x0F) as usize;
+    let bytes = [
+        mac[offset],
+        mac[offset + 1],
+        mac[offset + 2],
+        mac[offset + 3],
+    ];
+    u32::from_be_bytes(bytes) & 0x7FFF_FFFF
+}
+
+fn generate(algorithm: Algorithm, secret: &[u8], counter: u64, digits: u32) -> String {
+    let mac = algorithm.mac(secret, &counter.to_be_bytes());
+    let code = u64::from(dynamic_truncation(&mac)) % 10u64.pow(digits);
+    format!("{:0width$}", code, width = digits as usize)
+}
+
+/// HOTP: codes from a counter that both sides step forward after every
+/// successful login.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Hotp {
+    secret: Vec<u8>,
+    algorithm: Algorithm,
+    digits: u32,
+}
+
+// the shared secret is as good as the second factor itself, so it stays out
+// of logs
+impl fmt::Debug for Hotp {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Hotp")
+            .field("secret", &"<redacted>")
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .finish()
+    }
+}
+
+impl Hotp {
+    /// Fails with `InvalidParameter` unless `digits` is from 6 to 10.
+    pub fn new(secret: &[u8], algorithm: Algorithm, digits: u32) -> Result<Self, OtpError> {
+        Ok(Hotp {
+            secret: secret.to_vec(),
+            algorithm,
+            digits: check_digits(digits)?,
+        })
+    }
+
+    pub fn generate(&self, counter: u64) -> String {
+        generate(self.algorithm, &self.secret, counter, self.digits)
+    }
+
+    /// Checks `code` against the counters from `counter` to
+    /// `counter + look_ahead`, since the user may have generated codes they
+    /// never used. Hands back the counter that matched; the next expected
+    /// counter is one past it.
+    pub fn verify(&self, code: &str, counter: u64, look_ahead: u64) -> Option<u64> {
+        (counter..=counter.saturating_add(look_ahead)).find(|candidate| {
+            constant_time_eq(self.generate(*candidate).as_bytes(), code.as_bytes())
+        })
+    }
+}
+
+/// TOTP: codes from the current time.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Totp {
+    secret: Vec<u8>,
+    algorithm: Algorithm,
+    digits: u32,
+    step: u64,
+}
+
+impl fmt::Debug for Totp {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Totp")
+            .field("secret", &"<redacted>")
+            .field("algorithm", &self.algorithm)
+            .field("digits", &self.digits)
+            .field("step", &self.step)
+            .finish()
+    }
+}
+
+impl Totp {
+    /// `step` is in seconds; 30 is what nearly everyone uses. Fails with
+    /// `InvalidParameter` unless `digits` is from 6 to 10 and `step` isn't
+    /// zero.
+    pub fn new(
+        secret: &[u8],
+        algorithm: Algorithm,
+        digits: u32,
+        step: u64,
+    ) -> Result<Self, OtpError> {
+        if step == 0 {
+            return Err(OtpError::InvalidParameter(String::from("step")));
+        }
+
+        Ok(Totp {
+            secret: secret.to_vec(),
+            algorithm,
+            digits: check_digits(digits)?,
+            step,
+        })
+    }
+
+    fn counter(&self, unix_time: u64) -> u64 {
+        unix_time / self.step
+    }
+
+    pub fn generate(&self, unix_time: u64) -> String {
+        generate(
+            self.algorithm,
+            &self.secret,
+            self.counter(unix_time),
+            self.digits,
+        )
+    }
+
+    /// Checks `code` against the time step `unix_time` falls in, and up to
+    /// `window` steps either side of it -- clocks drift, and people take a few
+    /// seconds to type.
+    ///
+    /// Hands back how many steps off the match was (negative if the code was
+    /// from the past). A server can remember that drift for each user and
+    /// shift their `unix_time` by `drift * step` next time, so that a clock
+    /// that is steadily wandering off doesn't eventually fall out of the
+    /// window.
+    pub fn verify(&self, code: &str, unix_time: u64, window: u64) -> Option<i64> {
+        let counter = self.counter(unix_time);
+        let earliest = counter.saturating_sub(window);
+
+        (earliest..=counter.saturating_add(window))
+            .find(|candidate| {
+                let expected = generate(self.algorithm, &self.secret, *candidate, self.digits);
+                constant_time_eq(expected.as_bytes(), code.as_bytes())
+            })
+            .and_then(|candidate| {
+                // counters go up to 2^64 - 1, so the difference needs more
+                // room than i64 has on the way, and may not fit in it at all
+                i64::try_from(i128::from(candidate) - i128::from(counter)).ok()
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OtpError {
+    /// Not an `otpauth://` URI at all.
+    InvalidScheme,
+    /// Neither `hotp` nor `totp`.
+    UnknownType(String),
+    MissingSecret,
+    /// The secret isn't valid base32.
+    InvalidSecret,
+    UnknownAlgorithm(String),
+    /// A parameter that should be a number isn't one, or is out of range.
+    InvalidParameter(String),
+    /// HOTP URIs must say which counter to start from.
+    MissingCounter,
+    /// Something percent-encoded didn't decode to UTF-8.
+    InvalidEncoding,
+}
+
+impl fmt::Display for OtpError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OtpError::InvalidScheme => write!(formatter, "not an otpauth:// URI"),
+            OtpError::UnknownType(kind) => write!(formatter, "unknown OTP type {:?}", kind),
+            OtpError::MissingSecret => write!(formatter, "the URI has no secret"),
+            OtpError::InvalidSecret => write!(formatter, "the secret isn't valid base32"),
+            OtpError::UnknownAlgorithm(name) => write!(formatter, "unknown algorithm {:?}", name),
+            OtpError::InvalidParameter(name) => {
+                write!(formatter, "the {} parameter is invalid", name)
+            }
+            OtpError::MissingCounter => write!(formatter, "HOTP URIs need a counter"),
+            OtpError::InvalidEncoding => write!(formatter, "invalid percent-encoding"),
+        }
+    }
+}
+
+impl std::error::Error for OtpError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OtpKind {
+    Hotp { counter: u64 },
+    Totp { period: u64 },
+}
+
+/// Everything in an `otpauth://` URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OtpAuthUri {
+    pub kind: OtpKind,
+    /// The account name, such as an email address.
+    pub account: String,
+    pub issuer: Option<String>,
+    pub secret: Vec<u8>,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+}
+
+// everything but the unreserved characters from RFC 3986 gets escaped
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> Result<String, OtpError> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = text
+                .get(index + 1..index + 3)
+                .ok_or(OtpError::InvalidEncoding)?;
+            let byte = u8::from_str_radix(hex, 16).map_err(|_| OtpError::InvalidEncoding)?;
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| OtpError::InvalidEncoding)
+}
+
+fn parse_number(name: &str, value: &str) -> Result<u64, OtpError> {
+    value
+        .parse::<u64>()
+        .map_err(|_| OtpError::InvalidParameter(name.to_string()))
+}
+
+impl OtpAuthUri {
+    pub fn parse(uri: &str) -> Result<Self, OtpError> {
+        let rest = uri
+            .strip_prefix("otpauth://")
+            .ok_or(OtpError::InvalidScheme)?;
+        let (kind, rest) = rest.split_once('/').ok_or(OtpError::InvalidScheme)?;
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        // the label is "issuer:account", or just "account"
+        let label = percent_decode(label)?;
+        let (label_issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.to_string()), account.trim_start()),
+            None => (None, label.as_str()),
+        };
+
+        let mut secret = None;
+        let mut issuer = None;
+        let mut algorithm = Algorithm::Sha1;
+        let mut digits = MINIMUM_DIGITS;
+        let mut counter = None;
+        let mut period = 30;
+
+        for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let value = percent_decode(value)?;
+
+            match name {
+                "secret" => {
+                    secret = Some(base32_decode(&value).ok_or(OtpError::InvalidSecret)?);
+                }
+                "issuer" => issuer = Some(value),
+                "algorithm" => {
+                    algorithm = match value.to_ascii_uppercase().as_str() {
+                        "SHA1" => Algorithm::Sha1,
+                        "SHA256" => Algorithm::Sha256,
+                        _ => return Err(OtpError::UnknownAlgorithm(value)),
+                    };
+                }
+                "digits" => {
+                    let number = u32::try_from(parse_number(name, &value)?)
+                        .map_err(|_| OtpError::InvalidParameter(name.to_string()))?;
+                    digits = check_digits(number)?;
+                }
+                "counter" => counter = Some(parse_number(name, &value)?),
+                "period" => {
+                    period = parse_number(name, &value)?;
+                    if period == 0 {
+                        return Err(OtpError::InvalidParameter(name.to_string()));
+                    }
+                }
+                // anything else (like `image`) is some app's extension
+                _ => {}
+            }
+        }
+
+        let kind = match kind {
+            "hotp" => OtpKind::Hotp {
+                counter: counter.ok_or(OtpError::MissingCounter)?,
+            },
+            "totp" => OtpKind::Totp { period },
+            _ => return Err(OtpError::UnknownType(kind.to_string())),
+        };
+
+        Ok(OtpAuthUri {
+            kind,
+            account: account.to_string(),
+            // the issuer parameter wins if the two disagree
+            issuer: issuer.or(label_issuer),
+            secret: secret.ok_or(OtpError::MissingSecret)?,
+            algorithm,
+            digits,
+        })
+    }
+
+    pub fn to_uri(&self) -> String {
+        let (kind, extra) = match self.kind {
+            OtpKind::Hotp { counter } => ("hotp", format!("&counter={}", counter)),
+            OtpKind::Totp { period } => ("totp", format!("&period={}", period)),
+        };
+
+        let mut label = percent_encode(&self.account);
+        let mut issuer = String::new();
+        if let Some(name) = &self.issuer {
+            // some apps only read the label, others only the parameter
+            label = format!("{}:{}", percent_encode(name), label);
+            issuer = format!("&issuer={}", percent_encode(name));
+        }
+
+        format!(
+            "otpauth://{}/{}?secret={}{}&algorithm={}&digits={}{}",
+            kind,
+            label,
+            base32_encode(&self.secret),
+            issuer,
+            self.algorithm.name(),
+            self.digits,
+            extra
+        )
+    }
+
+    /// The generator for this URI, if it's an HOTP one. Parsed URIs always
+    /// make one, but the fields can be changed to ones that don't.
+    pub fn hotp(&self) -> Option<Result<Hotp, OtpError>> {
+        match self.kind {
+            OtpKind::Hotp { .. } => Some(Hotp::new(&self.secret, self.algorithm, self.digits)),
+            OtpKind::Totp { .. } => None,
+        }
+    }
+
+    /// The generator for this URI, if it's a TOTP one.
+    pub fn totp(&self) -> Option<Result<Totp, OtpError>> {
+        match self.kind {
+            OtpKind::Totp { period } => {
+                Some(Totp::new(&self.secret, self.algorithm, self.digits, period))
+            }
+            OtpKind::Hotp { .. } => None,
+        }
+    }
+}
+
+impl FromStr for OtpAuthUri {
+    type Err = OtpError;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        OtpAuthUri::parse(uri)
+    }
+}
+
+impl fmt::Display for OtpAuthUri {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.to_uri())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"12345678901234567890";
+    const SECRET_256: &[u8] = b"12345678901234567890123456789012";
+
+    #[test]
+    fn test_dynamic_truncation() {
+        // RFC 4226, section 5.4
+        let mac = [
+            0x1f, 0x86, 0x98, 0x69, 0x0e, 0x02, 0xca, 0x16, 0x61, 0x85, 0x50, 0xef, 0x7f, 0x19,
+            0xda, 0x8e, 0x94, 0x5b, 0x55, 0x5a,
+        ];
+        assert_eq!(0x50ef7f19, dynamic_truncation(&mac));
+    }
+
+    #[test]
+    fn test_hotp() {
+        // RFC 4226, appendix D
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+
+        let hotp = Hotp::new(SECRET, Algorithm::Sha1, 6).unwrap();
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(*code, hotp.generate(counter as u64));
+        }
+
+        assert_eq!(Some(4), hotp.verify("338314", 2, 3));
+        assert_eq!(None, hotp.verify("338314", 2, 1));
+        assert_eq!(None, hotp.verify("338314", 5, 10));
+    }
+
+    #[test]
+    fn test_totp() {
+        // RFC 6238, appendix B
+        let sha1 = Totp::new(SECRET, Algorithm::Sha1, 8, 30).unwrap();
+        let sha256 = Totp::new(SECRET_256, Algorithm::Sha256, 8, 30).unwrap();
+
+        let expected = [
+            (59, "94287082", "46119246"),
+            (1111111109, "07081804", "68084774"),
+            (1111111111, "14050471", "67062674"),
+            (1234567890, "89005924", "91819424"),
+            (2000000000, "69279037", "90698825"),
+            (20000000000, "65353130", "77737706"),
+        ];
+
+        for (time, code_sha1, code_sha256) in expected.iter() {
+            assert_eq!(*code_sha1, sha1.generate(*time));
+            assert_eq!(*code_sha256, sha256.generate(*time));
+        }
+    }
+
+    #[test]
+    fn test_totp_window() {
+        let totp = Totp::new(SECRET, Algorithm::Sha1, 6, 30).unwrap();
+        let code = totp.generate(1_000_000);
+
+        assert_eq!(Some(0), totp.verify(&code, 1_000_000, 1));
+        // the client's clock is a minute fast: its code is two steps ahead
+        assert_eq!(Some(2), totp.verify(&code, 1_000_000 - 60, 2));
+        assert_eq!(Some(-1), totp.verify(&code, 1_000_000 + 30, 1));
+        assert_eq!(None, totp.verify(&code, 1_000_000 + 90, 2));
+        assert_eq!(None, totp.verify("000000", 1_000_000, 0));
+
+        // offsets near the top of the counter range, and ones too big for an i64
+        let totp = Totp::new(SECRET, Algorithm::Sha1, 6, 1).unwrap();
+        let code = totp.generate(1 << 63);
+        assert_eq!(Some(1), totp.verify(&code, (1 << 63) - 1, 1));
+        let code = totp.generate(0);
+        assert_eq!(None, totp.verify(&code, u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn test_uri_round_trip() {
+        let uri = OtpAuthUri {
+            kind: OtpKind::Totp { period: 30 },
+            account: String::from("alice@example.com"),
+            issuer: Some(String::from("ACME Co")),
+            secret: SECRET.to_vec(),
+            algorithm: Algorithm::Sha256,
+            digits: 8,
+        };
+
+        let text = uri.to_uri();
+        assert_eq!(
+            "otpauth://totp/ACME%20Co:alice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=30",
+            text
+        );
+        assert_eq!(Ok(uri), text.parse::<OtpAuthUri>());
+    }
+
+    #[test]
+    fn test_uri_parse() {
+        let uri = OtpAuthUri::parse(
+            "otpauth://totp/Example:alice@google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example",
+        )
+        .unwrap();
+        assert_eq!("alice@google.com", uri.account);
+        assert_eq!(Some(String::from("Example")), uri.issuer);
+        assert_eq!(Algorithm::Sha1, uri.algorithm);
+        assert_eq!(6, uri.digits);
+        assert_eq!(b"Hello!\xde\xad\xbe\xef".to_vec(), uri.secret);
+        assert!(uri.hotp().is_none());
+        assert_eq!(
+            Totp::new(b"Hello!\xde\xad\xbe\xef", Algorithm::Sha1, 6, 30),
+            uri.totp().unwrap()
+        );
+
+        let mut changed = uri.clone();
+        changed.digits = 11;
+        assert_eq!(
+            Some(Err(OtpError::InvalidParameter(String::from("digits")))),
+            changed.totp()
+        );
+
+        let hotp = OtpAuthUri::parse(
+            "otpauth://hotp/bob?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=3",
+        )
+        .unwrap();
+        assert_eq!(OtpKind::Hotp { counter: 3 }, hotp.kind);
+        assert_eq!("969429", hotp.hotp().unwrap().unwrap().generate(3));
+
+        assert_eq!(
+            Err(OtpError::InvalidScheme),
+            OtpAuthUri::parse("https://example.com")
+        );
+        assert_eq!(
+            Err(OtpError::MissingCounter),
+            OtpAuthUri::parse("otpauth://hotp/bob?secret=GEZDGNBV")
+        );
+        assert_eq!(
+            Err(OtpError::MissingSecret),
+            OtpAuthUri::parse("otpauth://totp/bob")
+        );
+        assert_eq!(
+            Err(OtpError::UnknownAlgorithm(String::from("MD5"))),
+            OtpAuthUri::parse("otpauth://totp/bob?secret=GEZDGNBV&algorithm=MD5")
+        );
+        assert_eq!(
+            Err(OtpError::InvalidParameter(String::from("digits"))),
+            OtpAuthUri::parse("otpauth://totp/bob?secret=GEZDGNBV&digits=4")
+        );
+        // 2 ** 32 + 6 mustn't wrap around to 6
+        assert_eq!(
+            Err(OtpError::InvalidParameter(String::from("digits"))),
+            OtpAuthUri::parse("otpauth://totp/bob?secret=GEZDGNBV&digits=4294967302")
+        );
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert_eq!(
+            Some(OtpError::InvalidParameter(String::from("digits"))),
+            Hotp::new(SECRET, Algorithm::Sha1, 5).err()
+        );
+        assert_eq!(
+            Some(OtpError::InvalidParameter(String::from("digits"))),
+            Totp::new(SECRET, Algorithm::Sha1, 11, 30).err()
+        );
+        assert_eq!(
+            Some(OtpError::InvalidParameter(String::from("step"))),
+            Totp::new(SECRET, Algorithm::Sha1, 6, 0).err()
+        );
+    }
+
+    #[test]
+    fn test_debug_hides_secret() {
+        let hotp = Hotp::new(SECRET, Algorithm::Sha1, 6).unwrap();
+        let totp = Totp::new(SECRET, Algorithm::Sha1, 6, 30).unwrap();
+        for formatted in [format!("{:?}", hotp), format!("{:?}", totp)].iter() {
+            assert!(formatted.contains("<redacted>"));
+            // the bytes of "123"
+            assert!(!formatted.contains("49, 50, 51"));
+        }
+    }
+
+}
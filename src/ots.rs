@@ -0,0 +1,69 @@
+//! Hash-based one-time signatures: Lamport and WOTS+.
+//!
+//! These only need a hash function to be secure -- no factoring, no discrete
+//! logarithms -- so a quantum computer doesn't break them. The catch is in the
+//! name: a key pair may sign *one* message. Every signature gives away part of
+//! the secret key, and a second signature with the same key gives away enough
+//! for anyone to forge signatures on other messages.
+//!
+//! To help with that, `sign` takes the secret key by value, so the compiler
+//! won't let the same key be used twice. It can't stop a key being made again
+//! from the same seed, though -- a seed must only ever be used for one key
+//! pair, and that key pair for one message. (LMS and XMSS build many-time
+//! signatures out of these, and keep track of which keys have been used.)
+//!
+//! * Lamport is the simplest: one pair of secrets per bit of the message
+//!   digest, and the signature reveals one secret from every pair. Keys and
+//!   signatures are big (16 KiB and 8 KiB).
+//! * WOTS+ (Winternitz, as in RFC 8391) signs four bits at a time by walking
+//!   hash chains, which makes signatures about 2 KiB, at the cost of more
+//!   hashing.
+
+pub mod lamport;
+pub mod wots;
+
+pub use lamport::{LamportPublicKey, LamportSecretKey, LamportSignature};
+pub use wots::{WotsPublicKey, WotsSecretKey, WotsSignature};
+
+use std::fmt;
+
+pub type Hash = [u8; 32];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OtsError {
+    /// Serialized keys and signatures have a fixed length.
+    InvalidLength { expected: usize, actual: usize },
+}
+
+impl fmt::Display for OtsError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OtsError::InvalidLength { expected, actual } => write!(
+                formatter,
+                "expected {} bytes, but there are {}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OtsError {}
+
+/// Splits `bytes` into hashes, if there are exactly `count` of them.
+fn split_hashes(bytes: &[u8], count: usize) -> Result<Vec<Hash>, OtsError> {
+    if bytes.len() != count * 32 {
+        return Err(OtsError::InvalidLength {
+            expected: count * 32,
+            actual: bytes.len(),
+        });
+    }
+
+    Ok(bytes
+        .chunks_exact(32)
+        .map(|chunk| {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(chunk);
+            hash
+        })
+        .collect())
+}
@@ -0,0 +1,154 @@
+//! Lamport signatures over SHA-256.
+//!
+//! The secret key is 256 pairs of random values, and the public key is their
+//! hashes. To sign, hash the message and, for each of the 256 bits of the
+//! digest, reveal the first value of the pair if the bit is 0 and the second
+//! if it's 1. The verifier hashes each revealed value and checks it against
+//! the public key.
+
+use super::{split_hashes, Hash, OtsError};
+use crate::digest::Digest;
+use crate::hmac::hmac;
+use crate::sha2::Sha256;
+
+const BITS: usize = 256;
+
+fn bit(digest: &Hash, index: usize) -> usize {
+    usize::from(digest[index / 8] >> (7 - index % 8) & 1)
+}
+
+pub struct LamportSecretKey {
+    values: Vec<[Hash; 2]>,
+}
+
+impl LamportSecretKey {
+    /// Derives the whole key from `seed`, which should be 32 random bytes.
+    /// Each secret value is `HMAC-SHA256(seed, "Lamport" || index || bit)`,
+    /// with the index as two big-endian bytes.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let derive = |index: usize, bit: u8| {
+            let mut label = b"Lamport".to_vec();
+            label.extend_from_slice(&(index as u16).to_be_bytes());
+            label.push(bit);
+            hmac::<Sha256>(seed, &label)
+        };
+
+        LamportSecretKey {
+            values: (0..BITS)
+                .map(|index| [derive(index, 0), derive(index, 1)])
+                .collect(),
+        }
+    }
+
+    pub fn public_key(&self) -> LamportPublicKey {
+        LamportPublicKey {
+            hashes: self
+                .values
+                .iter()
+                .map(|[zero, one]| [Sha256::digest(zero), Sha256::digest(one)])
+                .collect(),
+        }
+    }
+
+    /// Signs the SHA-256 digest of `message`, using up the key.
+    pub fn sign(self, message: &[u8]) -> LamportSignature {
+        let digest = Sha256::digest(message);
+
+        LamportSignature {
+            values: (0..BITS)
+                .map(|index| self.values[index][bit(&digest, index)])
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LamportPublicKey {
+    hashes: Vec<[Hash; 2]>,
+}
+
+impl LamportPublicKey {
+    pub fn verify(&self, message: &[u8], signature: &LamportSignature) -> bool {
+        let digest = Sha256::digest(message);
+
+        signature
+            .values
+            .iter()
+            .enumerate()
+            .all(|(index, value)| Sha256::digest(value) == self.hashes[index][bit(&digest, index)])
+    }
+
+    /// The hashes for a 0 bit and a 1 bit, for each bit in turn: 16384 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.hashes.iter().flatten().flatten().copied().collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OtsError> {
+        let hashes = split_hashes(bytes, 2 * BITS)?;
+
+        Ok(LamportPublicKey {
+            hashes: hashes
+                .chunks_exact(2)
+                .map(|pair| [pair[0], pair[1]])
+                .collect(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LamportSignature {
+    values: Vec<Hash>,
+}
+
+impl LamportSignature {
+    /// The revealed values, one per bit: 8192 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.values.iter().flatten().copied().collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OtsError> {
+        Ok(LamportSignature {
+            values: split_hashes(bytes, BITS)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lamport() {
+        let secret_key = LamportSecretKey::from_seed(b"an example seed, not a random one");
+        let public_key = secret_key.public_key();
+        let signature = secret_key.sign(b"firmware manifest v1");
+
+        assert!(public_key.verify(b"firmware manifest v1", &signature));
+        assert!(!public_key.verify(b"firmware manifest v2", &signature));
+
+        // the same seed always gives the same key
+        let again = LamportSecretKey::from_seed(b"an example seed, not a random one");
+        assert_eq!(public_key, again.public_key());
+
+        let public_bytes = public_key.to_bytes();
+        let signature_bytes = signature.to_bytes();
+        assert_eq!(16384, public_bytes.len());
+        assert_eq!(8192, signature_bytes.len());
+
+        let public_key = LamportPublicKey::from_bytes(&public_bytes).unwrap();
+        let mut signature = LamportSignature::from_bytes(&signature_bytes).unwrap();
+        assert!(public_key.verify(b"firmware manifest v1", &signature));
+
+        signature.values[100][0] ^= 1;
+        assert!(!public_key.verify(b"firmware manifest v1", &signature));
+
+        assert_eq!(
+            Err(OtsError::InvalidLength {
+                expected: 8192,
+                actual: 8191
+            }),
+            LamportSignature::from_bytes(&signature_bytes[1..])
+        );
+    }
+
+}
@@ -0,0 +1,399 @@
+//! WOTS+, the Winternitz one-time signature from RFC 8391, with SHA-256,
+//! `n = 32` and `w = 16`.
+//!
+//! The digest being signed is read as 64 base-16 digits, followed by a 3-digit
+//! checksum, and each of those 67 digits gets its own hash chain. The secret
+//! key is the start of every chain and the public key is the end, 15 steps
+//! along. Signing digit `d` reveals the value `d` steps along its chain, and the
+//! verifier walks the remaining `15 - d` steps and checks that it ends up at
+//! the public key.
+//!
+//! Without the checksum, a forger could take a signature and walk some chains
+//! further, signing a digest with bigger digits. The checksum adds up how far
+//! each chain has *left* to go, so making any digit bigger makes the checksum
+//! smaller -- and that would mean walking a chain backwards.
+//!
+//! Every step of every chain is hashed with its own key and bitmask, made from
+//! the public seed and the step's `Address`. That's the "+": it keeps an
+//! attacker from attacking all of the chains (or all of the keys) at once.
+
+use super::{split_hashes, Hash, OtsError};
+use crate::digest::Digest;
+use crate::hmac::hmac;
+use crate::sha2::Sha256;
+
+pub const WINTERNITZ: usize = 16;
+const LOG_WINTERNITZ: usize = 4;
+
+/// How many chains there are: 64 for the digest, and 3 for the checksum.
+pub const CHAINS: usize = MESSAGE_CHAINS + CHECKSUM_CHAINS;
+const MESSAGE_CHAINS: usize = 64;
+const CHECKSUM_CHAINS: usize = 3;
+
+// the first 32 bytes of every hash say what the hash is for
+const PADDING_F: u8 = 0;
+const PADDING_PRF: u8 = 3;
+const PADDING_PRF_KEYGEN: u8 = 4;
+
+/// The 32-byte address (`ADRS`) from RFC 8391, section 2.5: where in a key
+/// (and in a larger XMSS structure) a hash is being computed. Every word is
+/// big endian.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Address([u8; 32]);
+
+impl Address {
+    fn set_word(&mut self, index: usize, value: u32) {
+        self.0[4 * index..4 * index + 4].copy_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn set_layer(&mut self, layer: u32) {
+        self.set_word(0, layer);
+    }
+
+    pub fn set_tree(&mut self, tree: u64) {
+        self.0[4..12].copy_from_slice(&tree.to_be_bytes());
+    }
+
+    /// Changing the type clears everything after it.
+    pub fn set_type(&mut self, address_type: u32) {
+        self.set_word(3, address_type);
+        for word in 4..8 {
+            self.set_word(word, 0);
+        }
+    }
+
+    pub fn set_ots(&mut self, ots: u32) {
+        self.set_word(4, ots);
+    }
+
+    pub fn set_chain(&mut self, chain: u32) {
+        self.set_word(5, chain);
+    }
+
+    pub fn set_hash(&mut self, hash: u32) {
+        self.set_word(6, hash);
+    }
+
+    /// Which L-tree, in an L-tree address (type 1).
+    pub fn set_ltree(&mut self, ltree: u32) {
+        self.set_word(4, ltree);
+    }
+
+    /// For L-tree and hash tree addresses (types 1 and 2).
+    pub fn set_tree_height(&mut self, height: u32) {
+        self.set_word(5, height);
+    }
+
+    /// For L-tree and hash tree addresses (types 1 and 2).
+    pub fn set_tree_index(&mut self, index: u32) {
+        self.set_word(6, index);
+    }
+
+    pub fn set_key_and_mask(&mut self, key_and_mask: u32) {
+        self.set_word(7, key_and_mask);
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// `SHA-256(toByte(padding, 32) || key || message...)`, the shape of all of the
+/// keyed hashes in RFC 8391.
+pub(crate) fn keyed_hash(padding: u8, key: &[u8], message: &[&[u8]]) -> Hash {
+    let mut prefix = [0u8; 32];
+    prefix[31] = padding;
+
+    let mut hasher = Sha256::default();
+    hasher.update(&prefix);
+    hasher.update(key);
+    for part in message {
+        hasher.update(part);
+    }
+    hasher.finalize()
+}
+
+pub(crate) fn prf(public_seed: &Hash, address: &Address) -> Hash {
+    keyed_hash(PADDING_PRF, public_seed, &[address.as_bytes()])
+}
+
+/// `chain` from RFC 8391, section 3.1.2: `steps` steps along a chain, starting
+/// `start` steps in.
+fn chain(
+    value: &Hash,
+    start: usize,
+    steps: usize,
+    public_seed: &Hash,
+    address: &mut Address,
+) -> Hash {
+    let mut value = *value;
+
+    for step in start..start + steps {
+        address.set_hash(step as u32);
+        address.set_key_and_mask(0);
+        let key = prf(public_seed, address);
+        address.set_key_and_mask(1);
+        let bitmask = prf(public_seed, address);
+
+        let mut masked = [0u8; 32];
+        for (masked, (value, mask)) in masked.iter_mut().zip(value.iter().zip(bitmask.iter())) {
+            *masked = value ^ mask;
+        }
+        value = keyed_hash(PADDING_F, &key, &[&masked]);
+    }
+
+    value
+}
+
+/// The digits of the digest, then of the checksum.
+fn digits(digest: &Hash) -> [usize; CHAINS] {
+    let mut digits = [0usize; CHAINS];
+    for (index, byte) in digest.iter().enumerate() {
+        digits[2 * index] = usize::from(byte >> 4);
+        digits[2 * index + 1] = usize::from(byte & 0x0F);
+    }
+
+    let checksum: usize = digits[..MESSAGE_CHAINS]
+        .iter()
+        .map(|digit| WINTERNITZ - 1 - digit)
+        .sum();
+    // RFC 8391 shifts the checksum left by 4 to fill two bytes, then only reads
+    // the top 12 bits back out -- so its digits are just its low 12 bits
+    for index in 0..CHECKSUM_CHAINS {
+        let shift = LOG_WINTERNITZ * (CHECKSUM_CHAINS - 1 - index);
+        digits[MESSAGE_CHAINS + index] = (checksum >> shift) & (WINTERNITZ - 1);
+    }
+
+    digits
+}
+
+/// The start of chain `index`: `PRF_keygen(secret_seed, public_seed || ADRS)`,
+/// the way NIST SP 800-208 derives WOTS+ secret keys.
+fn secret_value(
+    secret_seed: &Hash,
+    public_seed: &Hash,
+    address: &mut Address,
+    index: usize,
+) -> Hash {
+    address.set_chain(index as u32);
+    address.set_hash(0);
+    address.set_key_and_mask(0);
+    keyed_hash(
+        PADDING_PRF_KEYGEN,
+        secret_seed,
+        &[public_seed, address.as_bytes()],
+    )
+}
+
+/// The end of every chain, for the key at `address`.
+pub fn public_key(secret_seed: &Hash, public_seed: &Hash, address: &Address) -> Vec<Hash> {
+    let mut address = *address;
+
+    (0..CHAINS)
+        .map(|index| {
+            let start = secret_value(secret_seed, public_seed, &mut address, index);
+            chain(&start, 0, WINTERNITZ - 1, public_seed, &mut address)
+        })
+        .collect()
+}
+
+/// Signs a 32-byte digest with the key at `address`.
+pub fn sign(digest: &Hash, secret_seed: &Hash, public_seed: &Hash, address: &Address) -> Vec<Hash> {
+    let mut address = *address;
+
+    digits(digest)
+        .iter()
+        .enumerate()
+        .map(|(index, digit)| {
+            let start = secret_value(secret_seed, public_seed, &mut address, index);
+            chain(&start, 0, *digit, public_seed, &mut address)
+        })
+        .collect()
+}
+
+/// Walks each chain in a signature the rest of the way to its end. If the
+/// signature is good, this is the public key.
+///
+/// `signature` must hold `CHAINS` hashes.
+pub fn public_key_from_signature(
+    digest: &Hash,
+    signature: &[Hash],
+    public_seed: &Hash,
+    address: &Address,
+) -> Vec<Hash> {
+    assert_eq!(CHAINS, signature.len());
+
+    let mut address = *address;
+
+    digits(digest)
+        .iter()
+        .zip(signature.iter())
+        .enumerate()
+        .map(|(index, (digit, value))| {
+            address.set_chain(index as u32);
+            chain(
+                value,
+                *digit,
+                WINTERNITZ - 1 - digit,
+                public_seed,
+                &mut address,
+            )
+        })
+        .collect()
+}
+
+/// A standalone WOTS+ key, at the all-zero address.
+pub struct WotsSecretKey {
+    secret_seed: Hash,
+    public_seed: Hash,
+}
+
+impl WotsSecretKey {
+    pub fn new(secret_seed: Hash, public_seed: Hash) -> Self {
+        WotsSecretKey {
+            secret_seed,
+            public_seed,
+        }
+    }
+
+    /// Derives both seeds from one: `HMAC-SHA256(seed, "WOTS+ secret seed")`
+    /// and `HMAC-SHA256(seed, "WOTS+ public seed")`.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        WotsSecretKey::new(
+            hmac::<Sha256>(seed, b"WOTS+ secret seed"),
+            hmac::<Sha256>(seed, b"WOTS+ public seed"),
+        )
+    }
+
+    pub fn public_key(&self) -> WotsPublicKey {
+        WotsPublicKey {
+            public_seed: self.public_seed,
+            chains: public_key(&self.secret_seed, &self.public_seed, &Address::default()),
+        }
+    }
+
+    /// Signs the SHA-256 digest of `message`, using up the key.
+    pub fn sign(self, message: &[u8]) -> WotsSignature {
+        WotsSignature {
+            chains: sign(
+                &Sha256::digest(message),
+                &self.secret_seed,
+                &self.public_seed,
+                &Address::default(),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WotsPublicKey {
+    public_seed: Hash,
+    chains: Vec<Hash>,
+}
+
+impl WotsPublicKey {
+    pub fn verify(&self, message: &[u8], signature: &WotsSignature) -> bool {
+        let chains = public_key_from_signature(
+            &Sha256::digest(message),
+            &signature.chains,
+            &self.public_seed,
+            &Address::default(),
+        );
+
+        chains == self.chains
+    }
+
+    /// The public seed, then the end of each chain: 2176 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.public_seed.to_vec();
+        bytes.extend(self.chains.iter().flatten());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OtsError> {
+        let mut hashes = split_hashes(bytes, 1 + CHAINS)?;
+        let public_seed = hashes.remove(0);
+
+        Ok(WotsPublicKey {
+            public_seed,
+            chains: hashes,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WotsSignature {
+    chains: Vec<Hash>,
+}
+
+impl WotsSignature {
+    /// One value from each chain: 2144 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.chains.iter().flatten().copied().collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, OtsError> {
+        Ok(WotsSignature {
+            chains: split_hashes(bytes, CHAINS)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digits() {
+        // all zeroes: the checksum is as big as it gets, 64 * 15 = 0x3c0
+        let digits = digits(&[0u8; 32]);
+        assert!(digits[..64].iter().all(|digit| *digit == 0));
+        assert_eq!([3, 12, 0], digits[64..]);
+
+        let digits = super::digits(&[0xffu8; 32]);
+        assert_eq!([0, 0, 0], digits[64..]);
+    }
+
+    #[test]
+    fn test_wots() {
+        let secret_key = WotsSecretKey::from_seed(b"an example seed, not a random one");
+        let public_key = secret_key.public_key();
+        let signature = secret_key.sign(b"firmware manifest v1");
+
+        assert!(public_key.verify(b"firmware manifest v1", &signature));
+        assert!(!public_key.verify(b"firmware manifest v2", &signature));
+
+        let public_bytes = public_key.to_bytes();
+        let signature_bytes = signature.to_bytes();
+        assert_eq!(2176, public_bytes.len());
+        assert_eq!(2144, signature_bytes.len());
+
+        let public_key = WotsPublicKey::from_bytes(&public_bytes).unwrap();
+        let mut signature = WotsSignature::from_bytes(&signature_bytes).unwrap();
+        assert!(public_key.verify(b"firmware manifest v1", &signature));
+
+        signature.chains[66][0] ^= 1;
+        assert!(!public_key.verify(b"firmware manifest v1", &signature));
+        assert!(WotsPublicKey::from_bytes(&public_bytes[..2144]).is_err());
+    }
+
+    #[test]
+    fn test_addresses() {
+        // the same seeds at a different address are a different key
+        let secret_seed = [1u8; 32];
+        let public_seed = [2u8; 32];
+        let mut address = Address::default();
+        let first = public_key(&secret_seed, &public_seed, &address);
+        address.set_ots(1);
+        let second = public_key(&secret_seed, &public_seed, &address);
+        assert_ne!(first, second);
+
+        let digest = Sha256::digest(b"message");
+        let signature = sign(&digest, &secret_seed, &public_seed, &address);
+        assert_eq!(
+            second,
+            public_key_from_signature(&digest, &signature, &public_seed, &address)
+        );
+    }
+
+}
@@ -0,0 +1,240 @@
+//! Password-based key derivation: PBKDF2 and the older PBKDF1 (RFC 8018).
+//!
+//! Passwords make poor keys -- they're short, and people pick guessable ones.
+//! A KDF can't fix that, but it can make every guess expensive: the password
+//! goes through the hash (or HMAC) thousands of times, so an attacker with a
+//! list of candidates has to do all that work for each one. The salt makes
+//! sure they can't do the work once and reuse it against every user.
+//!
+//! Use PBKDF2. PBKDF1 is only here to read old data: its output can't be any
+//! longer than the hash, and it was only ever specified with MD2, MD5 and
+//! SHA-1.
+
+use crate::digest::Digest;
+use crate::hmac::Hmac;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PbkdfError {
+    /// Both KDFs need at least one iteration.
+    ZeroIterations,
+    /// More output was asked for than the KDF can produce: the hash output
+    /// length for PBKDF1, `(2 ** 32 - 1)` blocks of it for PBKDF2.
+    OutputTooLong { requested: usize, maximum: usize },
+}
+
+impl fmt::Display for PbkdfError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PbkdfError::ZeroIterations => {
+                write!(formatter, "the iteration count must be at least 1")
+            }
+            PbkdfError::OutputTooLong { requested, maximum } => write!(
+                formatter,
+                "this KDF can produce at most {} bytes with this hash, but {} were requested",
+                maximum, requested
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PbkdfError {}
+
+/// PBKDF2 with HMAC over `D`, filling all of `output`.
+///
+/// Each block of output is `U(1) ^ U(2) ^ ... ^ U(iterations)`, where
+/// `U(1) = HMAC(password, salt || block number)` and every later `U` is the
+/// HMAC of the one before it.
+pub fn pbkdf2<D: Digest>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    output: &mut [u8],
+) -> Result<(), PbkdfError> {
+    if iterations == 0 {
+        return Err(PbkdfError::ZeroIterations);
+    }
+
+    let maximum = (u32::MAX as usize).saturating_mul(D::OUTPUT_LENGTH);
+    if output.len() > maximum {
+        return Err(PbkdfError::OutputTooLong {
+            requested: output.len(),
+            maximum,
+        });
+    }
+
+    // keying HMAC is the same work every time, so do it once and clone
+    let keyed = Hmac::<D>::new(password);
+
+    for (index, piece) in output.chunks_mut(D::OUTPUT_LENGTH).enumerate() {
+        let block_number = index as u32 + 1;
+
+        let mut mac = keyed.clone();
+        mac.update(salt);
+        mac.update(&block_number.to_be_bytes());
+        let mut previous = mac.finalize();
+
+        let mut block = previous.as_ref().to_vec();
+        for _ in 1..iterations {
+            let mut mac = keyed.clone();
+            mac.update(previous.as_ref());
+            previous = mac.finalize();
+
+            for (byte, next) in block.iter_mut().zip(previous.as_ref().iter()) {
+                *byte ^= next;
+            }
+        }
+
+        piece.copy_from_slice(&block[..piece.len()]);
+    }
+
+    Ok(())
+}
+
+/// PBKDF2, into a new `Vec` of `length` bytes.
+pub fn pbkdf2_to_vec<D: Digest>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    length: usize,
+) -> Result<Vec<u8>, PbkdfError> {
+    let mut output = vec![0u8; length];
+    pbkdf2::<D>(password, salt, iterations, &mut output)?;
+    Ok(output)
+}
+
+/// PBKDF1: hash `password || salt`, then hash the result again
+/// `iterations - 1` more times, and keep the first `length` bytes.
+///
+/// The RFC has the salt as exactly 8 bytes; any length is accepted here, since
+/// formats that use PBKDF1 haven't always stuck to that.
+pub fn pbkdf1<D: Digest>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    length: usize,
+) -> Result<Vec<u8>, PbkdfError> {
+    if iterations == 0 {
+        return Err(PbkdfError::ZeroIterations);
+    }
+
+    if length > D::OUTPUT_LENGTH {
+        return Err(PbkdfError::OutputTooLong {
+            requested: length,
+            maximum: D::OUTPUT_LENGTH,
+        });
+    }
+
+    let mut hasher = D::default();
+    hasher.update(password);
+    hasher.update(salt);
+    let mut derived = hasher.finalize();
+
+    for _ in 1..iterations {
+        derived = D::digest(derived.as_ref());
+    }
+
+    Ok(derived.as_ref()[..length].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::md5::Md5;
+    use crate::sha2::Sha256;
+    use crate::utils::bytes_to_hex;
+    use crate::Sha1;
+
+    fn pbkdf2_hex<D: Digest>(
+        password: &[u8],
+        salt: &[u8],
+        iterations: u32,
+        length: usize,
+    ) -> String {
+        bytes_to_hex(&pbkdf2_to_vec::<D>(password, salt, iterations, length).unwrap())
+    }
+
+    #[test]
+    fn test_rfc_6070() {
+        assert_eq!(
+            "0c60c80f961f0e71f3a9b524af6012062fe037a6",
+            pbkdf2_hex::<Sha1>(b"password", b"salt", 1, 20)
+        );
+        assert_eq!(
+            "ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957",
+            pbkdf2_hex::<Sha1>(b"password", b"salt", 2, 20)
+        );
+        assert_eq!(
+            "4b007901b765489abead49d926f721d065a429c1",
+            pbkdf2_hex::<Sha1>(b"password", b"salt", 4096, 20)
+        );
+        // more than one block of output
+        assert_eq!(
+            "3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038",
+            pbkdf2_hex::<Sha1>(
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                25
+            )
+        );
+        assert_eq!(
+            "56fa6aa75548099dcc37d7f03425e0c3",
+            pbkdf2_hex::<Sha1>(b"pass\0word", b"sa\0lt", 4096, 16)
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_sha256_and_md5() {
+        assert_eq!(
+            "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+            pbkdf2_hex::<Sha256>(b"password", b"salt", 1, 32)
+        );
+        assert_eq!(
+            "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a",
+            pbkdf2_hex::<Sha256>(b"password", b"salt", 4096, 32)
+        );
+        assert_eq!(
+            "8d189946a32d883622a16ae18af0632f5791d5e7b1abb0ab1757d28ce3405614",
+            pbkdf2_hex::<Md5>(b"password", b"salt", 1000, 32)
+        );
+    }
+
+    #[test]
+    fn test_wpa2_psk() {
+        // the pairwise master key is PBKDF2-HMAC-SHA1 of the passphrase, salted
+        // with the SSID
+        assert_eq!(
+            "f42c6fc52df0ebef9ebb4b90b38a5f902e83fe1b135a70e23aed762e9710a12e",
+            pbkdf2_hex::<Sha1>(b"password", b"IEEE", 4096, 32)
+        );
+    }
+
+    #[test]
+    fn test_pbkdf1() {
+        assert_eq!(
+            "f8833429b112582447bc66f433497f75",
+            bytes_to_hex(&pbkdf1::<Sha1>(b"password", b"saltsalt", 1000, 16).unwrap())
+        );
+        assert_eq!(
+            "8006de5d2a5d15f9bbdb8f40196d5af1",
+            bytes_to_hex(&pbkdf1::<Md5>(b"password", b"saltsalt", 1000, 16).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Err(PbkdfError::ZeroIterations),
+            pbkdf2_to_vec::<Sha256>(b"password", b"salt", 0, 32)
+        );
+        assert_eq!(
+            Err(PbkdfError::OutputTooLong {
+                requested: 21,
+                maximum: 20
+            }),
+            pbkdf1::<Sha1>(b"password", b"saltsalt", 1, 21)
+        );
+    }
+
+}
@@ -0,0 +1,401 @@
+//! The padding schemes from PKCS #1 v2.2 (RFC 8017), on byte strings.
+//!
+//! RSA on its own is just `m ^ e mod n`, and encrypting or signing raw
+//! messages with it is badly broken. These encodings turn a message into an
+//! *encoded message* the length of the modulus, ready for the modular
+//! exponentiation (which isn't here -- that's for a bignum library or an
+//! HSM):
+//!
+//! * EME-OAEP for encryption: the message is padded and mixed with a random
+//!   seed, so that the same message never encrypts the same way twice
+//! * EMSA-PSS for signatures: the hash of the message is combined with a random
+//!   salt, with a proof of security that the older PKCS #1 v1.5 padding lacks
+//!
+//! Both are built on MGF1, a mask generation function that stretches a seed
+//! into a mask of any length with a hash. Nothing here generates randomness:
+//! the seed and salt are passed in, and must come from a secure random source.
+
+use crate::digest::Digest;
+use crate::utils::constant_time_eq;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pkcs1Error {
+    /// The message doesn't fit in the modulus with the padding (for OAEP, at
+    /// most `k - 2 * hash length - 2` bytes).
+    MessageTooLong { length: usize, maximum: usize },
+    /// The modulus is too small for this hash (and salt, for PSS).
+    EncodingTooShort,
+    /// The OAEP seed has to be exactly as long as the hash output.
+    InvalidSeedLength { length: usize, expected: usize },
+    /// An OAEP encoded message didn't decode. On purpose, this doesn't say
+    /// what was wrong with it: telling an attacker which check failed is how
+    /// Manger's attack recovers plaintexts.
+    DecryptionError,
+    /// The PSS encoded message doesn't match the message.
+    Inconsistent,
+}
+
+impl fmt::Display for Pkcs1Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pkcs1Error::MessageTooLong { length, maximum } => write!(
+                formatter,
+                "the message is {} bytes, but at most {} fit",
+                length, maximum
+            ),
+            Pkcs1Error::EncodingTooShort => write!(formatter, "the encoded message is too short"),
+            Pkcs1Error::InvalidSeedLength { length, expected } => write!(
+                formatter,
+                "the seed must be {} bytes, but is {}",
+                expected, length
+            ),
+            Pkcs1Error::DecryptionError => write!(formatter, "decryption error"),
+            Pkcs1Error::Inconsistent => write!(formatter, "inconsistent"),
+        }
+    }
+}
+
+impl std::error::Error for Pkcs1Error {}
+
+/// MGF1: `Hash(seed || 0) || Hash(seed || 1) || ...` (with the counter as four
+/// big-endian bytes), cut to the length of `mask`.
+pub fn mgf1<D: Digest>(seed: &[u8], mask: &mut [u8]) {
+    for (counter, piece) in mask.chunks_mut(D::OUTPUT_LENGTH).enumerate() {
+        let mut hasher = D::default();
+        hasher.update(seed);
+        hasher.update(&(counter as u32).to_be_bytes());
+        piece.copy_from_slice(&hasher.finalize().as_ref()[..piece.len()]);
+    }
+}
+
+/// Xors the MGF1 mask for `seed` into `data`.
+fn apply_mask<D: Digest>(seed: &[u8], data: &mut [u8]) {
+    let mut mask = vec![0u8; data.len()];
+    mgf1::<D>(seed, &mut mask);
+
+    for (byte, mask_byte) in data.iter_mut().zip(mask.iter()) {
+        *byte ^= mask_byte;
+    }
+}
+
+/// EME-OAEP encoding, for a modulus `k` bytes long.
+///
+/// The encoded message is `00 || maskedSeed || maskedDB`, where `DB` is
+/// `Hash(label) || 00 ... 00 || 01 || message`. The label is usually empty.
+pub fn oaep_encode<D: Digest>(
+    message: &[u8],
+    label: &[u8],
+    seed: &[u8],
+    k: usize,
+) -> Result<Vec<u8>, Pkcs1Error> {
+    let hash_length = D::OUTPUT_LENGTH;
+
+    if k < 2 * hash_length + 2 {
+        return Err(Pkcs1Error::EncodingTooShort);
+    }
+
+    let maximum = k - 2 * hash_length - 2;
+    if message.len() > maximum {
+        return Err(Pkcs1Error::MessageTooLong {
+            length: message.len(),
+            maximum,
+        });
+    }
+
+    if seed.len() != hash_length {
+        return Err(Pkcs1Error::InvalidSeedLength {
+            length: seed.len(),
+            expected: hash_length,
+        });
+    }
+
+    let mut data_block = Vec::with_capacity(k - hash_length - 1);
+    data_block.extend_from_slice(D::digest(label).as_ref());
+    data_block.resize(k - hash_length - message.len() - 2, 0x00);
+    data_block.push(0x01);
+    data_block.extend_from_slice(message);
+
+    let mut masked_seed = seed.to_vec();
+    apply_mask::<D>(&masked_seed, &mut data_block);
+    apply_mask::<D>(&data_block, &mut masked_seed);
+
+    let mut encoded = Vec::with_capacity(k);
+    encoded.push(0x00);
+    encoded.extend_from_slice(&masked_seed);
+    encoded.extend_from_slice(&data_block);
+
+    Ok(encoded)
+}
+
+/// EME-OAEP decoding: recovers the message from an encoded message, after
+/// the RSA decryption.
+///
+/// Every check is made before deciding whether to fail, and every failure is
+/// the same `DecryptionError`.
+pub fn oaep_decode<D: Digest>(encoded: &[u8], label: &[u8]) -> Result<Vec<u8>, Pkcs1Error> {
+    let hash_length = D::OUTPUT_LENGTH;
+    let k = encoded.len();
+
+    if k < 2 * hash_length + 2 {
+        return Err(Pkcs1Error::DecryptionError);
+    }
+
+    let mut seed = encoded[1..=hash_length].to_vec();
+    let mut data_block = encoded[hash_length + 1..].to_vec();
+    apply_mask::<D>(&data_block, &mut seed);
+    apply_mask::<D>(&seed, &mut data_block);
+
+    let label_hash_matches =
+        constant_time_eq(D::digest(label).as_ref(), &data_block[..hash_length]);
+
+    // find the 01 that ends the zero padding, looking at every byte either way
+    let mut separator = 0;
+    let mut padding_valid = true;
+    for (index, byte) in data_block.iter().enumerate().skip(hash_length) {
+        let looking = separator == 0;
+        if looking && *byte == 0x01 {
+            separator = index;
+        }
+        padding_valid &= !(looking && *byte != 0x00 && *byte != 0x01);
+    }
+
+    if encoded[0] != 0x00 || !label_hash_matches || separator == 0 || !padding_valid {
+        return Err(Pkcs1Error::DecryptionError);
+    }
+
+    Ok(data_block[separator + 1..].to_vec())
+}
+
+/// Hash(00 00 00 00 00 00 00 00 || Hash(message) || salt)
+fn pss_hash<D: Digest>(message: &[u8], salt: &[u8]) -> D::Output {
+    let mut hasher = D::default();
+    hasher.update(&[0u8; 8]);
+    hasher.update(D::digest(message).as_ref());
+    hasher.update(salt);
+    hasher.finalize()
+}
+
+fn encoded_length(encoded_bits: usize) -> usize {
+    encoded_bits.div_ceil(8)
+}
+
+/// EMSA-PSS encoding of `message` into `encoded_bits` bits -- one less than
+/// the length of the modulus in bits, so the result is always smaller than
+/// the modulus.
+///
+/// The salt is usually as long as the hash output; an empty salt makes the
+/// signature deterministic.
+pub fn pss_encode<D: Digest>(
+    message: &[u8],
+    salt: &[u8],
+    encoded_bits: usize,
+) -> Result<Vec<u8>, Pkcs1Error> {
+    let hash_length = D::OUTPUT_LENGTH;
+    let length = encoded_length(encoded_bits);
+
+    if length < hash_length + salt.len() + 2 {
+        return Err(Pkcs1Error::EncodingTooShort);
+    }
+
+    let hash = pss_hash::<D>(message, salt);
+
+    // DB = 00 ... 00 || 01 || salt
+    let mut data_block = vec![0u8; length - salt.len() - hash_length - 2];
+    data_block.push(0x01);
+    data_block.extend_from_slice(salt);
+
+    apply_mask::<D>(hash.as_ref(), &mut data_block);
+    data_block[0] &= 0xFF >> (8 * length - encoded_bits);
+
+    let mut encoded = data_block;
+    encoded.extend_from_slice(hash.as_ref());
+    encoded.push(0xbc);
+
+    Ok(encoded)
+}
+
+/// EMSA-PSS verification: checks that `encoded` (the RSA public operation
+/// applied to a signature) is a valid encoding of `message` with a salt of
+/// `salt_length` bytes.
+pub fn pss_verify<D: Digest>(
+    message: &[u8],
+    encoded: &[u8],
+    encoded_bits: usize,
+    salt_length: usize,
+) -> Result<(), Pkcs1Error> {
+    let hash_length = D::OUTPUT_LENGTH;
+    let length = encoded_length(encoded_bits);
+
+    if encoded.len() != length || length < hash_length + salt_length + 2 {
+        return Err(Pkcs1Error::Inconsistent);
+    }
+
+    if encoded[length - 1] != 0xbc {
+        return Err(Pkcs1Error::Inconsistent);
+    }
+
+    let (masked_data_block, rest) = encoded.split_at(length - hash_length - 1);
+    let hash = &rest[..hash_length];
+
+    let unused_bits = 8 * length - encoded_bits;
+    let top_bits_mask = !(0xFFu8 >> unused_bits);
+    if masked_data_block[0] & top_bits_mask != 0 {
+        return Err(Pkcs1Error::Inconsistent);
+    }
+
+    let mut data_block = masked_data_block.to_vec();
+    apply_mask::<D>(hash, &mut data_block);
+    data_block[0] &= !top_bits_mask;
+
+    let padding_length = length - hash_length - salt_length - 2;
+    if data_block[..padding_length]
+        .iter()
+        .any(|byte| *byte != 0x00)
+        || data_block[padding_length] != 0x01
+    {
+        return Err(Pkcs1Error::Inconsistent);
+    }
+
+    let salt = &data_block[padding_length + 1..];
+    if !constant_time_eq(pss_hash::<D>(message, salt).as_ref(), hash) {
+        return Err(Pkcs1Error::Inconsistent);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha2::Sha256;
+    use crate::utils::{bytes_to_hex, hex_to_bytes};
+    use crate::Sha1;
+
+    // the worked examples from RSA Laboratories' PKCS #1 v2.1 test vectors
+    // (oaep-int.txt and pss-int.txt), both with SHA-1 and a 1024-bit modulus
+    const OAEP_MESSAGE: &str = "d436e99569fd32a7c8a05bbc90d32c49";
+    const OAEP_SEED: &str = "aafd12f659cae63489b479e5076ddec2f06cb58f";
+    const OAEP_ENCODED: &str = concat!(
+        "00eb7a19ace9e3006350e329504b45e2ca82310b26dcd87d5c68f1eea8f55267",
+        "c31b2e8bb4251f84d7e0b2c04626f5aff93edcfb25c9c2b3ff8ae10e839a2ddb",
+        "4cdcfe4ff47728b4a1b7c1362baad29ab48d2869d5024121435811591be392f9",
+        "82fb3e87d095aeb40448db972f3ac14f7bc275195281ce32d2f1b76d4d353e2d"
+    );
+
+    const PSS_MESSAGE: &str = concat!(
+        "859eef2fd78aca00308bdc471193bf55bf9d78db8f8a672b484634f3c9c26e64",
+        "78ae10260fe0dd8c082e53a5293af2173cd50c6d5d354febf78b26021c25c027",
+        "12e78cd4694c9f469777e451e7f8e9e04cd3739c6bbfedae487fb55644e9ca74",
+        "ff77a53cb729802f6ed4a5ffa8ba159890fc"
+    );
+    const PSS_SALT: &str = "e3b5d5d002c1bce50c2b65ef88a188d83bce7e61";
+    const PSS_ENCODED: &str = concat!(
+        "66e4672e836ad121ba244bed6576b867d9a447c28a6e66a5b87dee7fbc7e65af",
+        "5057f86fae8984d9ba7f969ad6fe02a4d75f7445fefdd85b6d3a477c28d24ba1",
+        "e3756f792dd1dce8ca94440ecb5279ecd3183a311fc896da1cb39311af37ea4a",
+        "75e24bdbfd5c1da0de7cecdf1a896f9d8bc816d97cd7a2c43bad546fbe8cfebc"
+    );
+
+    fn mgf1_hex<D: Digest>(seed: &[u8], length: usize) -> String {
+        let mut mask = vec![0u8; length];
+        mgf1::<D>(seed, &mut mask);
+        bytes_to_hex(&mask)
+    }
+
+    #[test]
+    fn test_mgf1() {
+        assert_eq!("1ac907", mgf1_hex::<Sha1>(b"foo", 3));
+        assert_eq!("bc0c655e01", mgf1_hex::<Sha1>(b"bar", 5));
+        assert_eq!(
+            "382576a7841021cc28fc4c0948753fb8312090cea942ea4c4e735d10dc724b155f9f6069f289d61daca0cb814502ef04eae1",
+            mgf1_hex::<Sha256>(b"bar", 50)
+        );
+    }
+
+    #[test]
+    fn test_oaep() {
+        assert_eq!(
+            Ok(hex_to_bytes(OAEP_ENCODED).unwrap()),
+            oaep_encode::<Sha1>(
+                &hex_to_bytes(OAEP_MESSAGE).unwrap(),
+                b"",
+                &hex_to_bytes(OAEP_SEED).unwrap(),
+                128
+            )
+        );
+        assert_eq!(
+            Ok(hex_to_bytes(OAEP_MESSAGE).unwrap()),
+            oaep_decode::<Sha1>(&hex_to_bytes(OAEP_ENCODED).unwrap(), b"")
+        );
+        assert_eq!(
+            Err(Pkcs1Error::DecryptionError),
+            oaep_decode::<Sha1>(&hex_to_bytes(OAEP_ENCODED).unwrap(), b"some label")
+        );
+
+        let encoded = oaep_encode::<Sha256>(b"secret", b"label", &[7; 32], 128).unwrap();
+        assert_eq!(128, encoded.len());
+        assert_eq!(
+            Ok(b"secret".to_vec()),
+            oaep_decode::<Sha256>(&encoded, b"label")
+        );
+
+        let mut damaged = encoded.clone();
+        damaged[100] ^= 1;
+        assert_eq!(
+            Err(Pkcs1Error::DecryptionError),
+            oaep_decode::<Sha256>(&damaged, b"label")
+        );
+
+        // the longest message that fits, and one byte more
+        assert!(oaep_encode::<Sha256>(&[0; 62], b"", &[7; 32], 128).is_ok());
+        assert_eq!(
+            Err(Pkcs1Error::MessageTooLong {
+                length: 63,
+                maximum: 62
+            }),
+            oaep_encode::<Sha256>(&[0; 63], b"", &[7; 32], 128)
+        );
+        assert_eq!(
+            Err(Pkcs1Error::InvalidSeedLength {
+                length: 20,
+                expected: 32
+            }),
+            oaep_encode::<Sha256>(b"secret", b"", &[7; 20], 128)
+        );
+    }
+
+    #[test]
+    fn test_pss() {
+        let message = hex_to_bytes(PSS_MESSAGE).unwrap();
+        assert_eq!(
+            Ok(hex_to_bytes(PSS_ENCODED).unwrap()),
+            pss_encode::<Sha1>(&message, &hex_to_bytes(PSS_SALT).unwrap(), 1023)
+        );
+        assert_eq!(
+            Ok(()),
+            pss_verify::<Sha1>(&message, &hex_to_bytes(PSS_ENCODED).unwrap(), 1023, 20)
+        );
+        assert_eq!(
+            Err(Pkcs1Error::Inconsistent),
+            pss_verify::<Sha1>(&message[1..], &hex_to_bytes(PSS_ENCODED).unwrap(), 1023, 20)
+        );
+
+        // a modulus that isn't a whole number of bytes leaves spare top bits
+        let encoded = pss_encode::<Sha1>(b"message", &[3; 20], 1029).unwrap();
+        assert_eq!(129, encoded.len());
+        assert_eq!(0, encoded[0] & 0xF8);
+        assert_eq!(Ok(()), pss_verify::<Sha1>(b"message", &encoded, 1029, 20));
+        assert_eq!(
+            Err(Pkcs1Error::Inconsistent),
+            pss_verify::<Sha1>(b"message", &encoded, 1029, 16)
+        );
+
+        // with no salt, PSS is deterministic
+        assert_eq!(
+            pss_encode::<Sha256>(b"message", &[], 2047),
+            pss_encode::<Sha256>(b"message", &[], 2047)
+        );
+    }
+
+}
@@ -0,0 +1,466 @@
+//! SHA-1 with collision detection, after Marc Stevens and Dan Shumow's sha1dc
+//! (the hardened SHA-1 that Git and GitHub use).
+//!
+//! Every practical SHA-1 collision so far -- SHAttered in 2017, the
+//! chosen-prefix "SHA-1 is a Shambles" in 2020 -- is built from near-collision
+//! blocks that follow one of a small number of *disturbance vectors*: patterns
+//! of bit differences that pass through the compression function in a
+//! predictable way. That's the weak spot counter-cryptanalysis goes after. For
+//! each block, and for each of the 32 disturbance vectors the known attacks
+//! could use, we ask: "if this block were one half of a near-collision pair
+//! using this vector, what would the other half be, and would it end up with
+//! the same chaining value?" Working that out only takes one recompression
+//! from the middle of the block. If the answer is ever yes, the message is
+//! one half of a collision.
+//!
+//! Normal messages hash exactly as with SHA-1. When an attack is found, the
+//! blocks it's in are compressed twice more, giving the "safe hash": a digest
+//! that the colliding message doesn't share, so it can't be swapped for its
+//! twin. `finalize` gives the safe hash, and `finalize_checked` says whether
+//! there was an attack at all.
+//!
+//! sha1dc also checks "unavoidable bit conditions" first, to skip the vectors
+//! a block can't be using. That check is only a shortcut, and it's left out
+//! here: every vector is tried on every block, which makes this far slower
+//! than plain `Sha1` -- fine for checking signatures and Git objects, not for
+//! hashing gigabytes.
+
+use crate::digest::Digest;
+use crate::utils::{bytes_to_hex, BlockBuffer};
+use crate::{calculate_f, expand_block, get_k, Variant, INITIAL_HASH_STATE};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VectorKind {
+    I,
+    II,
+}
+
+/// One of the disturbance vectors from sha1dc: `I(K, b)` or `II(K, b)`, in the
+/// notation of Manuel's classification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisturbanceVector {
+    pub kind: VectorKind,
+    pub k: usize,
+    pub b: u32,
+    /// The step the recompression starts from: one where the near-collision
+    /// path has no differences in the state.
+    test_step: usize,
+}
+
+const fn vector(kind: VectorKind, k: usize, b: u32, test_step: usize) -> DisturbanceVector {
+    DisturbanceVector {
+        kind,
+        k,
+        b,
+        test_step,
+    }
+}
+
+/// The vectors sha1dc checks, in the same order.
+pub const DISTURBANCE_VECTORS: [DisturbanceVector; 32] = [
+    vector(VectorKind::I, 43, 0, 58),
+    vector(VectorKind::I, 44, 0, 58),
+    vector(VectorKind::I, 45, 0, 58),
+    vector(VectorKind::I, 46, 0, 58),
+    vector(VectorKind::I, 46, 2, 58),
+    vector(VectorKind::I, 47, 0, 58),
+    vector(VectorKind::I, 47, 2, 58),
+    vector(VectorKind::I, 48, 0, 58),
+    vector(VectorKind::I, 48, 2, 58),
+    vector(VectorKind::I, 49, 0, 58),
+    vector(VectorKind::I, 49, 2, 58),
+    vector(VectorKind::I, 50, 0, 65),
+    vector(VectorKind::I, 50, 2, 65),
+    vector(VectorKind::I, 51, 0, 65),
+    vector(VectorKind::I, 51, 2, 65),
+    vector(VectorKind::I, 52, 0, 65),
+    vector(VectorKind::II, 45, 0, 58),
+    vector(VectorKind::II, 46, 0, 58),
+    vector(VectorKind::II, 46, 2, 58),
+    vector(VectorKind::II, 47, 0, 58),
+    vector(VectorKind::II, 48, 0, 58),
+    vector(VectorKind::II, 49, 0, 58),
+    vector(VectorKind::II, 49, 2, 58),
+    vector(VectorKind::II, 50, 0, 65),
+    vector(VectorKind::II, 50, 2, 65),
+    vector(VectorKind::II, 51, 0, 65),
+    vector(VectorKind::II, 51, 2, 65),
+    vector(VectorKind::II, 52, 0, 65),
+    vector(VectorKind::II, 53, 0, 65),
+    vector(VectorKind::II, 54, 0, 65),
+    vector(VectorKind::II, 55, 0, 65),
+    vector(VectorKind::II, 56, 0, 65),
+];
+
+// the disturbance vector runs from step -5 to 79, so it's stored shifted up
+const VECTOR_OFFSET: usize = 5;
+
+/// The message XOR-difference that goes with a disturbance vector.
+///
+/// A disturbance vector is itself a SHA-1 message expansion (just with bit
+/// differences in place of words), so it's fixed by any 16 words in a row:
+/// for `I(K, b)`, words `K` to `K + 15` are zero apart from bit `b` of word
+/// `K + 15`, and `II(K, b)` also has bit `b + 31` of words `K + 1` and `K + 3`.
+/// It's expanded forwards and backwards from there, and each bit of it sets
+/// off a local collision, which needs the differences `DV[t]` in `W[t]`,
+/// `DV[t] <<< 5` in `W[t + 1]`, `DV[t]` in `W[t + 2]`, and `DV[t] <<< 30` in
+/// each of `W[t + 3]` to `W[t + 5]`.
+const fn message_difference(vector: &DisturbanceVector) -> [u32; 80] {
+    let mut disturbance = [0u32; 80 + VECTOR_OFFSET];
+    let start = vector.k + VECTOR_OFFSET;
+    disturbance[start + 15] = 1u32.rotate_left(vector.b);
+    if let VectorKind::II = vector.kind {
+        disturbance[start + 1] = (1u32 << 31).rotate_left(vector.b);
+        disturbance[start + 3] = (1u32 << 31).rotate_left(vector.b);
+    }
+
+    let mut index = start + 16;
+    while index < disturbance.len() {
+        disturbance[index] = (disturbance[index - 3]
+            ^ disturbance[index - 8]
+            ^ disturbance[index - 14]
+            ^ disturbance[index - 16])
+            .rotate_left(1);
+        index += 1;
+    }
+
+    // the expansion run backwards: W[t] = (W[t + 16] >>> 1) ^ W[t + 13] ^
+    // W[t + 8] ^ W[t + 2]
+    let mut index = start;
+    while index > 0 {
+        index -= 1;
+        disturbance[index] = disturbance[index + 16].rotate_right(1)
+            ^ disturbance[index + 13]
+            ^ disturbance[index + 8]
+            ^ disturbance[index + 2];
+    }
+
+    let mut difference = [0u32; 80];
+    let mut step = 0;
+    while step < 80 {
+        let at = step + VECTOR_OFFSET;
+        difference[step] = disturbance[at]
+            ^ disturbance[at - 1].rotate_left(5)
+            ^ disturbance[at - 2]
+            ^ disturbance[at - 3].rotate_left(30)
+            ^ disturbance[at - 4].rotate_left(30)
+            ^ disturbance[at - 5].rotate_left(30);
+        step += 1;
+    }
+
+    difference
+}
+
+const fn message_differences() -> [[u32; 80]; 32] {
+    let mut differences = [[0u32; 80]; 32];
+    let mut index = 0;
+    while index < DISTURBANCE_VECTORS.len() {
+        differences[index] = message_difference(&DISTURBANCE_VECTORS[index]);
+        index += 1;
+    }
+    differences
+}
+
+const MESSAGE_DIFFERENCES: [[u32; 80]; 32] = message_differences();
+
+// the same step as in `compress`, forwards...
+fn step(state: &mut [u32; 5], index: usize, word: u32) {
+    let [a, b, c, d, e] = *state;
+    let temp = a
+        .rotate_left(5)
+        .wrapping_add(calculate_f(index, b, c, d))
+        .wrapping_add(e)
+        .wrapping_add(get_k(index))
+        .wrapping_add(word);
+    *state = [temp, a, b.rotate_left(30), c, d];
+}
+
+// ...and backwards: from the state after step `index` to the one before it
+fn step_back(state: &mut [u32; 5], index: usize, word: u32) {
+    let [temp, a, c, d, e] = *state;
+    let b = c.rotate_right(30);
+    let e_before = temp.wrapping_sub(
+        a.rotate_left(5)
+            .wrapping_add(calculate_f(index, b, d, e))
+            .wrapping_add(get_k(index))
+            .wrapping_add(word),
+    );
+    *state = [a, b, d, e, e_before];
+}
+
+fn add_into(hash_state: &mut [u32; 5], state: &[u32; 5]) {
+    for (value, addition) in hash_state.iter_mut().zip(state.iter()) {
+        *value = value.wrapping_add(*addition);
+    }
+}
+
+/// Compresses one block, and checks whether it's half of a near-collision.
+fn compress_checked(hash_state: &mut [u32; 5], block: &[u8; 64]) -> Option<DisturbanceVector> {
+    let words = block
+        .chunks_exact(4)
+        .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+        .collect::<Vec<u32>>();
+    let expanded = expand_block(words, Variant::Sha1);
+
+    // the state going into each step
+    let mut states = [[0u32; 5]; 80];
+    let mut state = *hash_state;
+    for (index, word) in expanded.iter().enumerate() {
+        states[index] = state;
+        step(&mut state, index, *word);
+    }
+    add_into(hash_state, &state);
+
+    let found = DISTURBANCE_VECTORS
+        .iter()
+        .zip(MESSAGE_DIFFERENCES.iter())
+        .find(|(vector, difference)| {
+            let other = expanded
+                .iter()
+                .zip(difference.iter())
+                .map(|(word, difference)| word ^ difference)
+                .collect::<Vec<u32>>();
+
+            // the other block has the same state at the test step, so run
+            // it back to where it must have started and forward to where it
+            // ends up
+            let mut other_input = states[vector.test_step];
+            for index in (0..vector.test_step).rev() {
+                step_back(&mut other_input, index, other[index]);
+            }
+            let mut other_output = states[vector.test_step];
+            for (index, word) in other.iter().enumerate().skip(vector.test_step) {
+                step(&mut other_output, index, *word);
+            }
+            add_into(&mut other_input, &other_output);
+
+            other_input == *hash_state
+        })
+        .map(|(vector, _)| *vector);
+
+    if found.is_some() {
+        // the safe hash: two more rounds through the same block
+        for _ in 0..2 {
+            let mut state = *hash_state;
+            for (index, word) in expanded.iter().enumerate() {
+                step(&mut state, index, *word);
+            }
+            add_into(hash_state, &state);
+        }
+    }
+
+    found
+}
+
+/// What `finalize_checked` reports for a message that's half of a collision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionDetected {
+    /// Which 64-byte block of the message (counting the padding) the first
+    /// near-collision was found in.
+    pub block: u64,
+    pub vector: DisturbanceVector,
+    /// The digest the message gets instead of its real SHA-1.
+    pub safe_hash: [u8; 20],
+}
+
+impl fmt::Display for CollisionDetected {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "SHA-1 collision attack detected in block {} (disturbance vector {:?}({}, {}))",
+            self.block, self.vector.kind, self.vector.k, self.vector.b
+        )
+    }
+}
+
+impl std::error::Error for CollisionDetected {}
+
+/// SHA-1 that notices when it's hashing a collision attack.
+#[derive(Clone)]
+pub struct HardenedSha1 {
+    hash_state: [u32; 5],
+    buffer: BlockBuffer<64>,
+    blocks: u64,
+    detected: Option<(u64, DisturbanceVector)>,
+}
+
+impl Default for HardenedSha1 {
+    fn default() -> Self {
+        HardenedSha1 {
+            hash_state: INITIAL_HASH_STATE,
+            buffer: BlockBuffer::default(),
+            blocks: 0,
+            detected: None,
+        }
+    }
+}
+
+impl HardenedSha1 {
+    fn compress_block(
+        hash_state: &mut [u32; 5],
+        blocks: &mut u64,
+        detected: &mut Option<(u64, DisturbanceVector)>,
+        block: &[u8; 64],
+    ) {
+        if let Some(vector) = compress_checked(hash_state, block) {
+            detected.get_or_insert((*blocks, vector));
+        }
+        *blocks += 1;
+    }
+
+    /// The SHA-1 of the message, or the details of the attack if it's half of
+    /// a collision.
+    pub fn finalize_checked(mut self) -> Result<[u8; 20], CollisionDetected> {
+        let HardenedSha1 {
+            hash_state,
+            buffer,
+            blocks,
+            detected,
+        } = &mut self;
+        buffer.finish(false, |block| {
+            HardenedSha1::compress_block(hash_state, blocks, detected, block)
+        });
+
+        let mut output = [0u8; 20];
+        for (bytes, value) in output.chunks_exact_mut(4).zip(hash_state.iter()) {
+            bytes.copy_from_slice(&value.to_be_bytes());
+        }
+
+        match self.detected {
+            None => Ok(output),
+            Some((block, vector)) => Err(CollisionDetected {
+                block,
+                vector,
+                safe_hash: output,
+            }),
+        }
+    }
+}
+
+impl Digest for HardenedSha1 {
+    const BLOCK_LENGTH: usize = 64;
+    const OUTPUT_LENGTH: usize = 20;
+
+    type Output = [u8; 20];
+
+    fn update(&mut self, bytes: &[u8]) {
+        let HardenedSha1 {
+            hash_state,
+            buffer,
+            blocks,
+            detected,
+        } = self;
+        buffer.update(bytes, |block| {
+            HardenedSha1::compress_block(hash_state, blocks, detected, block)
+        });
+    }
+
+    /// The SHA-1 of the message -- or, if it's half of a collision, the safe
+    /// hash.
+    fn finalize(self) -> Self::Output {
+        self.finalize_checked()
+            .unwrap_or_else(|detected| detected.safe_hash)
+    }
+}
+
+pub fn hardened_sha1(raw_message: &str) -> String {
+    bytes_to_hex(&HardenedSha1::digest(raw_message.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hex_to_bytes;
+    use crate::{sha1, Sha1};
+
+    // the first 320 bytes of shattered-1.pdf: a PDF header, then the two
+    // near-collision blocks from the SHAttered attack
+    const SHATTERED_1: &str = concat!(
+        "255044462d312e330a25e2e3cfd30a0a0a312030206f626a0a3c3c2f57696474",
+        "682032203020522f4865696768742033203020522f547970652034203020522f",
+        "537562747970652035203020522f46696c7465722036203020522f436f6c6f72",
+        "53706163652037203020522f4c656e6774682038203020522f42697473506572",
+        "436f6d706f6e656e7420383e3e0a73747265616d0affd8fffe00245348412d31",
+        "20697320646561642121212121852fec092339759c39b1a1c63c4c97e1fffe01",
+        "7346dc9166b67e118f029ab621b2560ff9ca67cca8c7f85ba84c79030c2b3de2",
+        "18f86db3a90901d5df45c14f26fedfb3dc38e96ac22fe7bd728f0e45bce046d2",
+        "3c570feb141398bb552ef5a0a82be331fea48037b8b5d71f0e332edf93ac3500",
+        "eb4ddc0decc1a864790c782c76215660dd309791d06bd0af3f98cda4bc4629b1",
+    );
+
+    #[test]
+    fn test_message_differences() {
+        // the first words of sha1dc's tables for I(43, 0), I(46, 2) and
+        // II(45, 0)
+        assert_eq!(
+            [0x08000000, 0x9800000c, 0xd8000010, 0x08000010],
+            MESSAGE_DIFFERENCES[0][..4]
+        );
+        assert_eq!(vector(VectorKind::I, 46, 2, 58), DISTURBANCE_VECTORS[4]);
+        assert_eq!(
+            [0xb0000040, 0xd0000053, 0xd0000022, 0x20000000],
+            MESSAGE_DIFFERENCES[4][..4]
+        );
+        assert_eq!(VectorKind::II, DISTURBANCE_VECTORS[16].kind);
+        assert_eq!(
+            [0xec000014, 0x0c000002, 0xc0000010, 0xb400001c],
+            MESSAGE_DIFFERENCES[16][..4]
+        );
+
+        for (index, vector) in DISTURBANCE_VECTORS.iter().enumerate() {
+            assert!(!DISTURBANCE_VECTORS[..index].contains(vector));
+        }
+    }
+
+    #[test]
+    fn test_normal_messages() {
+        // with no attack, it's just SHA-1
+        for message in ["", "abc", "The quick brown fox jumps over the lazy dog"].iter() {
+            assert_eq!(sha1(message), hardened_sha1(message));
+        }
+
+        let message = vec![0x5a; 1000];
+        let mut hasher = HardenedSha1::default();
+        for piece in message.chunks(77) {
+            hasher.update(piece);
+        }
+        assert_eq!(Ok(Sha1::digest(&message)), hasher.finalize_checked());
+    }
+
+    #[test]
+    fn test_shattered() {
+        let first = hex_to_bytes(SHATTERED_1).unwrap();
+
+        // the other PDF differs only in the two near-collision blocks, by
+        // exactly the message difference of the vector the attack used
+        let mut second = first.clone();
+        for block in second[192..320].chunks_exact_mut(64) {
+            for (bytes, difference) in block
+                .chunks_exact_mut(4)
+                .zip(MESSAGE_DIFFERENCES[27].iter())
+            {
+                for (byte, difference) in bytes.iter_mut().zip(difference.to_be_bytes().iter()) {
+                    *byte ^= difference;
+                }
+            }
+        }
+        assert_ne!(first, second);
+        assert_eq!(Sha1::digest(&first), Sha1::digest(&second));
+
+        for message in [&first, &second].iter() {
+            let detected = HardenedSha1::digest(message);
+            let mut hasher = HardenedSha1::default();
+            hasher.update(message);
+            let error = hasher.finalize_checked().unwrap_err();
+
+            // the first block only gets near a collision; it's the second
+            // that brings the chaining values back together
+            assert_eq!(4, error.block);
+            assert_eq!(vector(VectorKind::II, 52, 0, 65), error.vector);
+            assert_eq!(detected, error.safe_hash);
+            assert_ne!(Sha1::digest(message), detected);
+        }
+    }
+
+}
@@ -1,7 +1,10 @@
 // #![allow(clippy::unreadable_literal)]
 
+use crate::digest::Digest;
+use crate::utils::bytes_to_hex;
 use crate::utils::preprocess;
 use crate::utils::to_hex_string;
+use crate::utils::BlockBuffer;
 use crate::utils::BLOCK_SIZE;
 
 const ROUND_CONSTANTS: [u32; 64] = [
@@ -19,7 +22,7 @@ fn get_upcoming_block(chunk: &[char]) -> Vec<u32> {
     // chunks_exact is on a slice and returns slices
     // but then we gather those up into a vec
     // which is how we end up with Vec<&[char]>
-    let mut block_units: Vec<u32> = chunk
+    let block_units: Vec<u32> = chunk
         .chunks_exact(32)
         .map(|block| {
             let int_bits = block.iter().collect::<String>();
@@ -27,6 +30,10 @@ fn get_upcoming_block(chunk: &[char]) -> Vec<u32> {
         })
         .collect();
 
+    expand_block(block_units)
+}
+
+fn expand_block(mut block_units: Vec<u32>) -> Vec<u32> {
     assert_eq!(16, block_units.len());
 
     // here we differ from Sha1
@@ -55,67 +62,73 @@ fn get_upcoming_block(chunk: &[char]) -> Vec<u32> {
     upcoming_block
 }
 
+const INITIAL_HASH_STATE: [u32; 8] = [
+    0x6a09e667u32,
+    0xbb67ae85u32,
+    0x3c6ef372u32,
+    0xa54ff53au32,
+    0x510e527fu32,
+    0x9b05688cu32,
+    0x1f83d9abu32,
+    0x5be0cd19u32,
+];
+
+fn compress(hash_state: &mut [u32; 8], upcoming_block: &[u32]) {
+    let mut a: u32 = hash_state[0];
+    let mut b: u32 = hash_state[1];
+    let mut c: u32 = hash_state[2];
+    let mut d: u32 = hash_state[3];
+    let mut e: u32 = hash_state[4];
+    let mut f: u32 = hash_state[5];
+    let mut g: u32 = hash_state[6];
+    let mut h: u32 = hash_state[7];
+
+    for (index, current_item) in upcoming_block.iter().enumerate() {
+        let constant_k = ROUND_CONSTANTS[index];
+
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+
+        let temp1: u32 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(constant_k)
+            .wrapping_add(*current_item);
+
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+
+        let temp2: u32 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+
+    hash_state[0] = hash_state[0].wrapping_add(a);
+    hash_state[1] = hash_state[1].wrapping_add(b);
+    hash_state[2] = hash_state[2].wrapping_add(c);
+    hash_state[3] = hash_state[3].wrapping_add(d);
+    hash_state[4] = hash_state[4].wrapping_add(e);
+    hash_state[5] = hash_state[5].wrapping_add(f);
+    hash_state[6] = hash_state[6].wrapping_add(g);
+    hash_state[7] = hash_state[7].wrapping_add(h);
+}
+
 pub fn sha256(raw_message: &str) -> String {
-    let mut hash_state: [u32; 8] = [
-        0x6a09e667u32,
-        0xbb67ae85u32,
-        0x3c6ef372u32,
-        0xa54ff53au32,
-        0x510e527fu32,
-        0x9b05688cu32,
-        0x1f83d9abu32,
-        0x5be0cd19u32,
-    ];
+    let mut hash_state: [u32; 8] = INITIAL_HASH_STATE;
 
     let message = preprocess(raw_message.to_string());
     let blocks = message.chars().collect::<Vec<char>>();
 
     for upcoming_block in blocks.chunks_exact(BLOCK_SIZE).map(&get_upcoming_block) {
-        let mut a: u32 = hash_state[0];
-        let mut b: u32 = hash_state[1];
-        let mut c: u32 = hash_state[2];
-        let mut d: u32 = hash_state[3];
-        let mut e: u32 = hash_state[4];
-        let mut f: u32 = hash_state[5];
-        let mut g: u32 = hash_state[6];
-        let mut h: u32 = hash_state[7];
-
-        for (index, current_item) in upcoming_block.iter().enumerate() {
-            let constant_k = ROUND_CONSTANTS[index];
-
-            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
-            let ch = (e & f) ^ (!e & g);
-
-            let temp1: u32 = h
-                .wrapping_add(s1)
-                .wrapping_add(ch)
-                .wrapping_add(constant_k)
-                .wrapping_add(*current_item);
-
-            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
-
-            let maj = (a & b) ^ (a & c) ^ (b & c);
-
-            let temp2: u32 = s0.wrapping_add(maj);
-
-            h = g;
-            g = f;
-            f = e;
-            e = d.wrapping_add(temp1);
-            d = c;
-            c = b;
-            b = a;
-            a = temp1.wrapping_add(temp2);
-        }
-
-        hash_state[0] = hash_state[0].wrapping_add(a);
-        hash_state[1] = hash_state[1].wrapping_add(b);
-        hash_state[2] = hash_state[2].wrapping_add(c);
-        hash_state[3] = hash_state[3].wrapping_add(d);
-        hash_state[4] = hash_state[4].wrapping_add(e);
-        hash_state[5] = hash_state[5].wrapping_add(f);
-        hash_state[6] = hash_state[6].wrapping_add(g);
-        hash_state[7] = hash_state[7].wrapping_add(h);
+        compress(&mut hash_state, &upcoming_block);
     }
 
     hash_state
@@ -124,6 +137,324 @@ pub fn sha256(raw_message: &str) -> String {
         .collect::<String>()
 }
 
+/// SHA-256 over bytes, fed in a piece at a time. `sha256` is the same hash
+/// for a whole `&str` at once.
+#[derive(Clone)]
+pub struct Sha256 {
+    hash_state: [u32; 8],
+    buffer: BlockBuffer<64>,
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Sha256 {
+            hash_state: INITIAL_HASH_STATE,
+            buffer: BlockBuffer::default(),
+        }
+    }
+}
+
+impl Sha256 {
+    /// The same as `Sha1::from_state`, with SHA-256's eight words of state.
+    pub fn from_state(hash_state: [u32; 8], length: u64) -> Self {
+        Sha256 {
+            hash_state,
+            buffer: BlockBuffer::resume(length),
+        }
+    }
+
+    fn compress_block(hash_state: &mut [u32; 8], block: &[u8; 64]) {
+        let block_units = block
+            .chunks_exact(4)
+            .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+            .collect::<Vec<u32>>();
+
+        compress(hash_state, &expand_block(block_units));
+    }
+}
+
+impl Digest for Sha256 {
+    const BLOCK_LENGTH: usize = 64;
+    const OUTPUT_LENGTH: usize = 32;
+
+    type Output = [u8; 32];
+
+    fn update(&mut self, bytes: &[u8]) {
+        let Sha256 { hash_state, buffer } = self;
+        buffer.update(bytes, |block| Sha256::compress_block(hash_state, block));
+    }
+
+    fn finalize(mut self) -> Self::Output {
+        let Sha256 { hash_state, buffer } = &mut self;
+        buffer.finish(false, |block| Sha256::compress_block(hash_state, block));
+
+        let mut output = [0u8; 32];
+        for (bytes, value) in output.chunks_exact_mut(4).zip(hash_state.iter()) {
+            bytes.copy_from_slice(&value.to_be_bytes());
+        }
+
+        output
+    }
+}
+
+// SHA-512 is the same design with 64-bit words: 80 rounds instead of 64,
+// 128-byte blocks, and its own rotation amounts and constants.
+
+const SHA512_ROUND_CONSTANTS: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+const SHA512_INITIAL_HASH_STATE: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+fn sha512_expand_block(block: &[u8; 128]) -> [u64; 80] {
+    let mut upcoming_block = [0u64; 80];
+    for (word, bytes) in upcoming_block.iter_mut().zip(block.chunks_exact(8)) {
+        let mut word_bytes = [0u8; 8];
+        word_bytes.copy_from_slice(bytes);
+        *word = u64::from_be_bytes(word_bytes);
+    }
+
+    for index in 16..80 {
+        let w_min15 = upcoming_block[index - 15];
+        let w_min2 = upcoming_block[index - 2];
+
+        let s0 = w_min15.rotate_right(1) ^ w_min15.rotate_right(8) ^ (w_min15 >> 7);
+        let s1 = w_min2.rotate_right(19) ^ w_min2.rotate_right(61) ^ (w_min2 >> 6);
+
+        upcoming_block[index] = upcoming_block[index - 16]
+            .wrapping_add(s0)
+            .wrapping_add(upcoming_block[index - 7])
+            .wrapping_add(s1);
+    }
+
+    upcoming_block
+}
+
+fn sha512_compress(hash_state: &mut [u64; 8], block: &[u8; 128]) {
+    let mut working = *hash_state;
+
+    for (constant_k, current_item) in SHA512_ROUND_CONSTANTS
+        .iter()
+        .zip(sha512_expand_block(block).iter())
+    {
+        let [a, b, c, d, e, f, g, h] = working;
+
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ (!e & g);
+        let temp1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*constant_k)
+            .wrapping_add(*current_item);
+
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+
+        working = [
+            temp1.wrapping_add(temp2),
+            a,
+            b,
+            c,
+            d.wrapping_add(temp1),
+            e,
+            f,
+            g,
+        ];
+    }
+
+    for (value, addition) in hash_state.iter_mut().zip(working.iter()) {
+        *value = value.wrapping_add(*addition);
+    }
+}
+
+/// SHA-512, fed bytes a piece at a time like `Sha256`.
+#[derive(Clone)]
+pub struct Sha512 {
+    hash_state: [u64; 8],
+    buffer: BlockBuffer<128>,
+}
+
+impl Default for Sha512 {
+    fn default() -> Self {
+        Sha512 {
+            hash_state: SHA512_INITIAL_HASH_STATE,
+            buffer: BlockBuffer::default(),
+        }
+    }
+}
+
+impl Sha512 {
+    fn finish(mut self) -> [u8; 64] {
+        let Sha512 { hash_state, buffer } = &mut self;
+        buffer.finish(false, |block| sha512_compress(hash_state, block));
+
+        let mut output = [0u8; 64];
+        for (bytes, value) in output.chunks_exact_mut(8).zip(hash_state.iter()) {
+            bytes.copy_from_slice(&value.to_be_bytes());
+        }
+
+        output
+    }
+}
+
+impl Digest for Sha512 {
+    const BLOCK_LENGTH: usize = 128;
+    const OUTPUT_LENGTH: usize = 64;
+
+    type Output = [u8; 64];
+
+    fn update(&mut self, bytes: &[u8]) {
+        let Sha512 { hash_state, buffer } = self;
+        buffer.update(bytes, |block| sha512_compress(hash_state, block));
+    }
+
+    fn finalize(self) -> Self::Output {
+        self.finish()
+    }
+}
+
+// FIPS 180-4, section 5.3.6.2: SHA-512 with the initial state run through
+// its own "SHA-512/t IV generation function"
+const SHA512_256_INITIAL_HASH_STATE: [u64; 8] = [
+    0x22312194fc2bf72c,
+    0x9f555fa3c84c64c2,
+    0x2393b86b6f53b151,
+    0x963877195940eabd,
+    0x96283ee2a88effe3,
+    0xbe5e1e2553863992,
+    0x2b0199fc2c85b8aa,
+    0x0eb72ddc81c52ca2,
+];
+
+/// SHA-512/256: SHA-512 with a different starting state, cut to 256 bits.
+/// It's faster than SHA-256 on 64-bit machines, and (being truncated) not
+/// open to length extension.
+#[derive(Clone)]
+pub struct Sha512_256(Sha512);
+
+impl Default for Sha512_256 {
+    fn default() -> Self {
+        Sha512_256(Sha512 {
+            hash_state: SHA512_256_INITIAL_HASH_STATE,
+            buffer: BlockBuffer::default(),
+        })
+    }
+}
+
+impl Digest for Sha512_256 {
+    const BLOCK_LENGTH: usize = 128;
+    const OUTPUT_LENGTH: usize = 32;
+
+    type Output = [u8; 32];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self) -> Self::Output {
+        let mut output = [0u8; 32];
+        output.copy_from_slice(&self.0.finish()[..32]);
+        output
+    }
+}
+
+pub fn sha512(raw_message: &str) -> String {
+    bytes_to_hex(&Sha512::digest(raw_message.as_bytes()))
+}
+
+pub fn sha512_256(raw_message: &str) -> String {
+    bytes_to_hex(&Sha512_256::digest(raw_message.as_bytes()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,4 +472,53 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_sha256_streaming() {
+        let message = "The quick brown fox jumps over the lazy dog".repeat(5);
+
+        let mut hasher = Sha256::default();
+        for piece in message.as_bytes().chunks(13) {
+            hasher.update(piece);
+        }
+
+        let expected = "7934ac96e6ebd24d26c21c2c423baf37dd2beb2fff683a0e1e6148f7e30a291f";
+        assert_eq!(expected, sha256(&message));
+        assert_eq!(expected, crate::utils::bytes_to_hex(&hasher.finalize()));
+    }
+
+    #[test]
+    fn test_sha512() {
+        assert_eq!(
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e",
+            sha512("")
+        );
+        assert_eq!(
+            "07e547d9586f6a73f73fbac0435ed76951218fb7d0c8d788a309d785436bbb642e93a252a954f23912547d1e8a3b5ed6e1bfd7097821233fa0538f3db854fee6",
+            sha512("The quick brown fox jumps over the lazy dog")
+        );
+
+        // 112 bytes, so the padding spills into a second block
+        let message = "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+        let mut hasher = Sha512::default();
+        for piece in message.as_bytes().chunks(7) {
+            hasher.update(piece);
+        }
+        assert_eq!(
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909",
+            bytes_to_hex(&hasher.finalize())
+        );
+    }
+
+    #[test]
+    fn test_sha512_256() {
+        assert_eq!(
+            "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+            sha512_256("abc")
+        );
+        assert_eq!(
+            "c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a",
+            sha512_256("")
+        );
+    }
+
 }
@@ -0,0 +1,297 @@
+//! SipHash, a keyed hash for hash tables.
+//!
+//! A hash table whose keys come from outside (HTTP headers, JSON object keys)
+//! can be attacked by sending lots of keys that all land in the same bucket.
+//! SipHash fixes that by mixing a secret 128-bit key into every hash: without
+//! the key there's no way to know which inputs collide. Unlike std's
+//! `DefaultHasher`, which is allowed to change between Rust releases, the
+//! algorithm here is pinned down, so the same key and input always give the
+//! same hash.
+//!
+//! SipHash-c-d does `c` rounds per 8-byte word of message and `d` rounds at
+//! the end. SipHash-2-4 is the original recommendation; SipHash-1-3 is
+//! faster, and is what Rust's own `HashMap` uses.
+
+use std::hash::{BuildHasher, Hasher};
+
+#[derive(Clone)]
+struct SipState {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    // message bytes that don't make up a whole word yet, little endian
+    tail: u64,
+    tail_length: usize,
+    length: u64,
+}
+
+impl SipState {
+    fn new(key: &[u8; 16], wide: bool) -> Self {
+        let mut k0 = [0u8; 8];
+        let mut k1 = [0u8; 8];
+        k0.copy_from_slice(&key[..8]);
+        k1.copy_from_slice(&key[8..]);
+        let k0 = u64::from_le_bytes(k0);
+        let k1 = u64::from_le_bytes(k1);
+
+        // "somepseudorandomlygeneratedbytes"
+        let mut state = SipState {
+            v0: k0 ^ 0x736f6d6570736575,
+            v1: k1 ^ 0x646f72616e646f6d,
+            v2: k0 ^ 0x6c7967656e657261,
+            v3: k1 ^ 0x7465646279746573,
+            tail: 0,
+            tail_length: 0,
+            length: 0,
+        };
+
+        if wide {
+            state.v1 ^= 0xee;
+        }
+
+        state
+    }
+
+    fn sip_round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, word: u64, rounds: usize) {
+        self.v3 ^= word;
+        for _ in 0..rounds {
+            self.sip_round();
+        }
+        self.v0 ^= word;
+    }
+
+    fn update(&mut self, bytes: &[u8], rounds: usize) {
+        self.length += bytes.len() as u64;
+
+        for byte in bytes {
+            self.tail |= u64::from(*byte) << (8 * self.tail_length);
+            self.tail_length += 1;
+
+            if self.tail_length == 8 {
+                let word = self.tail;
+                self.compress(word, rounds);
+                self.tail = 0;
+                self.tail_length = 0;
+            }
+        }
+    }
+
+    fn xor_lanes(&self) -> u64 {
+        self.v0 ^ self.v1 ^ self.v2 ^ self.v3
+    }
+
+    // the last word holds whatever bytes are left, with the low byte of the
+    // message length in its top byte
+    fn finish(&self, compression_rounds: usize, finalization_rounds: usize, wide: bool) -> u128 {
+        let mut state = self.clone();
+
+        let last_word = ((self.length & 0xff) << 56) | self.tail;
+        state.compress(last_word, compression_rounds);
+
+        state.v2 ^= if wide { 0xee } else { 0xff };
+        for _ in 0..finalization_rounds {
+            state.sip_round();
+        }
+        let first = state.xor_lanes();
+
+        if !wide {
+            return u128::from(first);
+        }
+
+        state.v1 ^= 0xdd;
+        for _ in 0..finalization_rounds {
+            state.sip_round();
+        }
+        let second = state.xor_lanes();
+
+        (u128::from(second) << 64) | u128::from(first)
+    }
+}
+
+/// SipHash-c-d with a 64-bit output. This is also a `std::hash::Hasher`, so
+/// it can go straight into a `HashMap` through `SipBuildHasher`.
+#[derive(Clone)]
+pub struct SipHasher<const C: usize, const D: usize> {
+    state: SipState,
+}
+
+pub type SipHasher24 = SipHasher<2, 4>;
+pub type SipHasher13 = SipHasher<1, 3>;
+
+impl<const C: usize, const D: usize> SipHasher<C, D> {
+    pub fn new(key: &[u8; 16]) -> Self {
+        SipHasher {
+            state: SipState::new(key, false),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.state.update(bytes, C);
+    }
+
+    pub fn value(&self) -> u64 {
+        self.state.finish(C, D, false) as u64
+    }
+
+    /// The hash as the 8 bytes the reference implementation outputs (that is,
+    /// little endian).
+    pub fn finalize(self) -> [u8; 8] {
+        self.value().to_le_bytes()
+    }
+}
+
+impl<const C: usize, const D: usize> Hasher for SipHasher<C, D> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.value()
+    }
+}
+
+/// SipHash-c-d with a 128-bit output, for when 64 bits isn't enough to keep
+/// accidental collisions away (content-addressed caches and the like).
+#[derive(Clone)]
+pub struct SipHasher128<const C: usize, const D: usize> {
+    state: SipState,
+}
+
+pub type SipHasher24_128 = SipHasher128<2, 4>;
+pub type SipHasher13_128 = SipHasher128<1, 3>;
+
+impl<const C: usize, const D: usize> SipHasher128<C, D> {
+    pub fn new(key: &[u8; 16]) -> Self {
+        SipHasher128 {
+            state: SipState::new(key, true),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.state.update(bytes, C);
+    }
+
+    /// The two 64-bit halves of the output, with the first one in the low
+    /// bits.
+    pub fn value(&self) -> u128 {
+        self.state.finish(C, D, true)
+    }
+
+    pub fn finalize(self) -> [u8; 16] {
+        self.value().to_le_bytes()
+    }
+}
+
+/// Makes `SipHasher`s that all share one key, for `HashMap::with_hasher`.
+///
+/// Pick the key at random when the process starts to defend a table against
+/// hostile keys; keep it fixed when hashes need to be reproducible (across
+/// runs or machines).
+#[derive(Clone)]
+pub struct SipBuildHasher<const C: usize, const D: usize> {
+    key: [u8; 16],
+}
+
+pub type SipBuildHasher24 = SipBuildHasher<2, 4>;
+pub type SipBuildHasher13 = SipBuildHasher<1, 3>;
+
+impl<const C: usize, const D: usize> SipBuildHasher<C, D> {
+    pub fn new(key: [u8; 16]) -> Self {
+        SipBuildHasher { key }
+    }
+}
+
+impl<const C: usize, const D: usize> BuildHasher for SipBuildHasher<C, D> {
+    type Hasher = SipHasher<C, D>;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        SipHasher::new(&self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // the test vectors from the reference implementation all use the key
+    // 00 01 02 ... 0f, and messages 00 01 02 ... of increasing length
+    fn reference_key() -> [u8; 16] {
+        let mut key = [0u8; 16];
+        for (index, byte) in key.iter_mut().enumerate() {
+            *byte = index as u8;
+        }
+        key
+    }
+
+    fn reference_message(length: usize) -> Vec<u8> {
+        (0..length as u8).collect()
+    }
+
+    #[test]
+    fn test_siphash24() {
+        let key = reference_key();
+
+        let mut hasher = SipHasher24::new(&key);
+        assert_eq!(
+            [0x31, 0x0e, 0x0e, 0xdd, 0x47, 0xdb, 0x6f, 0x72],
+            hasher.clone().finalize()
+        );
+
+        // the example worked through in the SipHash paper
+        hasher.update(&reference_message(15));
+        assert_eq!(0xa129ca6149be45e5, hasher.value());
+    }
+
+    #[test]
+    fn test_siphash13() {
+        let mut hasher = SipHasher13::new(&reference_key());
+        hasher.update(&reference_message(15));
+        assert_eq!(0xd320d86d2a519956, hasher.value());
+    }
+
+    #[test]
+    fn test_siphash24_128() {
+        let hasher = SipHasher24_128::new(&reference_key());
+        assert_eq!(
+            [
+                0xa3, 0x81, 0x7f, 0x04, 0xba, 0x25, 0xa8, 0xe6, 0x6d, 0xf6, 0x72, 0x14, 0xc7, 0x55,
+                0x02, 0x93
+            ],
+            hasher.finalize()
+        );
+    }
+
+    #[test]
+    fn test_build_hasher() {
+        let build_hasher = SipBuildHasher13::new(reference_key());
+
+        let mut hasher = build_hasher.build_hasher();
+        hasher.write(b"hello");
+        let mut same_key = SipHasher13::new(&reference_key());
+        same_key.update(b"hello");
+        assert_eq!(same_key.value(), hasher.finish());
+
+        let mut map = HashMap::with_hasher(build_hasher);
+        map.insert("key", 1);
+        assert_eq!(Some(&1), map.get("key"));
+    }
+
+}
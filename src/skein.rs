@@ -0,0 +1,336 @@
+//! Skein-256, Skein-512 and Skein-1024.
+//!
+//! Every other hash in this crate is either Merkle–Damgård (a compression
+//! function chained block by block) or a sponge. Skein is neither: it's built
+//! from a *tweakable block cipher*, Threefish, run in a chaining mode called
+//! UBI ("Unique Block Iteration"). Each block of the message is encrypted
+//! under the chaining value as the key, and the result is xored with the
+//! block itself. What makes every block unique is the tweak: it holds how
+//! many bytes have been processed so far, flags for the first and last
+//! block, and the *type* of what's being processed (key, config, message,
+//! output).
+//!
+//! A whole Skein hash is three or four UBI calls in a row:
+//!
+//! 1. (Skein-MAC only) UBI over the key, starting from all zeroes
+//! 2. UBI over a 32-byte configuration block that includes the output length
+//! 3. UBI over the message
+//! 4. UBI over an 8-byte counter, once per block of output needed
+
+use crate::digest::Digest;
+use crate::utils::bytes_to_hex;
+
+mod threefish;
+
+// the tweak's type field, in bits 120-125
+const TYPE_KEY: u64 = 0;
+const TYPE_CONFIG: u64 = 4;
+const TYPE_MESSAGE: u64 = 48;
+const TYPE_OUTPUT: u64 = 63;
+
+const FLAG_FIRST: u64 = 1 << 62;
+const FLAG_FINAL: u64 = 1 << 63;
+
+// "SHA3" read as a little endian u32, followed by version 1
+const SCHEMA_AND_VERSION: u64 = 0x0000_0001_3341_4853;
+
+fn to_words<const WORDS: usize>(block: &[u8]) -> [u64; WORDS] {
+    let mut words = [0u64; WORDS];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(8)) {
+        let mut word_bytes = [0u8; 8];
+        word_bytes.copy_from_slice(bytes);
+        *word = u64::from_le_bytes(word_bytes);
+    }
+    words
+}
+
+/// One run of UBI with a given type, fed a piece at a time.
+///
+/// The last block gets a flag in its tweak, and we can't know a block is the
+/// last until something comes after it -- so a full block is only processed
+/// once there's at least one more byte on the way.
+#[derive(Clone)]
+struct Ubi<const WORDS: usize> {
+    chaining_value: [u64; WORDS],
+    block_type: u64,
+    pending: Vec<u8>,
+    position: u64,
+    first: bool,
+}
+
+impl<const WORDS: usize> Ubi<WORDS> {
+    fn new(chaining_value: [u64; WORDS], block_type: u64) -> Self {
+        Ubi {
+            chaining_value,
+            block_type,
+            pending: Vec::with_capacity(8 * WORDS),
+            position: 0,
+            first: true,
+        }
+    }
+
+    fn process_block(&mut self, is_final: bool) {
+        let block_length = 8 * WORDS;
+        let used = self.pending.len();
+        self.position += used as u64;
+
+        // short blocks (only ever the last one) get padded out with zeroes
+        self.pending.resize(block_length, 0);
+        let block = to_words::<WORDS>(&self.pending);
+        self.pending.clear();
+
+        let mut high = self.block_type << 56;
+        if self.first {
+            high |= FLAG_FIRST;
+        }
+        if is_final {
+            high |= FLAG_FINAL;
+        }
+        let tweak = [self.position, high];
+
+        let encrypted = threefish::encrypt(&self.chaining_value, tweak, &block);
+        for ((value, cipher), plain) in self
+            .chaining_value
+            .iter_mut()
+            .zip(encrypted.iter())
+            .zip(block.iter())
+        {
+            *value = cipher ^ plain;
+        }
+
+        self.first = false;
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            if self.pending.len() == 8 * WORDS {
+                self.process_block(false);
+            }
+            self.pending.push(*byte);
+        }
+    }
+
+    fn finish(mut self) -> [u64; WORDS] {
+        self.process_block(true);
+        self.chaining_value
+    }
+}
+
+fn ubi<const WORDS: usize>(
+    chaining_value: [u64; WORDS],
+    block_type: u64,
+    bytes: &[u8],
+) -> [u64; WORDS] {
+    let mut ubi = Ubi::new(chaining_value, block_type);
+    ubi.update(bytes);
+    ubi.finish()
+}
+
+/// Skein with a `WORDS`-word internal state (4, 8 or 16 words for Skein-256,
+/// -512 and -1024) and any output length, chosen at runtime.
+///
+/// With a key, this is Skein-MAC: the key goes through UBI first, and the
+/// rest of the hash starts from there instead of from zero.
+#[derive(Clone)]
+pub struct Skein<const WORDS: usize> {
+    message: Ubi<WORDS>,
+    output_length: usize,
+}
+
+impl<const WORDS: usize> Skein<WORDS> {
+    /// `output_length` is in bytes.
+    pub fn new(output_length: usize) -> Self {
+        Skein::with_key(&[], output_length)
+    }
+
+    pub fn with_key(key: &[u8], output_length: usize) -> Self {
+        assert!(output_length > 0, "Skein can't produce an empty output");
+
+        let mut chaining_value = [0u64; WORDS];
+        if !key.is_empty() {
+            chaining_value = ubi(chaining_value, TYPE_KEY, key);
+        }
+
+        // bytes 0-7: schema and version; 8-15: output length in bits;
+        // 16-18: tree parameters (all zero for plain sequential hashing)
+        let mut config = [0u8; 32];
+        config[..8].copy_from_slice(&SCHEMA_AND_VERSION.to_le_bytes());
+        config[8..16].copy_from_slice(&(8 * output_length as u64).to_le_bytes());
+        let chaining_value = ubi(chaining_value, TYPE_CONFIG, &config);
+
+        Skein {
+            message: Ubi::new(chaining_value, TYPE_MESSAGE),
+            output_length,
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.message.update(bytes);
+    }
+
+    /// Each block of output is UBI over a counter (0, 1, 2, ...), so there's
+    /// no limit on how much output there can be.
+    pub fn finalize(self) -> Vec<u8> {
+        let output_length = self.output_length;
+        let chaining_value = self.message.finish();
+
+        let mut output = Vec::with_capacity(output_length);
+        let mut counter = 0u64;
+        while output.len() < output_length {
+            let block = ubi(chaining_value, TYPE_OUTPUT, &counter.to_le_bytes());
+            output.extend(block.iter().flat_map(|word| word.to_le_bytes()));
+            counter += 1;
+        }
+
+        output.truncate(output_length);
+        output
+    }
+}
+
+/// Skein with the output length fixed by the type, so it can be used as a
+/// `Digest`.
+#[derive(Clone)]
+pub struct SkeinDigest<const WORDS: usize, const OUTPUT: usize>(Skein<WORDS>);
+
+pub type Skein256 = SkeinDigest<4, 32>;
+pub type Skein512 = SkeinDigest<8, 64>;
+pub type Skein512_256 = SkeinDigest<8, 32>;
+pub type Skein1024 = SkeinDigest<16, 128>;
+
+impl<const WORDS: usize, const OUTPUT: usize> Default for SkeinDigest<WORDS, OUTPUT> {
+    fn default() -> Self {
+        SkeinDigest(Skein::new(OUTPUT))
+    }
+}
+
+impl<const WORDS: usize, const OUTPUT: usize> Digest for SkeinDigest<WORDS, OUTPUT> {
+    const BLOCK_LENGTH: usize = 8 * WORDS;
+    const OUTPUT_LENGTH: usize = OUTPUT;
+
+    type Output = [u8; OUTPUT];
+
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self) -> Self::Output {
+        let mut output = [0u8; OUTPUT];
+        output.copy_from_slice(&self.0.finalize());
+        output
+    }
+}
+
+pub fn skein256(raw_message: &str) -> String {
+    bytes_to_hex(&Skein256::digest(raw_message.as_bytes()))
+}
+
+pub fn skein512(raw_message: &str) -> String {
+    bytes_to_hex(&Skein512::digest(raw_message.as_bytes()))
+}
+
+pub fn skein1024(raw_message: &str) -> String {
+    bytes_to_hex(&Skein1024::digest(raw_message.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threefish() {
+        // all-zero key, tweak and plaintext
+        let encrypted = threefish::encrypt(&[0u64; 4], [0, 0], &[0u64; 4]);
+        let bytes = encrypted
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<u8>>();
+        assert_eq!(
+            "84da2a1f8beaee947066ae3e3103f1ad536db1f4a1192495116b9f3ce6133fd8",
+            bytes_to_hex(&bytes)
+        );
+    }
+
+    #[test]
+    fn test_skein_empty() {
+        assert_eq!(
+            "c8877087da56e072870daa843f176e9453115929094c3a40c463a196c29bf7ba",
+            skein256("")
+        );
+        assert_eq!(
+            "39ccc4554a8b31853b9de7a1fe638a24cce6b35a55f2431009e18780335d2621",
+            bytes_to_hex(&Skein512_256::digest(b""))
+        );
+        assert_eq!(
+            "bc5b4c50925519c290cc634277ae3d6257212395cba733bbad37a4af0fa06af41fca7903d06564fea7a2d3730dbdb80c1f85562dfcc070334ea4d1d9e72cba7a",
+            skein512("")
+        );
+        assert_eq!(
+            "0fff9563bb3279289227ac77d319b6fff8d7e9f09da1247b72a0a265cd6d2a62645ad547ed8193db48cff847c06494a03f55666d3b47eb4c20456c9373c86297d630d5578ebd34cb40991578f9f52b18003efa35d3da6553ff35db91b81ab890bec1b189b7f52cb2a783ebb7d823d725b0b4a71f6824e88f68f982eefc6d19c6",
+            skein1024("")
+        );
+    }
+
+    #[test]
+    fn test_skein_fox() {
+        let fox = "The quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            "b3250457e05d3060b1a4bbc1428bc75a3f525ca389aeab96cfa34638d96e492a",
+            bytes_to_hex(&Skein512_256::digest(fox.as_bytes()))
+        );
+        assert_eq!(
+            "94c2ae036dba8783d0b3f7d6cc111ff810702f5c77707999be7e1c9486ff238a7044de734293147359b4ac7e1d09cd247c351d69826b78dcddd951f0ef912713",
+            skein512(fox)
+        );
+    }
+
+    #[test]
+    fn test_skein_streaming() {
+        // blocks that are exactly full are the tricky ones, since they can't be
+        // processed until we know whether more is coming
+        for length in [63, 64, 65, 128, 300] {
+            let message = "a".repeat(length);
+
+            let mut hasher = Skein512::default();
+            for piece in message.as_bytes().chunks(16) {
+                hasher.update(piece);
+            }
+
+            assert_eq!(skein512(&message), bytes_to_hex(&hasher.finalize()));
+        }
+    }
+
+    #[test]
+    fn test_output_length() {
+        // the output length goes into the config block, so a shorter output is
+        // a different hash, not a prefix of the longer one
+        let short = Skein::<8>::new(32).finalize();
+        assert_eq!(Skein512_256::digest(b"").to_vec(), short);
+        assert_ne!(short[..], Skein512::digest(b"")[..32]);
+
+        let long = Skein::<4>::new(100).finalize();
+        assert_eq!(100, long.len());
+    }
+
+    #[test]
+    fn test_skein_mac() {
+        // only checks that the key is used; there's no known-answer vector
+        // from skein_golden_kat here yet, so a keyed Skein-512-256 KAT still
+        // needs adding
+        let mut mac = Skein::<8>::with_key(b"secret key", 32);
+        mac.update(b"message");
+        let tag = mac.finalize();
+
+        let mut same_key = Skein::<8>::with_key(b"secret key", 32);
+        same_key.update(b"message");
+        assert_eq!(tag, same_key.finalize());
+
+        let mut other_key = Skein::<8>::with_key(b"other key", 32);
+        other_key.update(b"message");
+        assert_ne!(tag, other_key.finalize());
+
+        let mut unkeyed = Skein::<8>::new(32);
+        unkeyed.update(b"message");
+        assert_ne!(tag, unkeyed.finalize());
+    }
+
+}
@@ -0,0 +1,143 @@
+//! Threefish, the tweakable block cipher underneath Skein.
+//!
+//! Threefish works on blocks of 4, 8 or 16 64-bit words (256, 512 or 1024
+//! bits), and the key is the same size as the block. On top of the key it
+//! takes a 128-bit *tweak*, which changes the permutation without the cost of
+//! a new key schedule -- Skein uses it to say where in the message a block
+//! sits, and what kind of data it is.
+//!
+//! There are no S-boxes and no tables. Every round is the same three things:
+//!
+//! * MIX pairs of words: add them, rotate the second one, and xor the sum in
+//! * permute the words, so the next round mixes different pairs
+//! * every fourth round, add in a subkey built from the key and the tweak
+
+// the key schedule's parity constant: the extra key word starts from this
+const C240: u64 = 0x1BD11BDAA9FC1A22;
+
+const ROTATIONS_256: [[u32; 2]; 8] = [
+    [14, 16],
+    [52, 57],
+    [23, 40],
+    [5, 37],
+    [25, 33],
+    [46, 12],
+    [58, 22],
+    [32, 32],
+];
+
+const ROTATIONS_512: [[u32; 4]; 8] = [
+    [46, 36, 19, 37],
+    [33, 27, 14, 42],
+    [17, 49, 36, 39],
+    [44, 9, 54, 56],
+    [39, 30, 34, 24],
+    [13, 50, 10, 17],
+    [25, 29, 39, 43],
+    [8, 35, 56, 22],
+];
+
+const ROTATIONS_1024: [[u32; 8]; 8] = [
+    [24, 13, 8, 47, 8, 17, 22, 37],
+    [38, 19, 10, 55, 49, 18, 23, 52],
+    [33, 4, 51, 13, 34, 41, 59, 17],
+    [5, 20, 48, 41, 47, 28, 16, 25],
+    [41, 9, 37, 31, 12, 47, 44, 30],
+    [16, 34, 56, 51, 4, 53, 42, 41],
+    [31, 44, 47, 46, 19, 42, 44, 25],
+    [9, 48, 35, 52, 23, 31, 37, 20],
+];
+
+// after each round, word `i` is replaced by word `PERMUTATION[i]`
+const PERMUTATION_256: [usize; 4] = [0, 3, 2, 1];
+const PERMUTATION_512: [usize; 8] = [2, 1, 4, 7, 6, 5, 0, 3];
+const PERMUTATION_1024: [usize; 16] = [0, 9, 2, 13, 6, 11, 4, 15, 10, 7, 12, 3, 14, 5, 8, 1];
+
+fn rotation(words: usize, round: usize, pair: usize) -> u32 {
+    match words {
+        4 => ROTATIONS_256[round % 8][pair],
+        8 => ROTATIONS_512[round % 8][pair],
+        16 => ROTATIONS_1024[round % 8][pair],
+        _ => panic!("Threefish only comes in 4, 8 and 16 words, not {}", words),
+    }
+}
+
+fn permutation(words: usize) -> &'static [usize] {
+    match words {
+        4 => &PERMUTATION_256,
+        8 => &PERMUTATION_512,
+        16 => &PERMUTATION_1024,
+        _ => panic!("Threefish only comes in 4, 8 and 16 words, not {}", words),
+    }
+}
+
+fn round_count(words: usize) -> usize {
+    if words == 16 {
+        80
+    } else {
+        72
+    }
+}
+
+/// Encrypt one block. `key` and `block` are words read little endian, and the
+/// tweak is two words, low one first.
+pub fn encrypt<const WORDS: usize>(
+    key: &[u64; WORDS],
+    tweak: [u64; 2],
+    block: &[u64; WORDS],
+) -> [u64; WORDS] {
+    // the key schedule uses one extra key word and one extra tweak word, each
+    // the xor of the others (the key's also gets the constant C240 mixed in)
+    let mut extended_key = [0u64; 17];
+    extended_key[..WORDS].copy_from_slice(key);
+    extended_key[WORDS] = key.iter().fold(C240, |parity, word| parity ^ word);
+
+    let extended_tweak = [tweak[0], tweak[1], tweak[0] ^ tweak[1]];
+
+    let add_subkey = |state: &mut [u64; WORDS], subkey: usize| {
+        for (index, word) in state.iter_mut().enumerate() {
+            let mut value = extended_key[(subkey + index) % (WORDS + 1)];
+
+            if index == WORDS - 3 {
+                value = value.wrapping_add(extended_tweak[subkey % 3]);
+            } else if index == WORDS - 2 {
+                value = value.wrapping_add(extended_tweak[(subkey + 1) % 3]);
+            } else if index == WORDS - 1 {
+                value = value.wrapping_add(subkey as u64);
+            }
+
+            *word = word.wrapping_add(value);
+        }
+    };
+
+    let permutation = permutation(WORDS);
+    let rounds = round_count(WORDS);
+
+    let mut state = *block;
+
+    for round in 0..rounds {
+        if round % 4 == 0 {
+            add_subkey(&mut state, round / 4);
+        }
+
+        for pair in 0..WORDS / 2 {
+            let x0 = state[2 * pair];
+            let x1 = state[2 * pair + 1];
+
+            let y0 = x0.wrapping_add(x1);
+            let y1 = x1.rotate_left(rotation(WORDS, round, pair)) ^ y0;
+
+            state[2 * pair] = y0;
+            state[2 * pair + 1] = y1;
+        }
+
+        let mixed = state;
+        for (index, word) in state.iter_mut().enumerate() {
+            *word = mixed[permutation[index]];
+        }
+    }
+
+    add_subkey(&mut state, rounds / 4);
+
+    state
+}
@@ -0,0 +1,452 @@
+//! The key derivations TLS builds its record keys from.
+//!
+//! TLS 1.0 to 1.2 stretch secrets with a PRF built out of HMAC chains,
+//! `P_hash`: TLS 1.0 and 1.1 XOR together a `P_MD5` and a `P_SHA1`, and TLS
+//! 1.2 uses a single `P_SHA256` (or the hash of the cipher suite). TLS 1.3
+//! switched to HKDF, with every key labelled through `HKDF-Expand-Label` and
+//! the handshake transcript mixed in by `Derive-Secret` (RFC 8446, section 7.1).
+//!
+//! Given a keylog file (the `SSLKEYLOGFILE` format), that's all it takes to
+//! get the record keys: the TLS 1.2 `CLIENT_RANDOM` lines give the master
+//! secret for `key_block`, and the TLS 1.3 `*_TRAFFIC_SECRET` lines go
+//! straight into `traffic_keys`.
+
+use crate::digest::Digest;
+use crate::hkdf::{self, HkdfError};
+use crate::hmac::{hmac, Hmac};
+use crate::md5::Md5;
+use crate::sha2::Sha256;
+use crate::Sha1;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsError {
+    /// The expansion itself failed, asking for more output than HKDF can give.
+    Hkdf(HkdfError),
+    /// An `HkdfLabel` field, the label or the context, was longer than its
+    /// length byte can say.
+    LabelTooLong {
+        field: &'static str,
+        length: usize,
+        maximum: usize,
+    },
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TlsError::Hkdf(error) => error.fmt(formatter),
+            TlsError::LabelTooLong {
+                field,
+                length,
+                maximum,
+            } => write!(
+                formatter,
+                "a TLS 1.3 {} can be at most {} bytes, but this one is {}",
+                field, maximum, length
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TlsError {}
+
+impl From<HkdfError> for TlsError {
+    fn from(error: HkdfError) -> Self {
+        TlsError::Hkdf(error)
+    }
+}
+
+/// `P_hash(secret, seed)` from RFC 5246, section 5: `HMAC(secret, A(i) ||
+/// seed)` for `A(0) = seed` and `A(i) = HMAC(secret, A(i - 1))`, filling all
+/// of `output`.
+pub fn p_hash<D: Digest>(secret: &[u8], seed: &[u8], output: &mut [u8]) {
+    let keyed = Hmac::<D>::new(secret);
+    let mut chain = hmac::<D>(secret, seed);
+
+    for piece in output.chunks_mut(D::OUTPUT_LENGTH) {
+        let mut mac = keyed.clone();
+        mac.update(chain.as_ref());
+        mac.update(seed);
+        piece.copy_from_slice(&mac.finalize().as_ref()[..piece.len()]);
+
+        let mut mac = keyed.clone();
+        mac.update(chain.as_ref());
+        chain = mac.finalize();
+    }
+}
+
+/// Which of the pre-1.3 PRFs a connection uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prf {
+    /// TLS 1.0 and 1.1: `P_MD5` over the first half of the secret XORed
+    /// with `P_SHA1` over the second half. With an odd length, the middle
+    /// byte goes in both halves.
+    Tls10,
+    /// TLS 1.2 with any cipher suite that doesn't ask for a different hash.
+    Tls12Sha256,
+}
+
+impl Prf {
+    /// `PRF(secret, label, seed)`, filling all of `output`.
+    pub fn derive(self, secret: &[u8], label: &[u8], seed: &[u8], output: &mut [u8]) {
+        let labelled_seed = [label, seed].concat();
+        match self {
+            Prf::Tls10 => {
+                let half = secret.len().div_ceil(2);
+                p_hash::<Md5>(&secret[..half], &labelled_seed, output);
+
+                let mut sha1_output = vec![0u8; output.len()];
+                p_hash::<Sha1>(
+                    &secret[secret.len() - half..],
+                    &labelled_seed,
+                    &mut sha1_output,
+                );
+                for (byte, other) in output.iter_mut().zip(sha1_output.iter()) {
+                    *byte ^= other;
+                }
+            }
+            Prf::Tls12Sha256 => p_hash::<Sha256>(secret, &labelled_seed, output),
+        }
+    }
+
+    /// The 48-byte master secret, from the premaster secret the key exchange
+    /// agreed on.
+    pub fn master_secret(
+        self,
+        premaster_secret: &[u8],
+        client_random: &[u8; 32],
+        server_random: &[u8; 32],
+    ) -> [u8; 48] {
+        let mut master_secret = [0u8; 48];
+        self.derive(
+            premaster_secret,
+            b"master secret",
+            &[&client_random[..], &server_random[..]].concat(),
+            &mut master_secret,
+        );
+        master_secret
+    }
+
+    /// The key block, to be cut into the MAC keys, encryption keys and IVs
+    /// the cipher suite needs (client's before server's, in that order).
+    ///
+    /// Unlike `master_secret`, the seed here has the server random first.
+    pub fn key_block(
+        self,
+        master_secret: &[u8; 48],
+        client_random: &[u8; 32],
+        server_random: &[u8; 32],
+        length: usize,
+    ) -> Vec<u8> {
+        let mut key_block = vec![0u8; length];
+        self.derive(
+            master_secret,
+            b"key expansion",
+            &[&server_random[..], &client_random[..]].concat(),
+            &mut key_block,
+        );
+        key_block
+    }
+}
+
+// struct HkdfLabel { uint16 length; opaque label<7..255>; opaque context<0..255>; }
+fn hkdf_label(label: &[u8], context: &[u8], length: u16) -> Result<Vec<u8>, TlsError> {
+    const PREFIX: &[u8] = b"tls13 ";
+    if label.len() > 255 - PREFIX.len() {
+        return Err(TlsError::LabelTooLong {
+            field: "label",
+            length: label.len(),
+            maximum: 255 - PREFIX.len(),
+        });
+    }
+    if context.len() > 255 {
+        return Err(TlsError::LabelTooLong {
+            field: "context",
+            length: context.len(),
+            maximum: 255,
+        });
+    }
+
+    let mut info = Vec::with_capacity(4 + PREFIX.len() + label.len() + context.len());
+    info.extend_from_slice(&length.to_be_bytes());
+    info.push((PREFIX.len() + label.len()) as u8);
+    info.extend_from_slice(PREFIX);
+    info.extend_from_slice(label);
+    info.push(context.len() as u8);
+    info.extend_from_slice(context);
+    Ok(info)
+}
+
+/// `HKDF-Expand-Label(secret, label, context, length)`. The label is given
+/// without its `"tls13 "` prefix, and can be at most 249 bytes; the context
+/// can be at most 255. TLS 1.3 has no way to encode anything longer, so those
+/// are `TlsError::LabelTooLong`.
+pub fn hkdf_expand_label<D: Digest>(
+    secret: &[u8],
+    label: &[u8],
+    context: &[u8],
+    length: u16,
+) -> Result<Vec<u8>, TlsError> {
+    let mut output = vec![0u8; usize::from(length)];
+    hkdf::expand::<D>(secret, &hkdf_label(label, context, length)?, &mut output)?;
+    Ok(output)
+}
+
+// HKDF-Expand-Label for exactly one hash's worth of output, which is a single
+// HMAC: T(1) = HMAC(secret, info || 0x01)
+fn expand_label_to_hash<D: Digest>(
+    secret: &[u8],
+    label: &[u8],
+    context: &[u8],
+) -> Result<D::Output, TlsError> {
+    let mut info = hkdf_label(label, context, D::OUTPUT_LENGTH as u16)?;
+    info.push(1);
+    Ok(hmac::<D>(secret, &info))
+}
+
+/// `Derive-Secret(secret, label, messages)`: `HKDF-Expand-Label` with the
+/// hash of the handshake messages so far as its context. When the transcript
+/// hash is already at hand, use `KeySchedule::expand` or `hkdf_expand_label`
+/// with it directly. The label has the same limit as in `hkdf_expand_label`.
+pub fn derive_secret<D: Digest>(
+    secret: &[u8],
+    label: &[u8],
+    messages: &[u8],
+) -> Result<D::Output, TlsError> {
+    expand_label_to_hash::<D>(secret, label, D::digest(messages).as_ref())
+}
+
+/// The TLS 1.3 chain of secrets: early secret, then handshake secret, then
+/// master secret, each one extracted from the last.
+#[derive(Clone)]
+pub struct KeySchedule<D: Digest> {
+    secret: D::Output,
+}
+
+impl<D: Digest> KeySchedule<D> {
+    /// Starts at the early secret, from the pre-shared key, or `None` for a
+    /// full handshake without one.
+    pub fn new(pre_shared_key: Option<&[u8]>) -> Self {
+        let zeros = vec![0u8; D::OUTPUT_LENGTH];
+        KeySchedule {
+            secret: hkdf::extract::<D>(&zeros, pre_shared_key.unwrap_or(&zeros)),
+        }
+    }
+
+    /// The secret at the current stage.
+    pub fn secret(&self) -> &[u8] {
+        self.secret.as_ref()
+    }
+
+    /// Moves on to the next stage, mixing in the (EC)DHE shared secret on the
+    /// way to the handshake secret, or `None` on the way to the master secret.
+    pub fn advance(&mut self, input_key_material: Option<&[u8]>) {
+        let derived = derive_secret::<D>(self.secret.as_ref(), b"derived", &[])
+            .expect("\"derived\" is a short enough label");
+        let zeros = vec![0u8; D::OUTPUT_LENGTH];
+        self.secret = hkdf::extract::<D>(derived.as_ref(), input_key_material.unwrap_or(&zeros));
+    }
+
+    /// A secret from the current stage, such as `"c hs traffic"` from the
+    /// handshake secret, given the transcript hash up to the right message.
+    pub fn expand(&self, label: &[u8], transcript_hash: &[u8]) -> Result<D::Output, TlsError> {
+        expand_label_to_hash::<D>(self.secret.as_ref(), label, transcript_hash)
+    }
+}
+
+/// The record protection key and IV for one direction of a TLS 1.3
+/// connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrafficKeys {
+    pub key: Vec<u8>,
+    pub iv: Vec<u8>,
+}
+
+/// Splits a traffic secret into its key and IV; `key_length` is 16 or 32 for
+/// the AES-GCM suites and 32 for ChaCha20-Poly1305, and the IV is always 12
+/// bytes.
+pub fn traffic_keys<D: Digest>(traffic_secret: &[u8], key_length: u16) -> TrafficKeys {
+    TrafficKeys {
+        key: hkdf_expand_label::<D>(traffic_secret, b"key", &[], key_length)
+            .expect("keys are short enough to expand to"),
+        iv: hkdf_expand_label::<D>(traffic_secret, b"iv", &[], 12)
+            .expect("IVs are short enough to expand to"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{bytes_to_hex, hex_to_bytes};
+
+    #[test]
+    fn test_tls12_prf() {
+        // the P_SHA256 vector posted to the TLS working group list
+        let mut output = [0u8; 100];
+        Prf::Tls12Sha256.derive(
+            &hex_to_bytes("9bbe436ba940f017b17652849a71db35").unwrap(),
+            b"test label",
+            &hex_to_bytes("a0ba9f936cda311827a6f796ffd5198c").unwrap(),
+            &mut output,
+        );
+        assert_eq!(
+            "e3f229ba727be17b8d122620557cd453c2aab21d07c3d495329b52d4e61edb5a6b301791e90d35c9c9a46b4e14baf9af0fa022f7077def17abfd3797c0564bab4fbc91666e9def9b97fce34f796789baa48082d122ee42c5a72e5a5110fff70187347b66",
+            bytes_to_hex(&output)
+        );
+    }
+
+    #[test]
+    fn test_tls10_prf() {
+        // an odd-length secret, so the halves share a byte
+        let secret = (0x0b..0x0b + 47).collect::<Vec<u8>>();
+        let mut output = [0u8; 80];
+        Prf::Tls10.derive(&secret, b"test label", b"seed", &mut output);
+        assert_eq!(
+            "97e00d13310909de9ca71d60508cb02e0a10323969b0cfccf0ea86a96e73826472e0e0ce73a0ff43d093852ea0bc8d8d4a88bccd6647a4e46cc377a70b6640b79fa8bd0efe8ea74bea45819177fb9f95",
+            bytes_to_hex(&output)
+        );
+
+        // and the output doesn't depend on how much of it is asked for
+        let mut shorter = [0u8; 33];
+        Prf::Tls10.derive(&secret, b"test label", b"seed", &mut shorter);
+        assert_eq!(output[..33], shorter);
+    }
+
+    #[test]
+    fn test_key_block_seed_order() {
+        let client_random = [0x11; 32];
+        let server_random = [0x22; 32];
+        let master_secret =
+            Prf::Tls12Sha256.master_secret(b"premaster", &client_random, &server_random);
+
+        let mut expected = vec![0u8; 40];
+        Prf::Tls12Sha256.derive(
+            &master_secret,
+            b"key expansion",
+            &[&server_random[..], &client_random[..]].concat(),
+            &mut expected,
+        );
+        assert_eq!(
+            expected,
+            Prf::Tls12Sha256.key_block(&master_secret, &client_random, &server_random, 40)
+        );
+    }
+
+    // RFC 8448, section 3: a simple 1-RTT handshake
+    #[test]
+    fn test_rfc8448_key_schedule() {
+        let mut schedule = KeySchedule::<Sha256>::new(None);
+        assert_eq!(
+            "33ad0a1c607ec03b09e6cd9893680ce210adf300aa1f2660e1b22e10f170f92a",
+            bytes_to_hex(schedule.secret())
+        );
+        assert_eq!(
+            "6f2615a108c702c5678f54fc9dbab69716c076189c48250cebeac3576c3611ba",
+            bytes_to_hex(&derive_secret::<Sha256>(schedule.secret(), b"derived", b"").unwrap())
+        );
+
+        schedule.advance(Some(
+            &hex_to_bytes("8bd4054fb55b9d63fdfbacf9f04b9f0d35e6d63f537563efd46272900f89492d")
+                .unwrap(),
+        ));
+        assert_eq!(
+            "1dc826e93606aa6fdc0aadc12f741b01046aa6b99f691ed221a9f0ca043fbeac",
+            bytes_to_hex(schedule.secret())
+        );
+
+        // the hash of ClientHello and ServerHello
+        let transcript_hash =
+            hex_to_bytes("860c06edc07858ee8e78f0e7428c58edd6b43f2ca3e6e95f02ed063cf0e1cad8")
+                .unwrap();
+        assert_eq!(
+            "b3eddb126e067f35a780b3abf45e2d8f3b1a950738f52e9600746a0e27a55a21",
+            bytes_to_hex(&schedule.expand(b"c hs traffic", &transcript_hash).unwrap())
+        );
+        let server_handshake = schedule.expand(b"s hs traffic", &transcript_hash).unwrap();
+        assert_eq!(
+            "b67b7d690cc16c4e75e54213cb2d37b4e9c912bcded9105d42befd59d391ad38",
+            bytes_to_hex(&server_handshake)
+        );
+        assert_eq!(
+            TrafficKeys {
+                key: hex_to_bytes("3fce516009c21727d0f2e4e86ee403bc").unwrap(),
+                iv: hex_to_bytes("5d313eb2671276ee13000b30").unwrap(),
+            },
+            traffic_keys::<Sha256>(&server_handshake, 16)
+        );
+
+        schedule.advance(None);
+        assert_eq!(
+            "18df06843d13a08bf2a449844c5f8a478001bc4d4c627984d5a41da8d0402919",
+            bytes_to_hex(schedule.secret())
+        );
+
+        // ... up to the server's Finished
+        let transcript_hash =
+            hex_to_bytes("9608102a0f1ccc6db6250b7b7e417b1a000eaada3daae4777a7686c9ff83df13")
+                .unwrap();
+        assert_eq!(
+            "9e40646ce79a7f9dc05af8889bce6552875afa0b06df0087f792ebb7c17504a5",
+            bytes_to_hex(&schedule.expand(b"c ap traffic", &transcript_hash).unwrap())
+        );
+        let server_application = schedule.expand(b"s ap traffic", &transcript_hash).unwrap();
+        assert_eq!(
+            "a11af9f05531f856ad47116b45a950328204b4f44bfb6b3a4b4f1f3fcb631643",
+            bytes_to_hex(&server_application)
+        );
+        assert_eq!(
+            TrafficKeys {
+                key: hex_to_bytes("9f02283b6c9c07efc26bb9f2ac92e356").unwrap(),
+                iv: hex_to_bytes("cf782b88dd83549aadf1e984").unwrap(),
+            },
+            traffic_keys::<Sha256>(&server_application, 16)
+        );
+        assert_eq!(
+            "fe22f881176eda18eb8f44529e6792c50c9a3f89452f68d8ae311b4309d3cf50",
+            bytes_to_hex(&schedule.expand(b"exp master", &transcript_hash).unwrap())
+        );
+
+        // ... and up to the client's Finished
+        let transcript_hash =
+            hex_to_bytes("209145a96ee8e2a122ff810047cc952684658d6049e86429426db87c54ad143d")
+                .unwrap();
+        assert_eq!(
+            "7df235f2031d2a051287d02b0241b0bfdaf86cc856231f2d5aba46c434ec196c",
+            bytes_to_hex(&schedule.expand(b"res master", &transcript_hash).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_label_too_long() {
+        let secret = [0u8; 32];
+        assert!(hkdf_expand_label::<Sha256>(&secret, &[b'a'; 249], &[], 16).is_ok());
+        assert_eq!(
+            Err(TlsError::LabelTooLong {
+                field: "label",
+                length: 250,
+                maximum: 249,
+            }),
+            hkdf_expand_label::<Sha256>(&secret, &[b'a'; 250], &[], 16)
+        );
+        assert_eq!(
+            Err(TlsError::LabelTooLong {
+                field: "context",
+                length: 256,
+                maximum: 255,
+            }),
+            hkdf_expand_label::<Sha256>(&secret, b"key", &[0; 256], 16)
+        );
+        assert_eq!(
+            Err(TlsError::Hkdf(HkdfError::OutputTooLong {
+                requested: 9000,
+                maximum: 8160,
+            })),
+            hkdf_expand_label::<Sha256>(&secret, b"key", &[], 9000)
+        );
+        assert!(KeySchedule::<Sha256>::new(None)
+            .expand(&[b'a'; 250], &secret)
+            .is_err());
+    }
+
+}
@@ -2,13 +2,166 @@ pub const BLOCK_SIZE: usize = 512; // 32 * 16
 const LENGTH_OF_LENGTH_STR: usize = 64;
 
 pub fn calc_zero_padding(length: usize) -> usize {
-    (BLOCK_SIZE - LENGTH_OF_LENGTH_STR - length - 1) % BLOCK_SIZE
+    // only the part of the message in the last block matters -- going through
+    // 2 * BLOCK_SIZE keeps this from underflowing for longer messages
+    (2 * BLOCK_SIZE - LENGTH_OF_LENGTH_STR - (length % BLOCK_SIZE) - 1) % BLOCK_SIZE
 }
 
 pub fn to_hex_string(value: u32) -> Vec<char> {
     format!("{:08x}", value).chars().collect::<Vec<char>>()
 }
 
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>()
+}
+
+/// The reverse of `bytes_to_hex`, in either case. An odd number of digits, or
+/// anything that isn't a hex digit, gives `None` -- including the sign that
+/// `from_str_radix` on its own would let through.
+pub fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+/// Compares two byte strings in time that depends only on their lengths, not
+/// on where they first differ -- so comparing a forged MAC against the real one
+/// doesn't leak, one byte at a time, how much of the forgery was right.
+pub fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+
+    let difference = left
+        .iter()
+        .zip(right.iter())
+        .fold(0u8, |difference, (left, right)| difference | (left ^ right));
+
+    // keep the optimizer from turning the fold back into an early exit
+    std::hint::black_box(difference) == 0
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Base32 (RFC 4648) without the `=` padding, the way OTP secrets are
+/// usually written: every 5 bits becomes one of `A-Z` and `2-7`.
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 8).div_ceil(5));
+
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | u32::from(*byte);
+        bits += 8;
+
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1F) as usize] as char);
+        }
+    }
+
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1F) as usize] as char);
+    }
+
+    encoded
+}
+
+/// The reverse of `base32_encode`. Lowercase letters and trailing `=` padding
+/// are accepted too; anything else that isn't in the alphabet gives `None`.
+pub fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for character in encoded.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|letter| *letter == character.to_ascii_uppercase())?;
+
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 (RFC 4648, section 4), padded with `=` to a multiple of four
+/// characters.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for group in bytes.chunks(3) {
+        let buffer = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |buffer, (index, byte)| {
+                buffer | u32::from(*byte) << (16 - 8 * index)
+            });
+
+        for index in 0..4 {
+            if index <= group.len() {
+                encoded
+                    .push(BASE64_ALPHABET[((buffer >> (18 - 6 * index)) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// The reverse of `base64_encode`. Only what `base64_encode` itself could
+/// have written is accepted: anything outside the alphabet, missing or extra
+/// padding, or leftover bits in the last character that aren't zero gives
+/// `None`, so every byte string has exactly one encoding that decodes.
+pub fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let trimmed = encoded.trim_end_matches('=');
+    let padding = encoded.len() - trimmed.len();
+    if !encoded.len().is_multiple_of(4) || padding > 2 {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(trimmed.len() * 3 / 4);
+
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for character in trimmed.bytes() {
+        let value = BASE64_ALPHABET
+            .iter()
+            .position(|letter| *letter == character)?;
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    // "Zh==" would otherwise decode to "f" along with "Zg=="
+    if buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+
+    Some(decoded)
+}
+
 /// The goal with preprocessing the message is to get a series of blocks to
 /// operate on, each of which is 512 bits in length.
 ///
@@ -72,6 +225,89 @@ pub fn preprocess_little_endian(raw_message: String) -> String {
     message
 }
 
+/// The same padding `preprocess` builds out of bit strings, but as bytes: a
+/// 1 bit (so 0x80, since messages here are whole bytes), enough zeroes to
+/// leave room for the length at the end of a block, and then the length of
+/// the message in bits.
+///
+/// `block_length` is in bytes. The length field takes up the last eighth of
+/// the block: 8 bytes for the 64-byte blocks of MD5/SHA-1/SHA-256, 16 bytes
+/// for the 128-byte blocks of SHA-512.
+pub fn md_padding(message_length: u64, block_length: usize, little_endian: bool) -> Vec<u8> {
+    let length_field = block_length / 8;
+    let used = (message_length % block_length as u64) as usize;
+    let zeroes = (2 * block_length - used - 1 - length_field) % block_length;
+
+    let length_in_bits = u128::from(message_length) * 8;
+    let length_bytes = if little_endian {
+        length_in_bits.to_le_bytes()[..length_field].to_vec()
+    } else {
+        length_in_bits.to_be_bytes()[16 - length_field..].to_vec()
+    };
+
+    let mut padding = vec![0x80];
+    padding.extend(std::iter::repeat_n(0u8, zeroes));
+    padding.extend(length_bytes);
+
+    padding
+}
+
+/// Collects bytes into whole blocks for the Merkle–Damgård hashes (MD5, SHA-1
+/// and SHA-2), so that they can be fed a message a piece at a time.
+///
+/// Every time a block fills up, it gets handed to `compress`.
+#[derive(Clone)]
+pub struct BlockBuffer<const BLOCK_LENGTH: usize> {
+    block: [u8; BLOCK_LENGTH],
+    filled: usize,
+    length: u64,
+}
+
+impl<const BLOCK_LENGTH: usize> Default for BlockBuffer<BLOCK_LENGTH> {
+    fn default() -> Self {
+        BlockBuffer {
+            block: [0; BLOCK_LENGTH],
+            filled: 0,
+            length: 0,
+        }
+    }
+}
+
+impl<const BLOCK_LENGTH: usize> BlockBuffer<BLOCK_LENGTH> {
+    /// A buffer for a hash that has already been through `length` bytes,
+    /// which must be a whole number of blocks.
+    pub fn resume(length: u64) -> Self {
+        assert_eq!(0, length % BLOCK_LENGTH as u64, "only whole blocks can be resumed");
+
+        BlockBuffer {
+            length,
+            ..BlockBuffer::default()
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8], mut compress: impl FnMut(&[u8; BLOCK_LENGTH])) {
+        self.length += bytes.len() as u64;
+
+        for byte in bytes {
+            self.block[self.filled] = *byte;
+            self.filled += 1;
+
+            if self.filled == BLOCK_LENGTH {
+                compress(&self.block);
+                self.filled = 0;
+            }
+        }
+    }
+
+    /// Pad out whatever is left and compress the last block (or two).
+    pub fn finish(&mut self, little_endian: bool, compress: impl FnMut(&[u8; BLOCK_LENGTH])) {
+        let padding = md_padding(self.length, BLOCK_LENGTH, little_endian);
+        self.update(&padding, compress);
+
+        assert_eq!(0, self.filled);
+    }
+}
+
 fn to_bits(input: &str) -> Vec<char> {
     input
         .as_bytes()
@@ -104,8 +340,12 @@ mod tests {
     #[test]
     fn test_num_zeros() {
         let message = "abc";
-        let length = to_bits(&message).len();
+        let length = to_bits(message).len();
         assert_eq!(423, calc_zero_padding(length));
+
+        // long enough that it no longer fits in one block
+        assert_eq!(511, calc_zero_padding(448));
+        assert_eq!(447, calc_zero_padding(512));
     }
 
     #[test]
@@ -121,4 +361,70 @@ mod tests {
         assert_eq!(output, preprocess_little_endian(String::from("1")));
     }
 
+    #[test]
+    fn test_md_padding() {
+        // the same padding `preprocess` builds for "abc", just as bytes
+        let padding = md_padding(3, 64, false);
+        let bits = padding
+            .iter()
+            .map(|byte| format!("{:08b}", byte))
+            .collect::<String>();
+
+        assert_eq!(preprocess(String::from("abc"))[24..], bits);
+
+        // 56 bytes leaves no room for the length, so it spills into another block
+        assert_eq!(72, md_padding(56, 64, false).len());
+        assert_eq!(0, (120 + md_padding(120, 128, false).len()) % 128);
+    }
+
+    #[test]
+    fn test_base32() {
+        // RFC 4648, section 10, without the padding
+        assert_eq!("", base32_encode(b""));
+        assert_eq!("MY", base32_encode(b"f"));
+        assert_eq!("MZXW6", base32_encode(b"foo"));
+        assert_eq!("MZXW6YTBOI", base32_encode(b"foobar"));
+
+        assert_eq!(Some(b"foobar".to_vec()), base32_decode("MZXW6YTBOI"));
+        assert_eq!(Some(b"fo".to_vec()), base32_decode("mzxq===="));
+        assert_eq!(None, base32_decode("MZXW1"));
+    }
+
+    #[test]
+    fn test_hex_to_bytes() {
+        assert_eq!(Some(vec![0x00, 0xab, 0xCD]), hex_to_bytes("00abCD"));
+        assert_eq!(Some(Vec::new()), hex_to_bytes(""));
+        assert_eq!(None, hex_to_bytes("abc"));
+        assert_eq!(None, hex_to_bytes("+f"));
+        assert_eq!(None, hex_to_bytes("0g"));
+        assert_eq!(None, hex_to_bytes("é0"));
+    }
+
+    #[test]
+    fn test_base64() {
+        // RFC 4648, section 10
+        assert_eq!("", base64_encode(b""));
+        assert_eq!("Zg==", base64_encode(b"f"));
+        assert_eq!("Zm8=", base64_encode(b"fo"));
+        assert_eq!("Zm9vYmFy", base64_encode(b"foobar"));
+        assert_eq!("+/8=", base64_encode(&[0xfb, 0xff]));
+
+        assert_eq!(Some(b"foobar".to_vec()), base64_decode("Zm9vYmFy"));
+        assert_eq!(Some(b"fo".to_vec()), base64_decode("Zm8="));
+        assert_eq!(Some(b"f".to_vec()), base64_decode("Zg=="));
+        assert_eq!(Some(Vec::new()), base64_decode(""));
+        assert_eq!(None, base64_decode("Zm9vY"));
+        assert_eq!(None, base64_decode("Zm9v!A=="));
+
+        // only the canonical encoding decodes
+        assert_eq!(None, base64_decode("Zm8"));
+        assert_eq!(None, base64_decode("Zm8=="));
+        assert_eq!(None, base64_decode("Zg="));
+        assert_eq!(None, base64_decode("Zg==="));
+        assert_eq!(None, base64_decode("Zm9v===="));
+        assert_eq!(None, base64_decode("Zh=="));
+        assert_eq!(None, base64_decode("Zm9="));
+        assert_eq!(None, base64_decode("Zm=v"));
+    }
+
 }
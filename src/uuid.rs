@@ -0,0 +1,247 @@
+//! Name-based UUIDs (RFC 9562): version 3 (MD5), version 5 (SHA-1), and
+//! version 8 with SHA-256.
+//!
+//! A name-based UUID is the hash of a *namespace* UUID followed by a name, cut
+//! to 16 bytes, with six bits overwritten to say what kind of UUID it is: four
+//! bits of version, and two bits of variant. The same namespace and name always
+//! give the same UUID, which makes them good stable identifiers for things
+//! that already have a name -- a URL, a domain, a catalog key.
+//!
+//! Prefer version 5 over version 3. Version 8 is RFC 9562's way of using a
+//! modern hash; the RFC's example uses SHA-256, and that's what's here.
+
+use crate::digest::Digest;
+use crate::md5::Md5;
+use crate::sha2::Sha256;
+use crate::Sha1;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid([u8; 16]);
+
+/// For fully-qualified domain names.
+pub const NAMESPACE_DNS: Uuid = Uuid([
+    0x6b, 0xa7, 0xb8, 0x10, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8,
+]);
+
+/// For URLs.
+pub const NAMESPACE_URL: Uuid = Uuid([
+    0x6b, 0xa7, 0xb8, 0x11, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8,
+]);
+
+/// For ISO OIDs.
+pub const NAMESPACE_OID: Uuid = Uuid([
+    0x6b, 0xa7, 0xb8, 0x12, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8,
+]);
+
+/// For X.500 distinguished names (in DER or text).
+pub const NAMESPACE_X500: Uuid = Uuid([
+    0x6b, 0xa7, 0xb8, 0x14, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4, 0x30, 0xc8,
+]);
+
+/// The variant field, in the top bits of byte 8. Everything this module makes
+/// is `Rfc9562`; the others are for reading UUIDs from elsewhere.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// `0xx`: the old Apollo NCS UUIDs.
+    Ncs,
+    /// `10x`: the RFC 4122 / RFC 9562 layout.
+    Rfc9562,
+    /// `110`: Microsoft's COM GUIDs.
+    Microsoft,
+    /// `111`: reserved.
+    Future,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UuidError {
+    /// Not 32 hex digits, with or without the usual hyphens.
+    InvalidFormat,
+}
+
+impl fmt::Display for UuidError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UuidError::InvalidFormat => write!(formatter, "not a valid UUID"),
+        }
+    }
+}
+
+impl std::error::Error for UuidError {}
+
+impl Uuid {
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Uuid(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// The version number in the top four bits of byte 6.
+    pub fn version(&self) -> u8 {
+        self.0[6] >> 4
+    }
+
+    pub fn variant(&self) -> Variant {
+        match self.0[8] {
+            0x00..=0x7F => Variant::Ncs,
+            0x80..=0xBF => Variant::Rfc9562,
+            0xC0..=0xDF => Variant::Microsoft,
+            _ => Variant::Future,
+        }
+    }
+
+    fn name_based<D: Digest>(namespace: &Uuid, name: &[u8], version: u8) -> Self {
+        let mut hasher = D::default();
+        hasher.update(&namespace.0);
+        hasher.update(name);
+        let hash = hasher.finalize();
+
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hash.as_ref()[..16]);
+        bytes[6] = (bytes[6] & 0x0F) | (version << 4);
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+
+        Uuid(bytes)
+    }
+
+    pub fn new_v3(namespace: &Uuid, name: &[u8]) -> Self {
+        Uuid::name_based::<Md5>(namespace, name, 3)
+    }
+
+    pub fn new_v5(namespace: &Uuid, name: &[u8]) -> Self {
+        Uuid::name_based::<Sha1>(namespace, name, 5)
+    }
+
+    /// Version 8 is "custom": RFC 9562 only fixes the version and variant
+    /// bits. This is the name-based SHA-256 construction from its appendix,
+    /// built exactly like version 5.
+    pub fn new_v8_sha256(namespace: &Uuid, name: &[u8]) -> Self {
+        Uuid::name_based::<Sha256>(namespace, name, 8)
+    }
+}
+
+/// The usual `8-4-4-4-12` hex form, in lowercase.
+impl fmt::Display for Uuid {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index == 4 || index == 6 || index == 8 || index == 10 {
+                write!(formatter, "-")?;
+            }
+            write!(formatter, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Accepts the hyphenated form and plain hex (in either case), optionally in
+/// braces or with a `urn:uuid:` prefix.
+impl FromStr for Uuid {
+    type Err = UuidError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.strip_prefix("urn:uuid:").unwrap_or(text);
+        let text = text
+            .strip_prefix('{')
+            .and_then(|inner| inner.strip_suffix('}'))
+            .unwrap_or(text);
+
+        let hyphens = [8, 13, 18, 23];
+        let digits: String = if text.len() == 36 {
+            if hyphens.iter().any(|index| text.as_bytes()[*index] != b'-') {
+                return Err(UuidError::InvalidFormat);
+            }
+            text.chars().filter(|character| *character != '-').collect()
+        } else {
+            text.to_string()
+        };
+
+        if digits.len() != 32 || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(UuidError::InvalidFormat);
+        }
+
+        let mut bytes = [0u8; 16];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digits[2 * index..2 * index + 2], 16)
+                .map_err(|_| UuidError::InvalidFormat)?;
+        }
+
+        Ok(Uuid(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaces() {
+        assert_eq!(
+            "6ba7b810-9dad-11d1-80b4-00c04fd430c8",
+            NAMESPACE_DNS.to_string()
+        );
+        assert_eq!(
+            "6ba7b811-9dad-11d1-80b4-00c04fd430c8",
+            NAMESPACE_URL.to_string()
+        );
+        assert_eq!(
+            "6ba7b812-9dad-11d1-80b4-00c04fd430c8",
+            NAMESPACE_OID.to_string()
+        );
+        assert_eq!(
+            "6ba7b814-9dad-11d1-80b4-00c04fd430c8",
+            NAMESPACE_X500.to_string()
+        );
+    }
+
+    // RFC 9562, appendix A and B
+    #[test]
+    fn test_name_based() {
+        let v3 = Uuid::new_v3(&NAMESPACE_DNS, b"www.example.com");
+        assert_eq!("5df41881-3aed-3515-88a7-2f4a814cf09e", v3.to_string());
+        assert_eq!(3, v3.version());
+
+        let v5 = Uuid::new_v5(&NAMESPACE_DNS, b"www.example.com");
+        assert_eq!("2ed6657d-e927-568b-95e1-2665a8aea6a2", v5.to_string());
+        assert_eq!(5, v5.version());
+
+        let v8 = Uuid::new_v8_sha256(&NAMESPACE_DNS, b"www.example.com");
+        assert_eq!("5c146b14-3c52-8afd-938a-375d0df1fbf6", v8.to_string());
+        assert_eq!(8, v8.version());
+        assert_eq!(Variant::Rfc9562, v8.variant());
+    }
+
+    #[test]
+    fn test_parse() {
+        let expected = Uuid::new_v5(&NAMESPACE_DNS, b"www.example.com");
+
+        for text in [
+            "2ed6657d-e927-568b-95e1-2665a8aea6a2",
+            "2ED6657D-E927-568B-95E1-2665A8AEA6A2",
+            "2ed6657de927568b95e12665a8aea6a2",
+            "{2ed6657d-e927-568b-95e1-2665a8aea6a2}",
+            "urn:uuid:2ed6657d-e927-568b-95e1-2665a8aea6a2",
+        ] {
+            assert_eq!(Ok(expected), text.parse::<Uuid>());
+        }
+
+        assert_eq!(
+            Err(UuidError::InvalidFormat),
+            "2ed6657d-e927-568b-95e1-2665a8aea6a".parse::<Uuid>()
+        );
+        assert_eq!(
+            Err(UuidError::InvalidFormat),
+            "2ed6657d_e927_568b_95e1_2665a8aea6a2".parse::<Uuid>()
+        );
+        assert_eq!(
+            Variant::Microsoft,
+            "00000000-0000-0000-c000-000000000046"
+                .parse::<Uuid>()
+                .unwrap()
+                .variant()
+        );
+    }
+
+}
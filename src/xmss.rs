@@ -0,0 +1,682 @@
+//! XMSS, the eXtended Merkle Signature Scheme from RFC 8391, with SHA-256.
+//!
+//! Like LMS (see `lms`), XMSS is a Merkle tree over `2 ** h` one-time keys --
+//! WOTS+ keys here, from `ots::wots` -- and a signature is a one-time signature
+//! plus the path up the tree. The differences are in the hashing: each WOTS+
+//! public key is squashed into one leaf by an unbalanced "L-tree", and every
+//! hash in the tree is keyed and masked by values made from the public seed
+//! and the hash's address, which lets XMSS get by with weaker assumptions
+//! about the hash function.
+//!
+//! **These signatures are stateful**, exactly as with LMS: `sign` moves on to
+//! the next one-time key before handing back the signature and refuses to sign
+//! once they're all used, and the state from `to_bytes` has to be saved before
+//! each signature is released. Loading an old copy of the state, or the same
+//! state into two signers, reuses one-time keys and lets anyone forge
+//! signatures.
+//!
+//! Private keys hold seeds rather than the WOTS+ secret keys themselves, the
+//! way the XMSS reference implementation and NIST SP 800-208 do it.
+
+use crate::ots::wots::{self, keyed_hash, prf, Address};
+use std::fmt;
+
+const N: usize = 32;
+
+type Hash = [u8; N];
+
+const PADDING_H: u8 = 1;
+const PADDING_H_MSG: u8 = 2;
+const PADDING_PRF: u8 = 3;
+
+const ADDRESS_OTS: u32 = 0;
+const ADDRESS_LTREE: u32 = 1;
+const ADDRESS_HASH_TREE: u32 = 2;
+
+const PRIVATE_KEY_LENGTH: usize = 4 + 4 + 4 * N;
+const PUBLIC_KEY_LENGTH: usize = 4 + 2 * N;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmssError {
+    /// Every one-time key has been used.
+    Exhausted,
+    /// An OID that isn't one of the SHA-256 parameter sets.
+    UnknownOid(u32),
+    /// A key or signature that's the wrong length for its parameters.
+    InvalidLength,
+    /// A saved private key whose seeds don't give its root.
+    InvalidState,
+    /// The signature doesn't match the message and the public key.
+    InvalidSignature,
+}
+
+impl fmt::Display for XmssError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XmssError::Exhausted => write!(formatter, "every one-time key has been used"),
+            XmssError::UnknownOid(oid) => write!(formatter, "unknown OID {:#010x}", oid),
+            XmssError::InvalidLength => write!(formatter, "invalid length"),
+            XmssError::InvalidState => write!(formatter, "inconsistent private key state"),
+            XmssError::InvalidSignature => write!(formatter, "invalid signature"),
+        }
+    }
+}
+
+impl std::error::Error for XmssError {}
+
+/// The XMSS parameter sets with SHA-256 and `n = 32`, from RFC 8391,
+/// section 5.3, named by tree height.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmssParameters {
+    Sha2_10_256,
+    Sha2_16_256,
+    Sha2_20_256,
+}
+
+impl XmssParameters {
+    pub fn oid(self) -> u32 {
+        match self {
+            XmssParameters::Sha2_10_256 => 1,
+            XmssParameters::Sha2_16_256 => 2,
+            XmssParameters::Sha2_20_256 => 3,
+        }
+    }
+
+    pub fn from_oid(oid: u32) -> Result<Self, XmssError> {
+        match oid {
+            1 => Ok(XmssParameters::Sha2_10_256),
+            2 => Ok(XmssParameters::Sha2_16_256),
+            3 => Ok(XmssParameters::Sha2_20_256),
+            _ => Err(XmssError::UnknownOid(oid)),
+        }
+    }
+
+    pub fn height(self) -> u32 {
+        match self {
+            XmssParameters::Sha2_10_256 => 10,
+            XmssParameters::Sha2_16_256 => 16,
+            XmssParameters::Sha2_20_256 => 20,
+        }
+    }
+}
+
+fn signature_length(height: u32) -> usize {
+    4 + N + wots::CHAINS * N + height as usize * N
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0u8; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_be_bytes(word)
+}
+
+fn read_hash(bytes: &[u8], offset: usize) -> Hash {
+    let mut hash = [0u8; N];
+    hash.copy_from_slice(&bytes[offset..offset + N]);
+    hash
+}
+
+/// `toByte(value, 32)`.
+fn index_bytes(value: u32) -> Hash {
+    let mut bytes = [0u8; N];
+    bytes[N - 4..].copy_from_slice(&value.to_be_bytes());
+    bytes
+}
+
+/// `RAND_HASH` from RFC 8391, section 4.1.4: `H` keyed, and with both halves
+/// masked, by values made from the public seed and the address.
+fn rand_hash(left: &Hash, right: &Hash, public_seed: &Hash, address: &mut Address) -> Hash {
+    address.set_key_and_mask(0);
+    let key = prf(public_seed, address);
+    address.set_key_and_mask(1);
+    let left_mask = prf(public_seed, address);
+    address.set_key_and_mask(2);
+    let right_mask = prf(public_seed, address);
+
+    let mut masked = [0u8; 2 * N];
+    for index in 0..N {
+        masked[index] = left[index] ^ left_mask[index];
+        masked[N + index] = right[index] ^ right_mask[index];
+    }
+
+    keyed_hash(PADDING_H, &key, &[&masked])
+}
+
+/// Squashes a WOTS+ public key into one hash, a level at a time. When a level
+/// has an odd number of nodes, the last one moves up a level unchanged.
+fn ltree(mut nodes: Vec<Hash>, public_seed: &Hash, address: &mut Address) -> Hash {
+    let mut height = 0;
+    while nodes.len() > 1 {
+        address.set_tree_height(height);
+        let next = nodes
+            .chunks(2)
+            .enumerate()
+            .map(|(index, pair)| match pair {
+                [left, right] => {
+                    address.set_tree_index(index as u32);
+                    rand_hash(left, right, public_seed, address)
+                }
+                [last] => *last,
+                _ => unreachable!(),
+            })
+            .collect();
+        nodes = next;
+        height += 1;
+    }
+
+    nodes[0]
+}
+
+fn ots_address(index: u32) -> Address {
+    let mut address = Address::default();
+    address.set_type(ADDRESS_OTS);
+    address.set_ots(index);
+    address
+}
+
+fn ltree_address(index: u32) -> Address {
+    let mut address = Address::default();
+    address.set_type(ADDRESS_LTREE);
+    address.set_ltree(index);
+    address
+}
+
+fn hash_tree_address() -> Address {
+    let mut address = Address::default();
+    address.set_type(ADDRESS_HASH_TREE);
+    address
+}
+
+/// `H_msg`, keyed by the randomizer, the root and the index.
+fn message_digest(randomizer: &Hash, root: &Hash, index: u32, message: &[u8]) -> Hash {
+    keyed_hash(
+        PADDING_H_MSG,
+        randomizer,
+        &[root, &index_bytes(index), message],
+    )
+}
+
+/// An XMSS private key, with its whole tree kept so that signing doesn't have
+/// to rebuild it. Making (or loading) a key builds every WOTS+ key in the
+/// tree, which takes a while even for `Sha2_10_256`, and is a lot of work for
+/// the taller trees.
+///
+/// There's deliberately no `Clone`, since two copies of the key would reuse
+/// one-time keys; `to_bytes` and `from_bytes` are the way to save and restore
+/// it.
+pub struct XmssPrivateKey {
+    parameters: XmssParameters,
+    height: u32,
+    next: u32,
+    secret_seed: Hash,
+    secret_prf: Hash,
+    public_seed: Hash,
+    // levels[0] is the leaves, and the last level is just the root
+    levels: Vec<Vec<Hash>>,
+}
+
+impl XmssPrivateKey {
+    /// All three seeds must be random: `secret_seed` makes the WOTS+ keys,
+    /// `secret_prf` makes the randomizer for each signature, and `public_seed`
+    /// (which ends up in the public key) makes the keys and masks for hashing.
+    pub fn new(
+        parameters: XmssParameters,
+        secret_seed: Hash,
+        secret_prf: Hash,
+        public_seed: Hash,
+    ) -> Self {
+        XmssPrivateKey::with_height(
+            parameters,
+            parameters.height(),
+            secret_seed,
+            secret_prf,
+            public_seed,
+        )
+    }
+
+    fn with_height(
+        parameters: XmssParameters,
+        height: u32,
+        secret_seed: Hash,
+        secret_prf: Hash,
+        public_seed: Hash,
+    ) -> Self {
+        let leaves = (0..1u32 << height)
+            .map(|index| {
+                let public_key = wots::public_key(&secret_seed, &public_seed, &ots_address(index));
+                ltree(public_key, &public_seed, &mut ltree_address(index))
+            })
+            .collect::<Vec<_>>();
+
+        let mut levels = vec![leaves];
+        let mut address = hash_tree_address();
+        for level in 0..height {
+            address.set_tree_height(level);
+            let next = levels[level as usize]
+                .chunks_exact(2)
+                .enumerate()
+                .map(|(index, pair)| {
+                    address.set_tree_index(index as u32);
+                    rand_hash(&pair[0], &pair[1], &public_seed, &mut address)
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        XmssPrivateKey {
+            parameters,
+            height,
+            next: 0,
+            secret_seed,
+            secret_prf,
+            public_seed,
+            levels,
+        }
+    }
+
+    fn root(&self) -> Hash {
+        self.levels[self.height as usize][0]
+    }
+
+    pub fn public_key(&self) -> XmssPublicKey {
+        XmssPublicKey {
+            parameters: self.parameters,
+            height: self.height,
+            root: self.root(),
+            public_seed: self.public_seed,
+        }
+    }
+
+    /// How many more signatures the key can make.
+    pub fn remaining(&self) -> u32 {
+        (1 << self.height) - self.next
+    }
+
+    /// Signs `message` with the next unused one-time key, and marks it used.
+    /// Save the key's state before releasing the signature.
+    pub fn sign(&mut self, message: &[u8]) -> Result<Vec<u8>, XmssError> {
+        if self.remaining() == 0 {
+            return Err(XmssError::Exhausted);
+        }
+
+        let index = self.next;
+        self.next += 1;
+
+        let randomizer = keyed_hash(PADDING_PRF, &self.secret_prf, &[&index_bytes(index)]);
+        let digest = message_digest(&randomizer, &self.root(), index, message);
+
+        let mut signature = Vec::with_capacity(signature_length(self.height));
+        signature.extend_from_slice(&index.to_be_bytes());
+        signature.extend_from_slice(&randomizer);
+        for value in wots::sign(
+            &digest,
+            &self.secret_seed,
+            &self.public_seed,
+            &ots_address(index),
+        ) {
+            signature.extend_from_slice(&value);
+        }
+        for (level, nodes) in self.levels[..self.height as usize].iter().enumerate() {
+            signature.extend_from_slice(&nodes[(index >> level) as usize ^ 1]);
+        }
+
+        Ok(signature)
+    }
+
+    /// The state to save: the OID, the index of the next unused one-time key,
+    /// the two secret seeds, the root and the public seed, in 136 bytes. **This
+    /// includes the secret seeds**, so it must be kept as secret as they are.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PRIVATE_KEY_LENGTH);
+        bytes.extend_from_slice(&self.parameters.oid().to_be_bytes());
+        bytes.extend_from_slice(&self.next.to_be_bytes());
+        bytes.extend_from_slice(&self.secret_seed);
+        bytes.extend_from_slice(&self.secret_prf);
+        bytes.extend_from_slice(&self.root());
+        bytes.extend_from_slice(&self.public_seed);
+        bytes
+    }
+
+    /// Loads saved state, rebuilding the tree (and checking that it has the
+    /// saved root).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, XmssError> {
+        if bytes.len() != PRIVATE_KEY_LENGTH {
+            return Err(XmssError::InvalidLength);
+        }
+
+        let parameters = XmssParameters::from_oid(read_u32(bytes, 0))?;
+        XmssPrivateKey::load(parameters, parameters.height(), bytes)
+    }
+
+    fn load(parameters: XmssParameters, height: u32, bytes: &[u8]) -> Result<Self, XmssError> {
+        let next = read_u32(bytes, 4);
+        if u64::from(next) > 1 << height {
+            return Err(XmssError::InvalidState);
+        }
+
+        let mut key = XmssPrivateKey::with_height(
+            parameters,
+            height,
+            read_hash(bytes, 8),
+            read_hash(bytes, 8 + N),
+            read_hash(bytes, 8 + 3 * N),
+        );
+        if key.root() != read_hash(bytes, 8 + 2 * N) {
+            return Err(XmssError::InvalidState);
+        }
+
+        key.next = next;
+        Ok(key)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XmssPublicKey {
+    parameters: XmssParameters,
+    height: u32,
+    root: Hash,
+    public_seed: Hash,
+}
+
+impl XmssPublicKey {
+    /// `OID || root || SEED`, as in RFC 8391, section 4.1.7.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PUBLIC_KEY_LENGTH);
+        bytes.extend_from_slice(&self.parameters.oid().to_be_bytes());
+        bytes.extend_from_slice(&self.root);
+        bytes.extend_from_slice(&self.public_seed);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, XmssError> {
+        if bytes.len() != PUBLIC_KEY_LENGTH {
+            return Err(XmssError::InvalidLength);
+        }
+
+        let parameters = XmssParameters::from_oid(read_u32(bytes, 0))?;
+        Ok(XmssPublicKey {
+            parameters,
+            height: parameters.height(),
+            root: read_hash(bytes, 4),
+            public_seed: read_hash(bytes, 4 + N),
+        })
+    }
+
+    /// `XMSS_verify` from RFC 8391, section 4.1.10.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), XmssError> {
+        if signature.len() != signature_length(self.height) {
+            return Err(XmssError::InvalidLength);
+        }
+
+        let index = read_u32(signature, 0);
+        if u64::from(index) >= 1 << self.height {
+            return Err(XmssError::InvalidSignature);
+        }
+
+        let randomizer = read_hash(signature, 4);
+        let digest = message_digest(&randomizer, &self.root, index, message);
+
+        let ots_start = 4 + N;
+        let ots_signature = (0..wots::CHAINS)
+            .map(|chain| read_hash(signature, ots_start + chain * N))
+            .collect::<Vec<_>>();
+        let public_key = wots::public_key_from_signature(
+            &digest,
+            &ots_signature,
+            &self.public_seed,
+            &ots_address(index),
+        );
+
+        let mut node = ltree(public_key, &self.public_seed, &mut ltree_address(index));
+        let mut address = hash_tree_address();
+        let path_start = ots_start + wots::CHAINS * N;
+        for level in 0..self.height {
+            let sibling = read_hash(signature, path_start + level as usize * N);
+            address.set_tree_height(level);
+            address.set_tree_index(index >> (level + 1));
+            node = if (index >> level) & 1 == 0 {
+                rand_hash(&node, &sibling, &self.public_seed, &mut address)
+            } else {
+                rand_hash(&sibling, &node, &self.public_seed, &mut address)
+            };
+        }
+
+        if node != self.root {
+            return Err(XmssError::InvalidSignature);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hex_to_bytes;
+
+    // a full Sha2_10_256 tree takes a million hashes or so to build, which is
+    // too slow for a test -- these keys have the same structure, with a tree
+    // only four levels tall
+    const HEIGHT: u32 = 4;
+
+    // a real Sha2_10_256 key, made from the seeds 00..1f (secret), 20..3f
+    // (PRF) and 40..5f (public), and its signature on MESSAGE with one-time
+    // key 677. This crate made the signature and it has not been checked
+    // against the XMSS reference implementation; the only independent check
+    // is a separate Python transcription of RFC 8391's XMSS_verify, which
+    // accepts it
+    const MESSAGE: &[u8] = b"The powers not delegated to the United States by the Constitution, \
+        nor prohibited by it to the States, are reserved to the States respectively, or to the \
+        people.\n";
+    const PUBLIC_KEY: &str = concat!(
+        "000000019d898033e37af48e6a116f8b15651cc26773467007ad19375d38c23c",
+        "690c3483404142434445464748494a4b4c4d4e4f505152535455565758595a5b",
+        "5c5d5e5f",
+    );
+    const SIGNATURE: &str = concat!(
+        "000002a503c95ce92ee202236e1feb3f4e7b2eb335dc748b17d363698e925609",
+        "9da9a87600f1e8cddc6f1e0db205628b16bde31d6e3b5cea194db17239137d54",
+        "8bfc30ce8fe5ec4854cbe778ddff9b6e1226ad0082a5a1f0ec29368b47f8b3a7",
+        "4f6720a57cedf46f3000048067688677ff3b0e52ee36ddb3cc22cde6421b9f5c",
+        "6b031a59de5220e172e255d013809e5963ac44d3482e55fa6c9aefca6f22cc51",
+        "ecc8e58d44e01fafab62e38a8642345257824dcc35233dff2d68d1bd2a589413",
+        "94232bbe5f786455b2dc5973c51f2551d5e9abd88ebeaf4651c9f68b4f638e48",
+        "081db2c7ec4e5b60296f267848ca25113d0dbebe2d7f05ef586c436b31f3a0dd",
+        "2987e86e76f247ebf77f918a2e37ea6c45536cc10edf5f638af826ad0f32c5cd",
+        "dd8abdb2832b1b7a7f8f0b17221d54f43ee0f7f7449630bcc1ffdc0fb5f65b7e",
+        "fdb383f649e1d12cc28561848ddc6cb25315a5515c7db8ccef82066e487b8eb0",
+        "1714e5c3a54068210d0738b2234acbbc432ae090074f172d353934a551181feb",
+        "84c25b9593e5fc8a21cdca330eccea0a9421382a6382fdbf41faabe760186a7c",
+        "ac12f5c1864588205a4f9e45b3d95d8cb543e9794d13116a1266d16d1a81a7ee",
+        "ea29996d531357602c184864dc032a3d16978e842b09b6e80946b077224a335e",
+        "689c95ba9e9b3a39fa8dad12933a25d1c310111c93707acd390292c9cd5d06c2",
+        "968ca7333961ddb302148eba83af37638317114de4263de9f85e0401a24c7618",
+        "19fe799e2e31d67b5baa7801c663ccc803945d64e675b4f1843bffa00c2b7d06",
+        "57a2a9588a320b10851a3b2d7c973fbb0b7390193d89d5dc0121058f08f7e42d",
+        "8a4722a124c51b3cbeb76acba0d9542797ccf21d3d5c94ec49ad22160bda1c0d",
+        "517b0dfbd7f33b4e0726b232947ea5b7de9dc4f164d60c2f7b4a79d3a3b00ada",
+        "b16dd38124854a3ec2a101329628b064b175d04eee6b1764b4b957c8d8bfaf1b",
+        "ee53b6e2500ac06ad67cf0c8524fd38b7f600b346d7ed570bd2fcdd14b57d282",
+        "4f6e829f2a4815e0e7afc75e980f9231191b5512a8df192a25c56db10f2cc3a4",
+        "bbad21f939fed364387e3c88e1aa63c2f54251174c3a874077594b9557ed398e",
+        "7892574ee2ce209ae75618d75ad28cdd4dfdb3992568625ae6707949290214cf",
+        "30a53c7b78ce85e506f5e0aa8fd831952877c5b67491e4d103d145ffbcb0520b",
+        "506a38affd4d6f6c02d49b49401612988317ae3cc3e22351ec7270ef3abe7c64",
+        "0605660a6b5ef095c8c51affb5f6cc5ba8c5c644211e25b5582593b69ef27f2d",
+        "c78ae7210c643b13a0d7a7d5cbb69ce27dfee25746295c6fdd61c5bb0d892e4f",
+        "58730341a34df750c05eeb2b98360af78f7a6b2f46ef61634b7bd77875f1d1ff",
+        "1d7ac79bcd2de579e30112080b10ecc62c302df8564b186cbc12e3c0cd42aad1",
+        "b2faf23e98887c0401ef130a58de99eeb68a8e020718ec8dc70f320fd7ba2a33",
+        "5ec801e35fb4210c0ef520f4b7e982a53189311c2c6a9d450b7b67ea7d4b857a",
+        "d81e34bfa465bfdded31a83e52520e5a4fd9548f4e9d6215bc5330f753323fc9",
+        "ba03d50a67e8b19b294e9663f739e9b90d69f0e8854da4974f36cd394a2522ea",
+        "4786172f4fb00144b2fdeaca32f73fd9ddbe4b7ab9cb6868198a0d84f31bcd27",
+        "3954329c9967acd209ab35a00d5e524830e03a60d8a28405cfe2b7c6e6134a5f",
+        "76fa0ec37f547621d3df8be0082f8e6d44e9f9e3e2a628702c89aafb281f1e30",
+        "4436ffba80243466b7b9f3f1fe18c72af97fc53eb36e0ac37cf4ff3bf2d2e7c9",
+        "76c7eb47a40708ea9d320b31b69a6c7e54db3b3c22d269ac5f56bfe7ff4057f8",
+        "4e3609988672a077cefcfefb5b1c583b7e6cd4b9a6f44c9af9293373aecb5c46",
+        "3292ecba968557b3e519d0f2acb9cdf0caba545e8a075e20c9c7fa30a351db43",
+        "612971eaa770b4f8ceaeb42f34cbad9a47eb72f8f479610f65d70110f03e182d",
+        "3457029c88ec5165d4c4a1a811937eb0e1fc6e6d753eda374ec189c556a63ab4",
+        "6de81f64584cbddcc7622211fd573da5c42338d2381aa14b49006fe4fbe4fa1b",
+        "03164219b2f37fc31d8ab33835da927a2646bb265f0c4d6109d4751315d7c49c",
+        "bc19587f1b67ac22efd9bebe7b0dd57d25900c2b19c886ab0eeec000c8254d62",
+        "0d1637d0c4a0bd3728bb4597733d4dcbcffaf4e2297a425a46986b2c00df1070",
+        "af8d675cf13cc26dc54864bbe94690cb506ac5f3c405db7da290ed280d7859eb",
+        "2295b1497f6b42b669f94817a8e217a14fa0f2253170cc725eb454299b1b8b8b",
+        "0279905bd65d46bcd18b6a98ee2d7f34b9f0d36341b0ed8a20518821e13a7f06",
+        "95b71690d48893374d744309c1984f72ae6c4f4fb3a5124a59578780ecc4c4c6",
+        "f57d7a523a6be798f0a036e287516b9878eb763f17140c44d6eaf2d6a69477a9",
+        "2b81961733d7b9d33297db302eefbc44624888ee807a9f07081160b7a35479f9",
+        "d73ea5d54c3f90567e7ebd25e7e911e0d3e61785a09261006ea586a44e16992a",
+        "3e8906b2bd75673a355263d04d3c25c17127b31c2aa455a24ebcd00b18611fda",
+        "cf02544f33642bd0995e545572f0ccf55dcc46d0edd8150d7cd9a8581650c9a5",
+        "0be442b90600b36c8b21e5867f8e2f29ba5c4d52f7e65e8095dc79b76325b080",
+        "95aee21a8e2de0580d6028508eedd8ec189971aed2b6729dfe0e15640b8a67ed",
+        "bb90775464d0a5a17a810b620296126886918ee77a3f5d2a9e4489b7d83ae80e",
+        "45125f8c2b3ca2d09d641864ddf70c1534cf8a00d6ca2389d9e42427a69901cc",
+        "4ccb65eb3b00722ff4b725ffb994e5561dc1739be293a547d4f5f8b6703e8f99",
+        "4aed0d3d25fa316d23f4e5a71079343c3019252084b8d38faba21d3172c9cdd0",
+        "3c548714cb2353c62d54b78a6e6e937a3e91b1f4b4f02a7aee108d08c64e5a65",
+        "12f5de89ccb999deb368f0c7ded2799a10dc6ae7e376ed11854301b950fc0d87",
+        "5e167134a3a00c21ba6fb31ee18c97f3b5d1a9e456480f1852c716d77068079a",
+        "d3e27a2531658fe0d36622a5ba5e3b4c18b1d57dfe95dbece03358f48444300f",
+        "ae852519cfcd5bd0d29cb39f22b2b976476f4071bd51d57b7916597711cb9397",
+        "6a02b3082c1f1bd55f7b8135df8bdc77c6a9d133946ed20ff6888eb910e1e5bf",
+        "7d9555b45e3b19ae974bd3714302d4de45d191f5ef9f284dcbedac43053cab99",
+        "2f4d204b9953fb8ae484c1e8e213e3ede74dcb42dc229873bba48a1381f0207b",
+        "05760d90500f0aa470e994b02b6bd8f18e77a86f6c97d765ac0c638423bf8101",
+        "249a6c829e0347f24535aad9efb66322cf291373fc6791d1a44c1956892609dd",
+        "0efaad729171970517ce50b1c842d334ff6a484852bd78dfb0afd2e0064daf37",
+        "fee512b40ffd2bc7c43a67dec6e1ad22d1fd1e58b0ade813d0a91d341c9ebddb",
+        "419ab5b1ab1bea0f4e96e008a40d36c8a6bd049bf265f1b3df85686b53c623b6",
+        "40e3175bcb84959b6d1e46955e18bc5cf27d5fe13f72589a395e1ee01eb9983d",
+        "5ce3e04b",
+    );
+
+    fn counting_seed(start: u8) -> Hash {
+        let mut seed = [0u8; N];
+        for (offset, byte) in seed.iter_mut().enumerate() {
+            *byte = start + offset as u8;
+        }
+        seed
+    }
+
+    fn small_key() -> XmssPrivateKey {
+        XmssPrivateKey::with_height(
+            XmssParameters::Sha2_10_256,
+            HEIGHT,
+            [1u8; 32],
+            [2u8; 32],
+            [3u8; 32],
+        )
+    }
+
+    #[test]
+    fn test_xmss() {
+        let mut private_key = small_key();
+        let public_key = private_key.public_key();
+
+        let first = private_key.sign(b"firmware manifest v1").unwrap();
+        let second = private_key.sign(b"firmware manifest v2").unwrap();
+        assert_eq!(4 + 32 + 67 * 32 + 4 * 32, first.len());
+        assert_eq!(Ok(()), public_key.verify(b"firmware manifest v1", &first));
+        assert_eq!(Ok(()), public_key.verify(b"firmware manifest v2", &second));
+        assert_eq!(
+            Err(XmssError::InvalidSignature),
+            public_key.verify(b"firmware manifest v1", &second)
+        );
+
+        // every byte of the signature counts, the path included
+        for position in [3, 10, 500, first.len() - 1].iter() {
+            let mut tampered = first.clone();
+            tampered[*position] ^= 1;
+            assert!(public_key
+                .verify(b"firmware manifest v1", &tampered)
+                .is_err());
+        }
+
+        // a full-size signature is 2500 bytes, as RFC 8391 says
+        assert_eq!(2500, signature_length(XmssParameters::Sha2_10_256.height()));
+
+        let bytes = public_key.to_bytes();
+        assert_eq!(68, bytes.len());
+        assert_eq!(
+            Ok(XmssParameters::Sha2_10_256),
+            XmssPublicKey::from_bytes(&bytes).map(|key| key.parameters)
+        );
+    }
+
+    #[test]
+    fn test_xmss_state() {
+        let mut private_key = small_key();
+        let public_key = private_key.public_key();
+        private_key.sign(b"one").unwrap();
+
+        let saved = private_key.to_bytes();
+        assert_eq!(136, saved.len());
+        let mut restored =
+            XmssPrivateKey::load(XmssParameters::Sha2_10_256, HEIGHT, &saved).unwrap();
+        assert_eq!(15, restored.remaining());
+
+        let signature = restored.sign(b"two").unwrap();
+        assert_eq!(1, read_u32(&signature, 0));
+        assert_eq!(Ok(()), public_key.verify(b"two", &signature));
+
+        while restored.remaining() > 0 {
+            restored.sign(b"more").unwrap();
+        }
+        assert_eq!(Err(XmssError::Exhausted), restored.sign(b"one too many"));
+
+        let mut corrupted = saved.clone();
+        corrupted[20] ^= 1;
+        assert_eq!(
+            Some(XmssError::InvalidState),
+            XmssPrivateKey::load(XmssParameters::Sha2_10_256, HEIGHT, &corrupted).err()
+        );
+        assert_eq!(
+            Some(XmssError::UnknownOid(0)),
+            XmssPrivateKey::from_bytes(&[0u8; 136]).err()
+        );
+    }
+
+    #[test]
+    fn test_sha2_10_256() {
+        let public_key = XmssPublicKey::from_bytes(&hex_to_bytes(PUBLIC_KEY).unwrap()).unwrap();
+        let signature = hex_to_bytes(SIGNATURE).unwrap();
+        assert_eq!(2500, signature.len());
+        assert_eq!(Ok(()), public_key.verify(MESSAGE, &signature));
+        assert_eq!(
+            Err(XmssError::InvalidSignature),
+            public_key.verify(b"The powers not delegated", &signature)
+        );
+
+        let mut moved = signature.clone();
+        moved[3] ^= 1;
+        assert_eq!(
+            Err(XmssError::InvalidSignature),
+            public_key.verify(MESSAGE, &moved)
+        );
+
+        // building the tree is too slow here, but the randomizer and the
+        // WOTS+ signature only need the seeds
+        let index = read_u32(&signature, 0);
+        assert_eq!(677, index);
+        let randomizer = keyed_hash(PADDING_PRF, &counting_seed(0x20), &[&index_bytes(index)]);
+        assert_eq!(randomizer, read_hash(&signature, 4));
+
+        let digest = message_digest(&randomizer, &public_key.root, index, MESSAGE);
+        let ots_signature = wots::sign(
+            &digest,
+            &counting_seed(0x00),
+            &counting_seed(0x40),
+            &ots_address(index),
+        );
+        assert_eq!(
+            ots_signature.concat(),
+            signature[4 + N..4 + N + wots::CHAINS * N]
+        );
+    }
+
+}